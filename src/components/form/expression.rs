@@ -24,15 +24,23 @@
 use leptos::*;
 
 use crate::{
-    components::icon::{IconArrowRightCircle, IconPlus, IconVariable, IconXMark},
+    components::icon::{
+        IconArrowRightCircle, IconBeaker, IconCodeBracket, IconPlus, IconVariable, IconXMark,
+    },
     core::{
-        form::{ExpressionError, FormErrorType},
-        schema::Validator,
+        expr::{
+            completion::{match_brackets, signature_at, word_start, Completion, CompletionKind},
+            Variable,
+        },
+        form::{Expression, ExpressionError, FormError, FormErrorType, FormValue},
+        schema::{ExpressionValidator, Validator},
     },
 };
 
 use super::FormElement;
 
+type SandboxResult = Option<Result<(Option<usize>, String), FormError>>;
+
 #[component]
 pub fn InputExpression(
     element: FormElement,
@@ -48,12 +56,12 @@ pub fn InputExpression(
                 (
                     idx,
                     expr.clone(),
-                    error.as_ref().and_then(|e| match e.id {
+                    error.and_then(|e| match e.id {
                         FormErrorType::Expression(ExpressionError::If(pos)) if pos == idx => {
-                            Some(ExpressionError::If(e.error.clone()))
+                            Some(ExpressionError::If(e.clone()))
                         }
                         FormErrorType::Expression(ExpressionError::Then(pos)) if pos == idx => {
-                            Some(ExpressionError::Then(e.error.clone()))
+                            Some(ExpressionError::Then(e.clone()))
                         }
                         _ => None,
                     }),
@@ -74,13 +82,12 @@ pub fn InputExpression(
             .data
             .get()
             .error(element.id)
-            .as_ref()
             .and_then(|e| match e.id {
-                FormErrorType::Expression(ExpressionError::Else) => Some(e.error.clone()),
+                FormErrorType::Expression(ExpressionError::Else) => Some(e.clone()),
                 _ => None,
             })
     });
-    let disable_add = element
+    let checks = element
         .data
         .get_untracked()
         .schema
@@ -89,10 +96,69 @@ pub fn InputExpression(
         .unwrap()
         .checks
         .default
+        .clone();
+    let disable_add = checks.as_ref().map_or(false, |checks| {
+        checks.validators.contains(&Validator::MaxItems(1))
+    });
+    let validator = checks
         .as_ref()
-        .map_or(false, |checks| {
-            checks.validators.contains(&Validator::MaxItems(1))
-        });
+        .and_then(|checks| {
+            checks.validators.iter().find_map(|v| match v {
+                Validator::IsValidExpression(v) => Some(*v),
+                _ => None,
+            })
+        })
+        .unwrap_or_default();
+
+    // Input being edited, along with its text and cursor position
+    let editing = create_rw_signal(None::<(ExpressionError<usize>, String, usize)>);
+    let track_cursor = move |target: ExpressionError<usize>, ev: &web_sys::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(ev);
+        let cursor = input.selection_start().ok().flatten().unwrap_or_default() as usize;
+        editing.set(Some((target, input.value(), cursor)));
+    };
+    let complete = Callback::new(move |completion: Completion| {
+        if let Some((target, text, cursor)) = editing.get_untracked() {
+            let start = word_start(&text, cursor);
+            let mut value = text.chars().take(start).collect::<String>();
+            value.push_str(completion.name);
+            if completion.kind == CompletionKind::Function {
+                value.push('(');
+            }
+            value.extend(text.chars().skip(cursor));
+
+            element.data.update(|data| match target {
+                ExpressionError::If(idx) => data.expr_update_if(element.id, idx, value),
+                ExpressionError::Then(idx) => data.expr_update_then(element.id, idx, value),
+                ExpressionError::Else => data.expr_update_else(element.id, value),
+            });
+            editing.set(None);
+        }
+    });
+
+    // Sandbox for testing the expression against sample values
+    let show_sandbox = create_rw_signal(false);
+    let samples = create_rw_signal(vec![String::new(); validator.variables.len()]);
+    let sandbox_result = create_memo(move |_| {
+        if !show_sandbox.get() {
+            return None;
+        }
+        let expr = match element.data.get().values.get(element.id) {
+            Some(FormValue::Expression(expr)) => expr.clone(),
+            Some(FormValue::Value(value)) => Expression {
+                else_: value.clone(),
+                ..Default::default()
+            },
+            _ => Expression::default(),
+        };
+        let variables = samples
+            .get()
+            .iter()
+            .map(|value| Variable::from_sample(value))
+            .collect::<Vec<_>>();
+
+        Some(expr.eval(&validator, &variables))
+    });
 
     view! {
         <div class="space-y-3">
@@ -105,15 +171,27 @@ pub fn InputExpression(
 
                 children=move |(idx, if_then, error)| {
                     let (is_if_err, is_then_err, error) = match error {
-                        Some(ExpressionError::If(err)) => (true, false, err.into()),
-                        Some(ExpressionError::Then(err)) => (false, true, err.into()),
+                        Some(ExpressionError::If(err)) => {
+                            (true, false, Some((if_then.if_.clone(), err)))
+                        }
+                        Some(ExpressionError::Then(err)) => {
+                            (false, true, Some((if_then.then_.clone(), err)))
+                        }
                         _ => (false, false, None),
                     };
                     let ok_class = "py-2 px-3 pe-11 block w-full border-gray-200 shadow-sm -mt-px -ms-px first:rounded-t-lg last:rounded-b-lg sm:first:rounded-s-lg sm:mt-0 sm:first:ms-0 sm:first:rounded-se-none sm:last:rounded-es-none sm:last:rounded-e-lg text-sm relative focus:z-10 focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600";
                     let err_class = "py-2 px-3 pe-11 block w-full border-red-500 shadow-sm -mt-px -ms-px first:rounded-t-lg last:rounded-b-lg sm:first:rounded-s-lg sm:mt-0 sm:first:ms-0 sm:first:rounded-se-none sm:last:rounded-es-none sm:last:rounded-e-lg text-sm relative focus:z-10 focus:border-red-500 focus:ring-red-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600";
                     view! {
                         <div class="space-y-3">
-                            <div class="relative">
+                            <div class=move || {
+                                if matches!(
+                                    sandbox_result.get(), Some(Ok((Some(pos), _))) if pos == idx
+                                ) {
+                                    "relative rounded-lg ring-2 ring-teal-500"
+                                } else {
+                                    "relative"
+                                }
+                            }>
                                 <div class="sm:flex rounded-lg shadow-sm">
                                     <input
                                         type="text"
@@ -134,6 +212,11 @@ pub fn InputExpression(
                                                     );
                                                 });
                                         }
+
+                                        on:input=move |ev| track_cursor(ExpressionError::If(idx), &ev)
+                                        on:keyup=move |ev| track_cursor(ExpressionError::If(idx), &ev)
+                                        on:click=move |ev| track_cursor(ExpressionError::If(idx), &ev)
+                                        on:blur=move |_| editing.set(None)
                                     />
 
                                     <span class="py-2 px-3 inline-flex items-center min-w-fit w-full border border-gray-200 bg-gray-50 text-sm text-gray-500 -mt-px -ms-px first:rounded-t-lg last:rounded-b-lg sm:w-auto sm:first:rounded-s-lg sm:mt-0 sm:first:ms-0 sm:first:rounded-se-none sm:last:rounded-es-none sm:last:rounded-e-lg dark:bg-gray-700 dark:border-gray-700 dark:text-gray-400">
@@ -159,6 +242,11 @@ pub fn InputExpression(
                                                     );
                                                 });
                                         }
+
+                                        on:input=move |ev| track_cursor(ExpressionError::Then(idx), &ev)
+                                        on:keyup=move |ev| track_cursor(ExpressionError::Then(idx), &ev)
+                                        on:click=move |ev| track_cursor(ExpressionError::Then(idx), &ev)
+                                        on:blur=move |_| editing.set(None)
                                     />
 
                                     <button
@@ -178,10 +266,19 @@ pub fn InputExpression(
                                 </div>
 
                             </div>
-                            {error
-                                .map(|error| {
-                                    view! { <p class="text-xs text-red-600 mt-2">{error}</p> }
-                                })}
+                            <ExpressionAssist
+                                target=ExpressionError::If(idx)
+                                editing=editing
+                                validator=validator
+                                on_complete=complete
+                            />
+                            <ExpressionAssist
+                                target=ExpressionError::Then(idx)
+                                editing=editing
+                                validator=validator
+                                on_complete=complete
+                            />
+                            {error.map(|(source, error)| expression_error(&source, error))}
 
                         </div>
                     }
@@ -210,28 +307,62 @@ pub fn InputExpression(
                                     data.expr_update_else(element.id, event_target_value(&ev));
                                 });
                         }
+
+                        on:input=move |ev| track_cursor(ExpressionError::Else, &ev)
+                        on:keyup=move |ev| track_cursor(ExpressionError::Else, &ev)
+                        on:click=move |ev| track_cursor(ExpressionError::Else, &ev)
+                        on:blur=move |_| editing.set(None)
                     />
 
                     <div class="absolute inset-y-0 end-0 flex items-center pointer-events-none z-20 pe-4">
                         <IconVariable attr:class="flex-shrink-0 size-4 text-gray-400"/>
                     </div>
                 </div>
+                <ExpressionAssist
+                    target=ExpressionError::Else
+                    editing=editing
+                    validator=validator
+                    on_complete=complete
+                />
 
                 {move || {
-                    else_err
-                        .get()
-                        .map(|error| {
-                            view! { <p class="text-xs text-red-600 mt-2">{error}</p> }
-                        })
+                    else_err.get().map(|error| expression_error(&else_value.get(), error))
                 }}
 
             </div>
         </div>
 
-        <p class="mt-3 text-end" class:hidden=disable_add>
+        <div class="mt-3 flex justify-end gap-x-2">
             <button
                 type="button"
                 class="py-1.5 px-2 inline-flex items-center gap-x-1 text-xs font-medium rounded-full border border-dashed border-gray-200 bg-white text-gray-800 hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-gray-800 dark:border-gray-700 dark:text-gray-300 dark:hover:bg-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                on:click=move |_| {
+                    element
+                        .data
+                        .update(|data| {
+                            data.expr_format(element.id);
+                        });
+                }
+            >
+
+                <IconCodeBracket attr:class="flex-shrink-0 size-3.5"/>
+                Format
+            </button>
+            <button
+                type="button"
+                class="py-1.5 px-2 inline-flex items-center gap-x-1 text-xs font-medium rounded-full border border-dashed border-gray-200 bg-white text-gray-800 hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-gray-800 dark:border-gray-700 dark:text-gray-300 dark:hover:bg-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                on:click=move |_| {
+                    show_sandbox.update(|show| *show = !*show);
+                }
+            >
+
+                <IconBeaker attr:class="flex-shrink-0 size-3.5"/>
+                Test
+            </button>
+            <button
+                type="button"
+                class="py-1.5 px-2 inline-flex items-center gap-x-1 text-xs font-medium rounded-full border border-dashed border-gray-200 bg-white text-gray-800 hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-gray-800 dark:border-gray-700 dark:text-gray-300 dark:hover:bg-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                class:hidden=disable_add
                 on:click=move |_| {
                     if if_thens
                         .get()
@@ -250,6 +381,254 @@ pub fn InputExpression(
                 <IconPlus attr:class="flex-shrink-0 size-3.5"/>
                 Add Condition
             </button>
-        </p>
+        </div>
+
+        <Show when=move || show_sandbox.get()>
+            <ExpressionSandbox validator=validator samples=samples result=sandbox_result/>
+        </Show>
+    }
+}
+
+#[component]
+fn ExpressionSandbox(
+    validator: ExpressionValidator,
+    samples: RwSignal<Vec<String>>,
+    result: Memo<SandboxResult>,
+) -> impl IntoView {
+    let inputs = validator
+        .variables
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            view! {
+                <div class="sm:flex rounded-lg shadow-sm">
+                    <span class="py-2 px-3 inline-flex items-center min-w-fit w-full sm:w-40 border border-gray-200 bg-gray-50 text-sm text-gray-500 -mt-px -ms-px first:rounded-t-lg sm:first:rounded-s-lg sm:mt-0 sm:first:ms-0 sm:first:rounded-se-none dark:bg-gray-700 dark:border-gray-700 dark:text-gray-400">
+                        {*name}
+                    </span>
+                    <input
+                        type="text"
+                        class="py-2 px-3 block w-full border-gray-200 shadow-sm -mt-px -ms-px last:rounded-b-lg sm:mt-0 sm:last:rounded-es-none sm:last:rounded-e-lg text-sm relative focus:z-10 focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                        placeholder="Sample value"
+                        prop:value=move || samples.get().get(idx).cloned().unwrap_or_default()
+                        on:input=move |ev| {
+                            samples
+                                .update(|samples| {
+                                    if let Some(sample) = samples.get_mut(idx) {
+                                        *sample = event_target_value(&ev);
+                                    }
+                                });
+                        }
+                    />
+
+                </div>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <div class="mt-3 p-4 space-y-3 bg-gray-50 border border-gray-200 rounded-lg dark:bg-slate-800 dark:border-gray-700">
+            <p class="text-xs text-gray-500">
+                "Enter sample values for the variables below. Use [a, b] for lists."
+            </p>
+            {inputs}
+            {move || match result.get() {
+                Some(Ok((branch, value))) => {
+                    let branch = match branch {
+                        Some(idx) => format!("Condition #{} matched", idx + 1),
+                        None => "No condition matched, using default".to_string(),
+                    };
+                    view! {
+                        <p class="text-sm text-teal-800 dark:text-teal-500">
+                            {branch} ": " <span class="font-mono font-semibold">{value}</span>
+                        </p>
+                    }
+                        .into_view()
+                }
+                Some(Err(err)) => {
+                    let location = match err.id {
+                        FormErrorType::Expression(ExpressionError::If(idx)) => {
+                            format!("Condition #{}", idx + 1)
+                        }
+                        FormErrorType::Expression(ExpressionError::Then(idx)) => {
+                            format!("Result #{}", idx + 1)
+                        }
+                        _ => "Default".to_string(),
+                    };
+                    view! {
+                        <p class="text-sm text-red-600">{location} ": " {err.error}</p>
+                    }
+                        .into_view()
+                }
+                None => ().into_view(),
+            }}
+
+        </div>
+    }
+}
+
+#[component]
+fn ExpressionAssist(
+    target: ExpressionError<usize>,
+    editing: RwSignal<Option<(ExpressionError<usize>, String, usize)>>,
+    validator: ExpressionValidator,
+    on_complete: Callback<Completion, ()>,
+) -> impl IntoView {
+    let state = create_memo(move |_| {
+        editing
+            .get()
+            .filter(|(editing_target, _, _)| *editing_target == target)
+            .map(|(_, text, cursor)| (text, cursor))
+    });
+
+    move || {
+        let (text, cursor) = state.get()?;
+        let prefix = text
+            .chars()
+            .skip(word_start(&text, cursor))
+            .take(cursor - word_start(&text, cursor))
+            .collect::<String>();
+        let completions = validator
+            .completions(&prefix)
+            .into_iter()
+            .take(8)
+            .map(|completion| {
+                let (label, kind) = match completion.kind {
+                    CompletionKind::Variable => (completion.name.to_string(), "variable"),
+                    CompletionKind::Constant => (completion.name.to_string(), "constant"),
+                    CompletionKind::Function => (
+                        format!(
+                            "{}({})",
+                            completion.name,
+                            vec!["_"; completion.num_args as usize].join(", ")
+                        ),
+                        "function",
+                    ),
+                };
+                view! {
+                    <button
+                        type="button"
+                        class="py-1 px-2 inline-flex items-center gap-x-1 text-xs font-mono rounded-md border border-gray-200 bg-white text-gray-800 hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-300 dark:hover:bg-gray-800"
+                        on:mousedown=move |ev| {
+                            ev.prevent_default();
+                            on_complete.call(completion);
+                        }
+                    >
+
+                        {label}
+                        <span class="font-sans text-gray-400">{kind}</span>
+                    </button>
+                }
+            })
+            .collect_view();
+
+        // Signature of the function call under the cursor
+        let signature = signature_at(&text, cursor).map(|signature| {
+            let args = (0..signature.num_args)
+                .map(|arg| {
+                    let class = if arg == signature.arg {
+                        "font-semibold text-blue-600 dark:text-blue-500"
+                    } else {
+                        ""
+                    };
+                    let sep = if arg + 1 < signature.num_args { ", " } else { "" };
+                    view! {
+                        <span class=class>{format!("arg{}", arg + 1)}</span>
+                        {sep}
+                    }
+                })
+                .collect_view();
+            view! {
+                <p class="text-xs font-mono text-gray-600 dark:text-gray-400">
+                    {signature.name} "(" {args} ")"
+                    <span class="font-sans text-gray-400">
+                        {format!(
+                            " expects {} argument{}",
+                            signature.num_args,
+                            if signature.num_args == 1 { "" } else { "s" },
+                        )}
+
+                    </span>
+                </p>
+            }
+        });
+
+        // Highlight the bracket pair next to the cursor and any unmatched ones
+        let brackets = match_brackets(&text);
+        let pair = brackets.pairs.iter().find(|(open, close)| {
+            [*open, *close]
+                .iter()
+                .any(|pos| *pos == cursor || *pos + 1 == cursor)
+        });
+        let brackets = (pair.is_some() || !brackets.unmatched.is_empty()).then(|| {
+            let chars = text
+                .chars()
+                .enumerate()
+                .map(|(pos, ch)| {
+                    let class = if brackets.unmatched.contains(&pos) {
+                        "font-bold text-red-600"
+                    } else if pair.map_or(false, |(open, close)| *open == pos || *close == pos) {
+                        "font-bold bg-blue-100 text-blue-800 dark:bg-blue-800/30 dark:text-blue-500"
+                    } else {
+                        ""
+                    };
+                    view! { <span class=class>{ch.to_string()}</span> }
+                })
+                .collect_view();
+            view! {
+                <p class="text-xs font-mono text-gray-600 whitespace-pre-wrap break-all dark:text-gray-400">
+                    {chars}
+                </p>
+            }
+        });
+
+        Some(view! {
+            <div class="space-y-1">
+                <div class="flex flex-wrap gap-1">{completions}</div>
+                {signature}
+                {brackets}
+            </div>
+        })
+    }
+}
+
+fn expression_error(source: &str, error: FormError) -> impl IntoView {
+    // Underline the token where the error was detected
+    let location = error.offset.map(|offset| {
+        let before = source.chars().take(offset).collect::<String>();
+        let mut rest = source.chars().skip(offset).peekable();
+        let mut token = String::new();
+        match rest.next() {
+            Some(ch) if ch.is_alphanumeric() || ch == '_' || ch == '$' => {
+                token.push(ch);
+                while let Some(ch) = rest.next_if(|ch| ch.is_alphanumeric() || *ch == '_') {
+                    token.push(ch);
+                }
+            }
+            Some(ch @ ('\'' | '"')) => {
+                token.push(ch);
+                for next_ch in rest.by_ref() {
+                    token.push(next_ch);
+                    if next_ch == ch {
+                        break;
+                    }
+                }
+            }
+            Some(ch) => token.push(ch),
+            None => token.push(' '),
+        }
+        let after = rest.collect::<String>();
+
+        view! {
+            <p class="mt-1 font-mono text-xs text-gray-600 whitespace-pre-wrap break-all dark:text-gray-400">
+                {before}
+                <span class="underline decoration-wavy decoration-red-500">{token}</span>
+                {after}
+            </p>
+        }
+    });
+
+    view! {
+        <p class="text-xs text-red-600 mt-2">{error.error}</p>
+        {location}
     }
 }
@@ -41,6 +41,13 @@ pub struct Footer {
     children: Children,
 }
 
+#[slot]
+pub struct Filters {
+    #[prop(optional, into)]
+    hide: MaybeSignal<bool>,
+    children: Children,
+}
+
 #[component]
 pub fn ListTable(
     #[prop(optional, into)] title: MaybeSignal<String>,
@@ -48,7 +55,20 @@ pub fn ListTable(
     children: Children,
     toolbar: Toolbar,
     footer: Footer,
+    #[prop(optional)] filters: Option<Filters>,
 ) -> impl IntoView {
+    let filters = filters.map(|filters| {
+        let hide = filters.hide;
+        view! {
+            <div
+                class="px-6 py-3 flex flex-wrap items-center gap-3 border-b border-gray-200 dark:border-gray-700"
+                class:hidden=move || hide.get()
+            >
+                {(filters.children)()}
+            </div>
+        }
+    });
+
     view! {
         <div class="flex flex-col">
             <div class="-m-1.5 overflow-x-auto">
@@ -81,6 +101,8 @@ pub fn ListTable(
 
                         </div>
 
+                        {filters}
+
                         <table class="min-w-full divide-y divide-gray-200 dark:divide-gray-700">
                             {children()}
                         </table>
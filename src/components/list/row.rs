@@ -25,6 +25,8 @@ use std::collections::HashSet;
 
 use leptos::*;
 
+pub const HIGHLIGHT_ROW_CLASS: &str = "bg-yellow-50 dark:bg-yellow-800/10";
+
 #[component]
 pub fn SelectItem(item_id: String) -> impl IntoView {
     let selected = use_context::<RwSignal<HashSet<String>>>().unwrap();
@@ -24,7 +24,10 @@
 use leptos::*;
 use web_sys::wasm_bindgen::JsCast;
 
-use crate::components::Color;
+use crate::{
+    components::{icon::IconClock, Color},
+    core::polling::{Poller, POLLING_INTERVALS},
+};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ButtonIcon {
@@ -110,3 +113,142 @@ pub fn SearchBox(
         </div>
     }
 }
+
+#[component]
+pub fn FilterInput(
+    #[prop(into)] placeholder: String,
+    #[prop(into)] value: MaybeSignal<Option<String>>,
+    #[prop(into)] on_change: Callback<String, ()>,
+    #[prop(optional)] input_type: Option<&'static str>,
+) -> impl IntoView {
+    let value_ = value.clone();
+    view! {
+        <input
+            type=input_type.unwrap_or("text")
+            class=concat!(
+                "py-2 px-3 block w-full sm:w-48 border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 ",
+                "disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 ",
+                "dark:text-gray-400 dark:focus:ring-gray-600",
+            )
+
+            placeholder=placeholder
+            prop:value=move || value_.get().unwrap_or_default()
+            on:change=move |ev| {
+                let filter = event_target_value(&ev);
+                if filter != value.get().unwrap_or_default() {
+                    on_change.call(filter);
+                }
+            }
+        />
+    }
+}
+
+#[component]
+pub fn FilterSelect(
+    #[prop(into)] value: MaybeSignal<Option<String>>,
+    options: &'static [(&'static str, &'static str)],
+    #[prop(into)] on_change: Callback<String, ()>,
+) -> impl IntoView {
+    view! {
+        <select
+            class="py-2 px-3 pe-9 block w-full sm:w-auto border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+            on:change=move |ev| {
+                on_change.call(event_target_value(&ev));
+            }
+        >
+
+            {options
+                .iter()
+                .map(|(id, label)| {
+                    let value = value.clone();
+                    view! {
+                        <option
+                            value=*id
+                            selected=move || value.get().as_deref().unwrap_or_default() == *id
+                        >
+                            {*label}
+                        </option>
+                    }
+                })
+                .collect_view()}
+
+        </select>
+    }
+}
+
+#[component]
+pub fn FilterToggles(
+    #[prop(into)] selected: MaybeSignal<Vec<String>>,
+    options: &'static [(&'static str, &'static str)],
+    #[prop(into)] on_toggle: Callback<String, ()>,
+) -> impl IntoView {
+    view! {
+        <div class="inline-flex rounded-lg shadow-sm">
+
+            {options
+                .iter()
+                .map(|(id, label)| {
+                    let selected = selected.clone();
+                    let is_selected = move || selected.get().iter().any(|s| s == id);
+                    view! {
+                        <button
+                            type="button"
+                            class=move || {
+                                if is_selected() {
+                                    "py-2 px-3 -ms-px first:rounded-s-lg first:ms-0 last:rounded-e-lg text-sm font-medium border border-blue-600 bg-blue-600 text-white dark:border-blue-500"
+                                } else {
+                                    "py-2 px-3 -ms-px first:rounded-s-lg first:ms-0 last:rounded-e-lg text-sm font-medium border border-gray-200 bg-white text-gray-800 hover:bg-gray-50 dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800"
+                                }
+                            }
+
+                            on:click=move |_| on_toggle.call(id.to_string())
+                        >
+                            {*label}
+                        </button>
+                    }
+                })
+                .collect_view()}
+
+        </div>
+    }
+}
+
+#[component]
+pub fn PollingControl(poller: Poller) -> impl IntoView {
+    view! {
+        <div class="inline-flex items-center gap-x-2">
+            <Show when=move || poller.errors.get() != 0>
+                <span class="text-xs text-red-500 whitespace-nowrap">
+
+                    {move || {
+                        poller
+                            .delay()
+                            .map(|delay| format!("Refresh failed, retrying in {}s", delay.as_secs()))
+                            .unwrap_or_else(|| "Refresh failed".to_string())
+                    }}
+
+                </span>
+            </Show>
+            <FilterSelect
+                value=Signal::derive(move || Some(poller.interval.get().to_string()))
+                options=POLLING_INTERVALS
+                on_change=Callback::new(move |value: String| {
+                    poller.set_interval(value.parse().unwrap_or_default());
+                })
+            />
+
+            <Show when=move || poller.interval.get() != 0>
+                <ToolbarButton
+                    text=Signal::derive(move || {
+                        if poller.paused.get() { "Resume" } else { "Pause" }.to_string()
+                    })
+
+                    color=Color::Gray
+                    on_click=Callback::new(move |_| poller.toggle_pause())
+                >
+                    <IconClock/>
+                </ToolbarButton>
+            </Show>
+        </div>
+    }
+}
@@ -37,6 +37,7 @@ pub struct Modal {
     pub button_text: String,
     pub danger: bool,
     pub on_confirm: Arc<dyn Fn()>,
+    pub content: Option<Arc<dyn Fn() -> View>>,
 }
 
 pub fn init_modals() {
@@ -83,7 +84,14 @@ pub fn Modal() -> impl IntoView {
                     >
 
                         <div
-                            class="hs-overlay-open:mt-7 hs-overlay-open:opacity-100 hs-overlay-open:duration-500 mt-0 opacity-0 ease-out transition-all sm:max-w-lg sm:w-full m-3 sm:mx-auto min-h-[calc(100%-3.5rem)] flex items-center"
+                            class=move || {
+                                if modal.get().content.is_some() {
+                                    "hs-overlay-open:mt-7 hs-overlay-open:opacity-100 hs-overlay-open:duration-500 mt-0 opacity-0 ease-out transition-all sm:max-w-3xl sm:w-full m-3 sm:mx-auto min-h-[calc(100%-3.5rem)] flex items-center"
+                                } else {
+                                    "hs-overlay-open:mt-7 hs-overlay-open:opacity-100 hs-overlay-open:duration-500 mt-0 opacity-0 ease-out transition-all sm:max-w-lg sm:w-full m-3 sm:mx-auto min-h-[calc(100%-3.5rem)] flex items-center"
+                                }
+                            }
+
                             node_ref=modal_target
                         >
                             <div class="w-full flex flex-col bg-white border shadow-sm rounded-xl dark:bg-gray-800 dark:border-gray-700 dark:shadow-slate-700/[.7]">
@@ -111,6 +119,7 @@ pub fn Modal() -> impl IntoView {
                                     <p class="text-gray-800 dark:text-gray-400">
                                         {move || { modal.get().message }}
                                     </p>
+                                    {move || modal.get().content.map(|content| content())}
                                 </div>
                                 <div class="flex justify-end items-center gap-x-2 py-3 px-4 border-t dark:border-gray-700">
                                     <button
@@ -170,6 +179,7 @@ impl Modal {
             button_text: "Confirm".to_string(),
             danger: false,
             on_confirm: Arc::new(|| {}),
+            content: None,
         }
     }
     pub fn with_message(mut self, message: impl Into<String>) -> Self {
@@ -177,6 +187,11 @@ impl Modal {
         self
     }
 
+    pub fn with_content(mut self, content: impl Fn() -> View + 'static) -> Self {
+        self.content = Some(Arc::new(content));
+        self
+    }
+
     pub fn with_button(mut self, button_text: impl Into<String>) -> Self {
         self.button_text = button_text.into();
         self
@@ -204,6 +219,7 @@ impl Default for Modal {
             button_text: "Confirm".to_string(),
             danger: false,
             on_confirm: Arc::new(|| {}),
+            content: None,
         }
     }
 }
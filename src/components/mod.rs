@@ -28,6 +28,7 @@ pub mod icon;
 pub mod layout;
 pub mod list;
 pub mod messages;
+pub mod progress;
 pub mod report;
 pub mod skeleton;
 
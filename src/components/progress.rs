@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use leptos::*;
+
+use super::{form::button::Button, Color};
+
+#[component]
+pub fn ProgressCard(
+    #[prop(into)] title: String,
+    #[prop(into)] summary: String,
+    percent: u64,
+    is_running: bool,
+    #[prop(into)] on_stop: Callback<(), ()>,
+    #[prop(into)] on_dismiss: Callback<(), ()>,
+) -> impl IntoView {
+    view! {
+        <div class="mb-4 p-4 bg-white border border-gray-200 rounded-xl shadow-sm dark:bg-slate-900 dark:border-gray-700">
+            <div class="flex items-center justify-between gap-x-4">
+                <div class="grow">
+                    <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">{title}</h3>
+                    <p class="text-sm text-gray-500">{summary}</p>
+                    <div
+                        class="mt-2 flex w-full h-1.5 bg-gray-200 rounded-full overflow-hidden dark:bg-gray-700"
+                        role="progressbar"
+                    >
+                        <div
+                            class="flex flex-col justify-center overflow-hidden bg-blue-600 transition duration-500"
+                            style=format!("width: {}%", percent.min(100))
+                        ></div>
+                    </div>
+                </div>
+                {if is_running {
+                    view! {
+                        <Button
+                            text="Stop"
+                            color=Color::Red
+                            on_click=Callback::new(move |_| on_stop.call(()))
+                        />
+                    }
+                } else {
+                    view! {
+                        <Button
+                            text="Dismiss"
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| on_dismiss.call(()))
+                        />
+                    }
+                }}
+
+            </div>
+        </div>
+    }
+}
@@ -65,6 +65,18 @@ pub fn ReportSection(
     }
 }
 
+#[component]
+pub fn StatsSection(#[prop(into)] title: String, children: Children) -> impl IntoView {
+    view! {
+        <div class="gap-2 sm:gap-4 py-8 first:pt-0 last:pb-0 border-t first:border-transparent border-gray-200 dark:border-gray-700 dark:first:border-transparent">
+            <div class="sm:col-span-12 pb-4">
+                <h2 class="text-lg font-semibold text-gray-800 dark:text-gray-200">{title}</h2>
+            </div>
+            {children()}
+        </div>
+    }
+}
+
 #[component]
 pub fn ReportItem(
     #[prop(into)] label: String,
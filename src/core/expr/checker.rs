@@ -0,0 +1,358 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    core::schema::{ExpressionType, ExpressionValidator},
+    pages::config::schema::{V_LOCAL_PORT, V_PRIORITY, V_RECIPIENTS, V_REMOTE_PORT, V_TLS},
+};
+
+use super::{
+    BinaryOperator, CompileError, Constant, Expression, ExpressionItem, UnaryOperator, FUNCTIONS,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableType {
+    String,
+    Integer,
+    Float,
+    Array,
+    Any,
+}
+
+impl Expression {
+    pub fn check(
+        &self,
+        validator: &ExpressionValidator,
+        offsets: &[usize],
+    ) -> Result<VariableType, CompileError> {
+        let mut stack: Vec<VariableType> = Vec::new();
+
+        for (pos, item) in self.items.iter().enumerate() {
+            let offset = offsets.get(pos).copied().unwrap_or_default();
+            let error = |error: String| CompileError { offset, error };
+            let mut pop = || {
+                stack
+                    .pop()
+                    .ok_or_else(|| error("Missing operand".to_string()))
+            };
+
+            let typ = match item {
+                ExpressionItem::Variable(var) => validator
+                    .variables
+                    .get(*var as usize)
+                    .map_or(VariableType::Any, |name| variable_type(name)),
+                ExpressionItem::Capture(_) => VariableType::Any,
+                ExpressionItem::Constant(constant) => match constant {
+                    Constant::Integer(_) => VariableType::Integer,
+                    Constant::Float(_) => VariableType::Float,
+                    Constant::String(_) => VariableType::String,
+                },
+                ExpressionItem::UnaryOperator(op) => {
+                    let value = pop()?;
+                    match op {
+                        UnaryOperator::Not => VariableType::Integer,
+                        UnaryOperator::Minus if value.is_number() => value,
+                        UnaryOperator::Minus => {
+                            return Err(error(format!("Cannot negate {} value", value.name())));
+                        }
+                    }
+                }
+                ExpressionItem::BinaryOperator(op) => {
+                    let right = pop()?;
+                    let left = pop()?;
+                    match op {
+                        BinaryOperator::Add => match (left, right) {
+                            (VariableType::Array, _) => VariableType::Array,
+                            (VariableType::String, _) | (_, VariableType::String) => {
+                                VariableType::String
+                            }
+                            (VariableType::Integer, VariableType::Integer) => VariableType::Integer,
+                            (VariableType::Any, _) | (_, VariableType::Any) => VariableType::Any,
+                            (_, VariableType::Array) => {
+                                return Err(error("Cannot add an array to a number".to_string()));
+                            }
+                            _ => VariableType::Float,
+                        },
+                        BinaryOperator::Subtract
+                        | BinaryOperator::Multiply
+                        | BinaryOperator::Divide => {
+                            if let Some(typ) = [left, right].into_iter().find(|typ| {
+                                matches!(typ, VariableType::String | VariableType::Array)
+                            }) {
+                                return Err(error(format!(
+                                    "Arithmetic operators cannot be applied to {} value",
+                                    typ.name()
+                                )));
+                            }
+                            match (left, right) {
+                                (VariableType::Integer, VariableType::Integer) => {
+                                    VariableType::Integer
+                                }
+                                (VariableType::Any, _) | (_, VariableType::Any) => {
+                                    VariableType::Any
+                                }
+                                _ => VariableType::Float,
+                            }
+                        }
+                        BinaryOperator::And | BinaryOperator::Or | BinaryOperator::Xor => {
+                            VariableType::Integer
+                        }
+                        BinaryOperator::Eq
+                        | BinaryOperator::Ne
+                        | BinaryOperator::Lt
+                        | BinaryOperator::Le
+                        | BinaryOperator::Gt
+                        | BinaryOperator::Ge => {
+                            let is_ordering =
+                                !matches!(op, BinaryOperator::Eq | BinaryOperator::Ne);
+                            let is_mismatch = match (left, right) {
+                                (VariableType::Any, _) | (_, VariableType::Any) => false,
+                                (VariableType::Array, VariableType::Array) => is_ordering,
+                                (VariableType::Array, _) | (_, VariableType::Array) => true,
+                                (VariableType::String, other) | (other, VariableType::String) => {
+                                    other != VariableType::String
+                                }
+                                _ => false,
+                            };
+                            if is_mismatch {
+                                return Err(error(format!(
+                                    "Cannot compare {} value with {} value",
+                                    left.name(),
+                                    right.name()
+                                )));
+                            }
+                            VariableType::Integer
+                        }
+                    }
+                }
+                ExpressionItem::Function { id, num_args } => {
+                    for _ in 0..*num_args {
+                        pop()?;
+                    }
+                    FUNCTIONS
+                        .get(*id as usize)
+                        .map_or(VariableType::Any, |(name, _)| function_type(name))
+                }
+                ExpressionItem::Regex(_) => {
+                    pop()?;
+                    VariableType::Integer
+                }
+                ExpressionItem::ArrayAccess => {
+                    let index = pop()?;
+                    let array = pop()?;
+                    if matches!(index, VariableType::String | VariableType::Array) {
+                        return Err(error(format!(
+                            "Array index must be a number, found {} value",
+                            index.name()
+                        )));
+                    }
+                    match array {
+                        VariableType::String => VariableType::String,
+                        VariableType::Array | VariableType::Any => VariableType::Any,
+                        _ => {
+                            return Err(error(format!("Cannot index {} value", array.name())));
+                        }
+                    }
+                }
+                ExpressionItem::ArrayBuild(num_items) => {
+                    for _ in 0..*num_items {
+                        pop()?;
+                    }
+                    VariableType::Array
+                }
+                ExpressionItem::JmpIf { .. } => continue,
+            };
+
+            stack.push(typ);
+        }
+
+        Ok(stack.pop().unwrap_or(VariableType::Any))
+    }
+}
+
+impl VariableType {
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            VariableType::Integer | VariableType::Float | VariableType::Any
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariableType::String => "a string",
+            VariableType::Integer => "an integer",
+            VariableType::Float => "a float",
+            VariableType::Array => "an array",
+            VariableType::Any => "any",
+        }
+    }
+}
+
+impl ExpressionType {
+    pub fn accepts(&self, typ: VariableType) -> bool {
+        match (self, typ) {
+            (ExpressionType::Any, _) | (_, VariableType::Any) => true,
+            (ExpressionType::Boolean, typ) => typ.is_number(),
+            (ExpressionType::Integer | ExpressionType::Duration | ExpressionType::Size, typ) => {
+                matches!(typ, VariableType::Integer)
+            }
+            (ExpressionType::String, typ) => typ != VariableType::Array,
+            (ExpressionType::Array(_), VariableType::Array) => true,
+            (ExpressionType::Array(item), typ) => item.accepts(typ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExpressionType::Any => "any value",
+            ExpressionType::Boolean => "a boolean",
+            ExpressionType::Integer => "an integer",
+            ExpressionType::String => "a string",
+            ExpressionType::Duration => "a duration",
+            ExpressionType::Size => "a size",
+            ExpressionType::Array(ExpressionType::Duration) => "a list of durations",
+            ExpressionType::Array(ExpressionType::String) => "a list of strings",
+            ExpressionType::Array(_) => "a list",
+        }
+    }
+}
+
+fn variable_type(name: &str) -> VariableType {
+    match name {
+        V_REMOTE_PORT | V_LOCAL_PORT | V_PRIORITY | V_TLS => VariableType::Integer,
+        V_RECIPIENTS => VariableType::Array,
+        _ => VariableType::String,
+    }
+}
+
+fn function_type(name: &str) -> VariableType {
+    match name {
+        "count" | "len" | "count_spaces" | "count_uppercase" | "count_lowercase"
+        | "count_chars" | "counter_incr" | "counter_get" => VariableType::Integer,
+        "is_intersect"
+        | "is_email"
+        | "is_empty"
+        | "is_number"
+        | "is_ip_addr"
+        | "is_ipv4_addr"
+        | "is_ipv6_addr"
+        | "is_uppercase"
+        | "is_lowercase"
+        | "has_digits"
+        | "contains"
+        | "contains_ignore_case"
+        | "eq_ignore_case"
+        | "starts_with"
+        | "ends_with"
+        | "is_local_domain"
+        | "is_local_address"
+        | "key_exists"
+        | "key_set" => VariableType::Integer,
+        "sort" | "dedup" | "winnow" | "lines" | "split" | "rsplit" | "split_once"
+        | "rsplit_once" | "split_words" => VariableType::Array,
+        "email_part" | "ip_reverse_name" | "trim" | "trim_end" | "trim_start" | "to_lowercase"
+        | "to_uppercase" | "substring" | "strip_prefix" | "strip_suffix" => VariableType::String,
+        _ => VariableType::Any,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        expr::checker::VariableType,
+        schema::{ExpressionType, ExpressionValidator},
+    };
+
+    const VALIDATOR: ExpressionValidator = ExpressionValidator {
+        variables: &["rcpt", "remote_port", "recipients"],
+        constants: &["relaxed"],
+        returns: ExpressionType::Any,
+    };
+
+    fn check(expr: &str) -> Result<VariableType, String> {
+        let (expr, offsets) = VALIDATOR
+            .parser(expr)
+            .parse_with_offsets()
+            .unwrap_or_else(|err| panic!("failed to parse {expr:?}: {}", err.error));
+        expr.check(&VALIDATOR, &offsets).map_err(|err| err.error)
+    }
+
+    #[test]
+    fn infers_types() {
+        assert_eq!(check("remote_port + 1"), Ok(VariableType::Integer));
+        assert_eq!(check("remote_port * 1.5"), Ok(VariableType::Float));
+        assert_eq!(check("rcpt + '.bak'"), Ok(VariableType::String));
+        assert_eq!(check("len(rcpt) > 3"), Ok(VariableType::Integer));
+        assert_eq!(check("split(rcpt, '@')"), Ok(VariableType::Array));
+        assert_eq!(check("to_lowercase(rcpt)"), Ok(VariableType::String));
+        assert_eq!(check("recipients[0]"), Ok(VariableType::Any));
+        assert_eq!(check("rcpt[0]"), Ok(VariableType::String));
+    }
+
+    #[test]
+    fn rejects_mismatched_operands() {
+        assert_eq!(
+            check("rcpt > 10"),
+            Err("Cannot compare a string value with an integer value".to_string())
+        );
+        assert_eq!(
+            check("rcpt * 2"),
+            Err("Arithmetic operators cannot be applied to a string value".to_string())
+        );
+        assert_eq!(
+            check("-rcpt"),
+            Err("Cannot negate a string value".to_string())
+        );
+        assert_eq!(
+            check("remote_port[0]"),
+            Err("Cannot index an integer value".to_string())
+        );
+        assert_eq!(
+            check("recipients['a']"),
+            Err("Array index must be a number, found a string value".to_string())
+        );
+        assert_eq!(check("rcpt == 'john'"), Ok(VariableType::Integer));
+        assert_eq!(check("recipients == recipients"), Ok(VariableType::Integer));
+    }
+
+    #[test]
+    fn checks_return_type() {
+        let validator = VALIDATOR.returns(ExpressionType::Integer);
+        assert!(validator.check("remote_port + 1", true).is_ok());
+        assert!(validator.check("rcpt", false).is_ok());
+        let err = validator.check("rcpt", true).unwrap_err();
+        assert_eq!(err.error, "Expected an integer, found a string value");
+        assert_eq!(err.offset, 0);
+
+        let validator = VALIDATOR.returns(ExpressionType::Array(&ExpressionType::String));
+        assert!(validator.check("split(rcpt, ',')", true).is_ok());
+        assert!(validator.check("rcpt", true).is_ok());
+    }
+
+    #[test]
+    fn error_offset_in_characters() {
+        let err = VALIDATOR.check("'é' + rcpt * 2", false).unwrap_err();
+        assert_eq!(err.offset, 11);
+    }
+}
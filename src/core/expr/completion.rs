@@ -0,0 +1,342 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::core::schema::ExpressionValidator;
+
+use super::{tokenizer::Tokenizer, Token, FUNCTIONS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Variable,
+    Constant,
+    Function,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion {
+    pub name: &'static str,
+    pub kind: CompletionKind,
+    pub num_args: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub num_args: u32,
+    pub arg: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Brackets {
+    pub pairs: Vec<(usize, usize)>,
+    pub unmatched: Vec<usize>,
+}
+
+struct Frame {
+    function: Option<(String, u32)>,
+    arg: u32,
+}
+
+impl ExpressionValidator {
+    pub fn completions(&self, prefix: &str) -> Vec<Completion> {
+        if prefix.is_empty() {
+            return vec![];
+        }
+
+        self.variables
+            .iter()
+            .map(|name| Completion {
+                name,
+                kind: CompletionKind::Variable,
+                num_args: 0,
+            })
+            .chain(self.constants.iter().map(|name| Completion {
+                name,
+                kind: CompletionKind::Constant,
+                num_args: 0,
+            }))
+            .chain(
+                FUNCTIONS
+                    .iter()
+                    .map(|(name, num_args)| Completion {
+                        name,
+                        kind: CompletionKind::Function,
+                        num_args: *num_args,
+                    })
+                    .chain([Completion {
+                        name: "matches",
+                        kind: CompletionKind::Function,
+                        num_args: 2,
+                    }]),
+            )
+            .filter(|completion| completion.name.starts_with(prefix) && completion.name != prefix)
+            .collect()
+    }
+}
+
+// Offsets are in characters, not bytes
+pub fn signature_at(expr: &str, cursor: usize) -> Option<Signature> {
+    let expr = &expr[..byte_offset(expr, cursor)];
+    let mut tokenizer = Tokenizer::new(expr, |_| Ok(Token::Variable(0)));
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut function = None;
+
+    while let Ok(Some(token)) = tokenizer.next() {
+        match token {
+            Token::Function { name, num_args, .. } => {
+                function = Some((name.to_string(), num_args));
+                continue;
+            }
+            Token::Regex(_) => {
+                function = Some(("matches".to_string(), 2));
+                continue;
+            }
+            Token::OpenParen => {
+                // The regular expression was already consumed by the tokenizer
+                let arg = function
+                    .as_ref()
+                    .map_or(0, |(name, _)| (name == "matches") as u32);
+                stack.push(Frame {
+                    function: function.take(),
+                    arg,
+                });
+            }
+            Token::OpenBracket => stack.push(Frame {
+                function: None,
+                arg: 0,
+            }),
+            Token::CloseParen | Token::CloseBracket => {
+                stack.pop();
+            }
+            Token::Comma => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.arg += 1;
+                }
+            }
+            _ => {}
+        }
+        function = None;
+    }
+
+    stack.into_iter().rev().find_map(|frame| {
+        frame.function.map(|(name, num_args)| Signature {
+            name,
+            num_args,
+            arg: frame.arg,
+        })
+    })
+}
+
+pub fn match_brackets(expr: &str) -> Brackets {
+    let mut tokenizer = Tokenizer::new(expr, |_| Ok(Token::Variable(0)));
+    let mut brackets = Brackets::default();
+    let mut stack: Vec<(Token, usize)> = Vec::new();
+
+    loop {
+        match tokenizer.next() {
+            Ok(Some(token @ (Token::OpenParen | Token::OpenBracket))) => {
+                stack.push((token, tokenizer.offset()));
+            }
+            Ok(Some(token @ (Token::CloseParen | Token::CloseBracket))) => {
+                let expected = if token == Token::CloseParen {
+                    Token::OpenParen
+                } else {
+                    Token::OpenBracket
+                };
+                match stack.pop() {
+                    Some((open, offset)) if open == expected => {
+                        brackets.pairs.push((offset, tokenizer.offset()));
+                    }
+                    Some((_, offset)) => {
+                        brackets.unmatched.push(offset);
+                        brackets.unmatched.push(tokenizer.offset());
+                    }
+                    None => brackets.unmatched.push(tokenizer.offset()),
+                }
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(_) => {
+                // The tokenizer only fails on a closing parenthesis when none is open
+                if expr.as_bytes().get(tokenizer.offset()) == Some(&b')')
+                    && !stack.iter().any(|(token, _)| *token == Token::OpenParen)
+                {
+                    brackets.unmatched.push(tokenizer.offset());
+                }
+                break;
+            }
+        }
+    }
+    brackets
+        .unmatched
+        .extend(stack.into_iter().map(|(_, offset)| offset));
+
+    // Convert byte offsets to character offsets
+    let to_char = |offset: usize| expr.get(..offset).map_or(offset, |s| s.chars().count());
+    for (open, close) in brackets.pairs.iter_mut() {
+        *open = to_char(*open);
+        *close = to_char(*close);
+    }
+    for offset in brackets.unmatched.iter_mut() {
+        *offset = to_char(*offset);
+    }
+
+    brackets
+}
+
+pub fn word_start(expr: &str, cursor: usize) -> usize {
+    let word_len = expr
+        .chars()
+        .take(cursor)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .take_while(|ch| ch.is_alphanumeric() || *ch == '_')
+        .count();
+    cursor.saturating_sub(word_len)
+}
+
+fn byte_offset(expr: &str, offset: usize) -> usize {
+    expr.char_indices()
+        .nth(offset)
+        .map_or(expr.len(), |(pos, _)| pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::schema::{ExpressionType, ExpressionValidator};
+
+    use super::*;
+
+    const VALIDATOR: ExpressionValidator = ExpressionValidator {
+        variables: &["rcpt", "rcpt_domain", "sender"],
+        constants: &["relaxed", "strict"],
+        returns: ExpressionType::Any,
+    };
+
+    fn names(prefix: &str) -> Vec<&'static str> {
+        VALIDATOR
+            .completions(prefix)
+            .into_iter()
+            .map(|completion| completion.name)
+            .collect()
+    }
+
+    #[test]
+    fn completions() {
+        assert_eq!(names(""), Vec::<&str>::new());
+        assert_eq!(names("rcpt"), vec!["rcpt_domain"]);
+        assert_eq!(names("rel"), vec!["relaxed"]);
+        assert_eq!(
+            names("is_ip"),
+            vec!["is_ip_addr", "is_ipv4_addr", "is_ipv6_addr"]
+        );
+        assert_eq!(names("match"), vec!["matches"]);
+
+        let completion = VALIDATOR.completions("ends_").pop().unwrap();
+        assert_eq!(completion.kind, CompletionKind::Function);
+        assert_eq!(completion.num_args, 2);
+        assert_eq!(
+            VALIDATOR.completions("sen").pop().unwrap().kind,
+            CompletionKind::Variable
+        );
+    }
+
+    #[test]
+    fn signatures() {
+        let expr = "contains(rcpt, to_lowercase(sender)) && x";
+        assert_eq!(
+            signature_at(expr, 9),
+            Some(Signature {
+                name: "contains".to_string(),
+                num_args: 2,
+                arg: 0
+            })
+        );
+        assert_eq!(
+            signature_at(expr, 15),
+            Some(Signature {
+                name: "contains".to_string(),
+                num_args: 2,
+                arg: 1
+            })
+        );
+        assert_eq!(
+            signature_at(expr, 30),
+            Some(Signature {
+                name: "to_lowercase".to_string(),
+                num_args: 1,
+                arg: 0
+            })
+        );
+        assert_eq!(signature_at(expr, expr.len()), None);
+        assert_eq!(signature_at("[rcpt, ", 7), None);
+        assert_eq!(
+            signature_at("email_part([rcpt, sender][1], ", 31),
+            Some(Signature {
+                name: "email_part".to_string(),
+                num_args: 2,
+                arg: 1
+            })
+        );
+    }
+
+    #[test]
+    fn brackets() {
+        assert_eq!(
+            match_brackets("len(a[1]) + (b)"),
+            Brackets {
+                pairs: vec![(5, 7), (3, 8), (12, 14)],
+                unmatched: vec![],
+            }
+        );
+        assert_eq!(
+            match_brackets("(a + b"),
+            Brackets {
+                pairs: vec![],
+                unmatched: vec![0],
+            }
+        );
+        assert_eq!(
+            match_brackets("a + b)"),
+            Brackets {
+                pairs: vec![],
+                unmatched: vec![5],
+            }
+        );
+        assert_eq!(
+            match_brackets("'é' + (a)"),
+            Brackets {
+                pairs: vec![(6, 8)],
+                unmatched: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn word_boundaries() {
+        assert_eq!(word_start("len(rcpt_do", 11), 4);
+        assert_eq!(word_start("a + ", 4), 4);
+        assert_eq!(word_start("é + abc", 7), 4);
+    }
+}
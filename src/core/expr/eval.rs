@@ -0,0 +1,426 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{borrow::Cow, cmp::Ordering, fmt::Display};
+
+use super::{
+    functions::exec_function, BinaryOperator, Constant, Expression, ExpressionItem, UnaryOperator,
+    Variable,
+};
+
+pub trait ResolveVariable {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_>;
+}
+
+impl ResolveVariable for Vec<Variable<'_>> {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        self.get(variable as usize)
+            .map(|v| v.as_ref())
+            .unwrap_or_default()
+    }
+}
+
+impl Expression {
+    pub fn eval<'x>(
+        &'x self,
+        resolver: &'x impl ResolveVariable,
+        captures: &mut Vec<String>,
+    ) -> Result<Variable<'x>, String> {
+        let mut stack = Vec::new();
+        let mut pos = 0;
+
+        while let Some(item) = self.items.get(pos) {
+            match item {
+                ExpressionItem::Variable(var) => {
+                    stack.push(resolver.resolve_variable(*var));
+                }
+                ExpressionItem::Capture(capture) => {
+                    stack.push(Variable::String(Cow::Owned(
+                        captures.get(*capture as usize).cloned().unwrap_or_default(),
+                    )));
+                }
+                ExpressionItem::Constant(val) => {
+                    stack.push(Variable::from(val));
+                }
+                ExpressionItem::UnaryOperator(op) => {
+                    let value = stack.pop().ok_or_else(stack_underflow)?;
+                    stack.push(match op {
+                        UnaryOperator::Not => Variable::from(!value.to_bool()),
+                        UnaryOperator::Minus => match value.to_number() {
+                            Some(Variable::Integer(n)) => Variable::Integer(n.wrapping_neg()),
+                            Some(Variable::Float(n)) => Variable::Float(-n),
+                            _ => Variable::Integer(0),
+                        },
+                    });
+                }
+                ExpressionItem::BinaryOperator(op) => {
+                    let right = stack.pop().ok_or_else(stack_underflow)?;
+                    let left = stack.pop().ok_or_else(stack_underflow)?;
+                    stack.push(match op {
+                        BinaryOperator::Add
+                        | BinaryOperator::Subtract
+                        | BinaryOperator::Multiply
+                        | BinaryOperator::Divide => left.arithmetic(right, *op),
+                        BinaryOperator::And => Variable::from(left.to_bool() & right.to_bool()),
+                        BinaryOperator::Or => Variable::from(left.to_bool() | right.to_bool()),
+                        BinaryOperator::Xor => Variable::from(left.to_bool() ^ right.to_bool()),
+                        BinaryOperator::Eq => Variable::from(left.is_equal(&right)),
+                        BinaryOperator::Ne => Variable::from(!left.is_equal(&right)),
+                        BinaryOperator::Lt => {
+                            Variable::from(left.compare(&right) == Some(Ordering::Less))
+                        }
+                        BinaryOperator::Le => Variable::from(matches!(
+                            left.compare(&right),
+                            Some(Ordering::Less | Ordering::Equal)
+                        )),
+                        BinaryOperator::Gt => {
+                            Variable::from(left.compare(&right) == Some(Ordering::Greater))
+                        }
+                        BinaryOperator::Ge => Variable::from(matches!(
+                            left.compare(&right),
+                            Some(Ordering::Greater | Ordering::Equal)
+                        )),
+                    });
+                }
+                ExpressionItem::JmpIf { val, pos: jmp_pos } => {
+                    // Short-circuit, the value is left on the stack
+                    if stack.last().ok_or_else(stack_underflow)?.to_bool() == *val {
+                        pos += *jmp_pos as usize;
+                    }
+                }
+                ExpressionItem::Function { id, num_args } => {
+                    let num_args = *num_args as usize;
+                    if stack.len() < num_args {
+                        return Err(stack_underflow());
+                    }
+                    let args = stack.split_off(stack.len() - num_args);
+                    stack.push(exec_function(*id, args)?);
+                }
+                ExpressionItem::ArrayAccess => {
+                    let index = stack.pop().ok_or_else(stack_underflow)?;
+                    let array = stack.pop().ok_or_else(stack_underflow)?;
+                    let index = index.to_integer().unwrap_or_default();
+                    stack.push(match array {
+                        Variable::Array(mut items) => {
+                            if index >= 0 && (index as usize) < items.len() {
+                                items.swap_remove(index as usize)
+                            } else {
+                                Variable::default()
+                            }
+                        }
+                        Variable::String(value) if index >= 0 => value
+                            .chars()
+                            .nth(index as usize)
+                            .map(|ch| Variable::String(Cow::Owned(ch.to_string())))
+                            .unwrap_or_default(),
+                        _ => Variable::default(),
+                    });
+                }
+                ExpressionItem::ArrayBuild(num_items) => {
+                    let num_items = *num_items as usize;
+                    if stack.len() < num_items {
+                        return Err(stack_underflow());
+                    }
+                    let items = stack.split_off(stack.len() - num_items);
+                    stack.push(Variable::Array(items));
+                }
+                ExpressionItem::Regex(regex) => {
+                    let value = stack.pop().ok_or_else(stack_underflow)?;
+                    let value = value.to_str();
+                    captures.clear();
+
+                    stack.push(if let Some(found) = regex.captures(value.as_ref()) {
+                        captures.extend(
+                            found
+                                .iter()
+                                .map(|m| m.map(|m| m.as_str().to_string()).unwrap_or_default()),
+                        );
+                        Variable::from(true)
+                    } else {
+                        Variable::from(false)
+                    });
+                }
+            }
+
+            pos += 1;
+        }
+
+        match stack.len() {
+            0 => Ok(Variable::default()),
+            1 => Ok(stack.pop().unwrap()),
+            _ => Err("Invalid expression, too many values left on the stack".to_string()),
+        }
+    }
+}
+
+fn stack_underflow() -> String {
+    "Invalid expression, missing operand".to_string()
+}
+
+impl<'x> Variable<'x> {
+    pub fn to_bool(&self) -> bool {
+        match self {
+            Variable::String(value) => !value.is_empty(),
+            Variable::Integer(value) => *value != 0,
+            Variable::Float(value) => *value != 0.0,
+            Variable::Array(items) => !items.is_empty(),
+        }
+    }
+
+    pub fn to_str(&self) -> Cow<'_, str> {
+        match self {
+            Variable::String(value) => Cow::Borrowed(value.as_ref()),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
+
+    pub fn to_integer(&self) -> Option<i64> {
+        match self {
+            Variable::Integer(value) => Some(*value),
+            Variable::Float(value) => Some(*value as i64),
+            Variable::String(value) => value.trim().parse::<i64>().ok(),
+            Variable::Array(_) => None,
+        }
+    }
+
+    pub fn to_number(&self) -> Option<Variable<'static>> {
+        match self {
+            Variable::Integer(value) => Some(Variable::Integer(*value)),
+            Variable::Float(value) => Some(Variable::Float(*value)),
+            Variable::String(value) => {
+                let value = value.trim();
+                value
+                    .parse::<i64>()
+                    .map(Variable::Integer)
+                    .or_else(|_| value.parse::<f64>().map(Variable::Float))
+                    .ok()
+            }
+            Variable::Array(_) => None,
+        }
+    }
+
+    pub fn as_ref(&self) -> Variable<'_> {
+        match self {
+            Variable::String(value) => Variable::String(Cow::Borrowed(value.as_ref())),
+            Variable::Integer(value) => Variable::Integer(*value),
+            Variable::Float(value) => Variable::Float(*value),
+            Variable::Array(items) => Variable::Array(items.iter().map(|v| v.as_ref()).collect()),
+        }
+    }
+
+    pub fn into_owned(self) -> Variable<'static> {
+        match self {
+            Variable::String(value) => Variable::String(Cow::Owned(value.into_owned())),
+            Variable::Integer(value) => Variable::Integer(value),
+            Variable::Float(value) => Variable::Float(value),
+            Variable::Array(items) => {
+                Variable::Array(items.into_iter().map(|v| v.into_owned()).collect())
+            }
+        }
+    }
+
+    pub fn into_array(self) -> Vec<Variable<'x>> {
+        match self {
+            Variable::Array(items) => items,
+            Variable::String(value) if value.is_empty() => vec![],
+            value => vec![value],
+        }
+    }
+
+    fn arithmetic(self, other: Variable<'x>, op: BinaryOperator) -> Variable<'x> {
+        match (self, other) {
+            (Variable::Array(mut items), other) if op == BinaryOperator::Add => {
+                items.push(other);
+                Variable::Array(items)
+            }
+            (left @ Variable::String(_), right) | (left, right @ Variable::String(_))
+                if op == BinaryOperator::Add
+                    && (left.to_number().is_none() || right.to_number().is_none()) =>
+            {
+                Variable::String(Cow::Owned(format!("{left}{right}")))
+            }
+            (left, right) => match (left.to_number(), right.to_number()) {
+                (Some(Variable::Integer(a)), Some(Variable::Integer(b))) => {
+                    Variable::Integer(match op {
+                        BinaryOperator::Add => a.wrapping_add(b),
+                        BinaryOperator::Subtract => a.wrapping_sub(b),
+                        BinaryOperator::Multiply => a.wrapping_mul(b),
+                        _ => a.checked_div(b).unwrap_or_default(),
+                    })
+                }
+                (Some(a), Some(b)) => {
+                    let (a, b) = (a.to_float(), b.to_float());
+                    Variable::Float(match op {
+                        BinaryOperator::Add => a + b,
+                        BinaryOperator::Subtract => a - b,
+                        BinaryOperator::Multiply => a * b,
+                        _ if b != 0.0 => a / b,
+                        _ => 0.0,
+                    })
+                }
+                _ => Variable::default(),
+            },
+        }
+    }
+
+    fn to_float(&self) -> f64 {
+        match self {
+            Variable::Integer(value) => *value as f64,
+            Variable::Float(value) => *value,
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_equal(&self, other: &Variable<'_>) -> bool {
+        match (self, other) {
+            (Variable::Array(left), Variable::Array(right)) => {
+                left.len() == right.len() && left.iter().zip(right).all(|(l, r)| l.is_equal(r))
+            }
+            (Variable::Array(_), _) | (_, Variable::Array(_)) => false,
+            _ => self.compare(other) == Some(Ordering::Equal),
+        }
+    }
+
+    pub fn compare(&self, other: &Variable<'_>) -> Option<Ordering> {
+        match (self, other) {
+            (Variable::String(left), Variable::String(right)) => Some(left.cmp(right)),
+            (Variable::Integer(left), Variable::Integer(right)) => Some(left.cmp(right)),
+            (Variable::Array(_), _) | (_, Variable::Array(_)) => None,
+            (left, right) => match (left.to_number(), right.to_number()) {
+                (Some(Variable::Integer(left)), Some(Variable::Integer(right))) => {
+                    Some(left.cmp(&right))
+                }
+                (Some(left), Some(right)) => left.to_float().partial_cmp(&right.to_float()),
+                _ => Some(left.to_str().as_ref().cmp(right.to_str().as_ref())),
+            },
+        }
+    }
+}
+
+impl<'x> From<&'x Constant> for Variable<'x> {
+    fn from(value: &'x Constant) -> Self {
+        match value {
+            Constant::Integer(value) => Variable::Integer(*value),
+            Constant::Float(value) => Variable::Float(*value),
+            Constant::String(value) => Variable::String(Cow::Borrowed(value.as_str())),
+        }
+    }
+}
+
+impl Display for Variable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variable::String(value) => f.write_str(value),
+            Variable::Integer(value) => write!(f, "{value}"),
+            Variable::Float(value) => write!(f, "{value}"),
+            Variable::Array(items) => {
+                f.write_str("[")?;
+                for (pos, item) in items.iter().enumerate() {
+                    if pos > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let Variable::String(value) = item {
+                        write!(f, "{value:?}")?;
+                    } else {
+                        write!(f, "{item}")?;
+                    }
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        expr::Variable,
+        schema::{ExpressionType, ExpressionValidator},
+    };
+
+    const VALIDATOR: ExpressionValidator = ExpressionValidator {
+        variables: &["rcpt", "sender_domain", "size", "retry_num"],
+        constants: &["relaxed"],
+        returns: ExpressionType::Any,
+    };
+
+    fn eval(expr: &str, values: Vec<Variable<'static>>) -> String {
+        let expr = VALIDATOR
+            .parse(expr)
+            .unwrap_or_else(|err| panic!("failed to parse {expr:?}: {err}"));
+        expr.eval(&values, &mut Vec::new())
+            .unwrap_or_else(|err| panic!("failed to evaluate: {err}"))
+            .to_string()
+    }
+
+    fn values() -> Vec<Variable<'static>> {
+        vec![
+            Variable::from("john@example.org".to_string()),
+            Variable::from("example.org".to_string()),
+            Variable::from(1024),
+            Variable::from(3),
+        ]
+    }
+
+    #[test]
+    fn arithmetic_and_precedence() {
+        assert_eq!(eval("1 + 2 * 3", vec![]), "7");
+        assert_eq!(eval("(1 + 2) * 3", vec![]), "9");
+        assert_eq!(eval("10 / 4", vec![]), "2");
+        assert_eq!(eval("10.0 / 4", vec![]), "2.5");
+        assert_eq!(eval("-size + 24", values()), "-1000");
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(eval("size > 1000 && retry_num < 5", values()), "1");
+        assert_eq!(eval("size > 2000 || retry_num == 3", values()), "1");
+        assert_eq!(eval("!(retry_num >= 3)", values()), "0");
+        assert_eq!(eval("sender_domain == 'example.org'", values()), "1");
+        assert_eq!(eval("sender_domain != \"example.org\"", values()), "0");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("len(sender_domain)", values()), "11");
+        assert_eq!(eval("ends_with(rcpt, '@example.org')", values()), "1");
+        assert_eq!(
+            eval("to_uppercase(email_part(rcpt, 'local'))", values()),
+            "JOHN"
+        );
+        assert_eq!(eval("count(split(sender_domain, '.'))", values()), "2");
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(eval("[1, 2, 3][1]", vec![]), "2");
+        assert_eq!(eval("['a', 'b']", vec![]), "[\"a\", \"b\"]");
+        assert_eq!(eval("[1, 2][5]", vec![]), "0");
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        assert!(VALIDATOR.parse("unknown_var + 1").is_err());
+        assert!(VALIDATOR.parse("relaxed == 'relaxed'").is_ok());
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{cell::RefCell, iter::Peekable, vec::IntoIter};
+
+use super::{tokenizer::Tokenizer, BinaryOperator, Constant, Token, UnaryOperator};
+
+enum Node {
+    Atom(String),
+    Unary(UnaryOperator, Box<Node>),
+    Binary(BinaryOperator, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+    Array(Vec<Node>),
+    Index(Box<Node>, Box<Node>),
+}
+
+struct Formatter<'x> {
+    expr: &'x str,
+    names: Vec<String>,
+    tokens: Peekable<IntoIter<(Token, usize)>>,
+}
+
+pub fn format_expression(expr: &str) -> Result<String, String> {
+    let names = RefCell::new(Vec::new());
+    let mut tokenizer = Tokenizer::new(expr, |name| {
+        let mut names = names.borrow_mut();
+        names.push(name.to_string());
+        Ok(Token::Variable(names.len() as u32 - 1))
+    });
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next()? {
+        tokens.push((token, tokenizer.offset()));
+    }
+    drop(tokenizer);
+
+    let mut formatter = Formatter {
+        expr,
+        names: names.into_inner(),
+        tokens: tokens.into_iter().peekable(),
+    };
+    if formatter.tokens.peek().is_none() {
+        return Ok(String::new());
+    }
+    let node = formatter.parse_expr(0)?;
+    if formatter.tokens.next().is_some() {
+        return Err("Unexpected token after end of expression".to_string());
+    }
+
+    let mut result = String::with_capacity(expr.len());
+    node.print(&mut result);
+    Ok(result)
+}
+
+impl Formatter<'_> {
+    fn parse_expr(&mut self, min_precedence: i32) -> Result<Node, String> {
+        let mut left = self.parse_unary()?;
+
+        while let Some((Token::BinaryOperator(op), _)) = self.tokens.peek() {
+            let op = *op;
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.tokens.next();
+            let right = self.parse_expr(op.precedence() + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, String> {
+        if let Some((Token::UnaryOperator(op), _)) = self.tokens.peek() {
+            let op = *op;
+            self.tokens.next();
+            Ok(Node::Unary(op, Box::new(self.parse_unary()?)))
+        } else {
+            let mut node = self.parse_primary()?;
+            while matches!(self.tokens.peek(), Some((Token::OpenBracket, _))) {
+                self.tokens.next();
+                let index = self.parse_expr(0)?;
+                self.expect(Token::CloseBracket)?;
+                node = Node::Index(Box::new(node), Box::new(index));
+            }
+            Ok(node)
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, String> {
+        let (token, offset) = self
+            .tokens
+            .next()
+            .ok_or_else(|| "Unexpected end of expression".to_string())?;
+
+        match token {
+            Token::Variable(idx) => Ok(Node::Atom(self.names[idx as usize].clone())),
+            Token::Capture(idx) => Ok(Node::Atom(format!("${idx}"))),
+            Token::Constant(Constant::String(value)) => {
+                Ok(Node::Atom(quote_string(&value, self.raw_string(offset))))
+            }
+            Token::Constant(_) => Ok(Node::Atom(
+                self.expr[offset..]
+                    .chars()
+                    .take_while(|ch| ch.is_ascii_alphanumeric() || *ch == '.')
+                    .collect(),
+            )),
+            Token::OpenParen => {
+                let node = self.parse_expr(0)?;
+                self.expect(Token::CloseParen)?;
+                Ok(node)
+            }
+            Token::OpenBracket => Ok(Node::Array(self.parse_list(Token::CloseBracket)?)),
+            Token::Function { name, .. } => {
+                self.expect(Token::OpenParen)?;
+                Ok(Node::Call(
+                    name.to_string(),
+                    self.parse_list(Token::CloseParen)?,
+                ))
+            }
+            Token::Regex(regex) => {
+                self.expect(Token::OpenParen)?;
+                let raw = self.expr[offset..]
+                    .find(['\'', '"'])
+                    .and_then(|pos| self.raw_string(offset + pos));
+                let mut args = vec![Node::Atom(quote_string(regex.as_str(), raw))];
+                args.extend(self.parse_list(Token::CloseParen)?);
+                Ok(Node::Call("matches".to_string(), args))
+            }
+            _ => Err("Unexpected token".to_string()),
+        }
+    }
+
+    fn parse_list(&mut self, close: Token) -> Result<Vec<Node>, String> {
+        let mut items = Vec::new();
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |(token, _)| *token == close)
+        {
+            self.tokens.next();
+            return Ok(items);
+        }
+
+        loop {
+            items.push(self.parse_expr(0)?);
+            match self.tokens.next() {
+                Some((Token::Comma, _)) => {}
+                Some((token, _)) if token == close => return Ok(items),
+                _ => return Err("Expected comma or closing bracket".to_string()),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.tokens.next() {
+            Some((token, _)) if token == expected => Ok(()),
+            _ => Err(format!("Expected {:?}", expected)),
+        }
+    }
+
+    // Returns the string literal starting at `offset`, including its quotes
+    fn raw_string(&self, offset: usize) -> Option<&str> {
+        let bytes = self.expr.as_bytes();
+        let quote = *bytes.get(offset)?;
+        let mut last_ch = 0;
+        for (pos, &ch) in bytes.iter().enumerate().skip(offset + 1) {
+            if ch == quote && last_ch != b'\\' {
+                return self.expr.get(offset..=pos);
+            }
+            last_ch = ch;
+        }
+        None
+    }
+}
+
+impl Node {
+    fn print(&self, out: &mut String) {
+        match self {
+            Node::Atom(value) => out.push_str(value),
+            Node::Unary(op, node) => {
+                out.push(match op {
+                    UnaryOperator::Not => '!',
+                    UnaryOperator::Minus => '-',
+                });
+                node.print_wrapped(
+                    out,
+                    matches!(node.as_ref(), Node::Binary(..) | Node::Unary(..)),
+                );
+            }
+            Node::Binary(op, left, right) => {
+                let precedence = op.precedence();
+                left.print_wrapped(
+                    out,
+                    matches!(left.as_ref(), Node::Binary(op, ..) if op.precedence() < precedence),
+                );
+                out.push(' ');
+                out.push_str(op.as_str());
+                out.push(' ');
+                right.print_wrapped(
+                    out,
+                    matches!(right.as_ref(), Node::Binary(op, ..) if op.precedence() <= precedence),
+                );
+            }
+            Node::Call(name, args) => {
+                out.push_str(name);
+                out.push('(');
+                print_list(args, out);
+                out.push(')');
+            }
+            Node::Array(items) => {
+                out.push('[');
+                print_list(items, out);
+                out.push(']');
+            }
+            Node::Index(node, index) => {
+                node.print_wrapped(
+                    out,
+                    matches!(node.as_ref(), Node::Binary(..) | Node::Unary(..)),
+                );
+                out.push('[');
+                index.print(out);
+                out.push(']');
+            }
+        }
+    }
+
+    fn print_wrapped(&self, out: &mut String, wrap: bool) {
+        if wrap {
+            out.push('(');
+            self.print(out);
+            out.push(')');
+        } else {
+            self.print(out);
+        }
+    }
+}
+
+impl BinaryOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::Xor => "^",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+        }
+    }
+}
+
+fn print_list(items: &[Node], out: &mut String) {
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        item.print(out);
+    }
+}
+
+// Strings with escape sequences or both kinds of quotes are kept verbatim
+fn quote_string(value: &str, raw: Option<&str>) -> String {
+    let is_plain = !value.contains(|ch: char| ch == '\\' || ch.is_control());
+    if is_plain && !value.contains('\'') {
+        format!("'{value}'")
+    } else if is_plain && !value.contains('"') {
+        format!("\"{value}\"")
+    } else {
+        raw.map(|raw| raw.to_string())
+            .unwrap_or_else(|| format!("'{value}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_expression;
+
+    fn format(expr: &str) -> String {
+        format_expression(expr).unwrap_or_else(|err| panic!("failed to format {expr:?}: {err}"))
+    }
+
+    #[test]
+    fn normalizes_spacing() {
+        assert_eq!(format(""), "");
+        assert_eq!(format("a+b*c"), "a + b * c");
+        assert_eq!(format("  a   &&b||  c "), "a && b || c");
+        assert_eq!(format("len( rcpt )>3"), "len(rcpt) > 3");
+        assert_eq!(format("[ 1,2 ,3 ][0]"), "[1, 2, 3][0]");
+        assert_eq!(format("! is_empty(a)"), "!is_empty(a)");
+    }
+
+    #[test]
+    fn removes_redundant_parentheses() {
+        assert_eq!(format("(a + b)"), "a + b");
+        assert_eq!(format("a + (b * c)"), "a + b * c");
+        assert_eq!(format("(a + b) * c"), "(a + b) * c");
+        assert_eq!(format("a - (b - c)"), "a - (b - c)");
+        assert_eq!(format("(a - b) - c"), "a - b - c");
+        assert_eq!(format("!(a == b)"), "!(a == b)");
+        assert_eq!(format("-(a)"), "-a");
+    }
+
+    #[test]
+    fn quotes_strings() {
+        assert_eq!(format("rcpt == \"john\""), "rcpt == 'john'");
+        assert_eq!(format("rcpt == \"it's\""), "rcpt == \"it's\"");
+        assert_eq!(format("eq_ignore_case(a, 'B')"), "eq_ignore_case(a, 'B')");
+    }
+
+    #[test]
+    fn is_idempotent() {
+        for expr in [
+            "a+b*c",
+            "(a||b)&&!c",
+            "contains(split(rcpt,'@'),'x')[ 1 ]",
+            "matches('^a.*$', rcpt) && size>1024",
+        ] {
+            let formatted = format(expr);
+            assert_eq!(format(&formatted), formatted);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(format_expression("a +").is_err());
+        assert!(format_expression("(a + b").is_err());
+    }
+}
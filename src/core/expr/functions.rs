@@ -0,0 +1,287 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{borrow::Cow, cmp::Ordering, net::IpAddr};
+
+use super::{Variable, FUNCTIONS};
+
+pub(crate) fn exec_function(id: u32, args: Vec<Variable<'_>>) -> Result<Variable<'_>, String> {
+    let name = FUNCTIONS
+        .get(id as usize)
+        .map(|(name, _)| *name)
+        .ok_or_else(|| format!("Unknown function id {id}"))?;
+    let mut args = args.into_iter();
+    let mut arg = move || args.next().unwrap_or_default();
+
+    Ok(match name {
+        "count" => match arg() {
+            Variable::Array(items) => items.len().into(),
+            value => (value.to_bool() as i64).into(),
+        },
+        "sort" => {
+            let mut items = arg().into_array();
+            let reverse = arg().to_bool();
+            items.sort_by(|a, b| a.compare(b).unwrap_or(Ordering::Equal));
+            if reverse {
+                items.reverse();
+            }
+            Variable::Array(items)
+        }
+        "dedup" => {
+            let mut items: Vec<Variable<'_>> = Vec::new();
+            for item in arg().into_array() {
+                if !items.iter().any(|i| i.is_equal(&item)) {
+                    items.push(item);
+                }
+            }
+            Variable::Array(items)
+        }
+        "winnow" => Variable::Array(
+            arg()
+                .into_array()
+                .into_iter()
+                .filter(|item| item.to_bool())
+                .collect(),
+        ),
+        "is_intersect" => {
+            let left = arg().into_array();
+            let right = arg().into_array();
+            left.iter()
+                .any(|l| right.iter().any(|r| l.is_equal(r)))
+                .into()
+        }
+        "is_email" => {
+            let value = arg();
+            let value = value.to_str();
+            value
+                .rsplit_once('@')
+                .map_or(false, |(local, domain)| {
+                    !local.is_empty()
+                        && domain.contains('.')
+                        && !domain.starts_with('.')
+                        && !domain.ends_with('.')
+                        && !value.contains(char::is_whitespace)
+                })
+                .into()
+        }
+        "email_part" => {
+            let value = arg();
+            let part = arg();
+            let value = value.to_str();
+            value
+                .rsplit_once('@')
+                .map(|(local, domain)| match part.to_str().as_ref() {
+                    "local" => local.to_string(),
+                    "domain" => domain.to_string(),
+                    "host" => domain
+                        .split_once('.')
+                        .map_or("", |(host, _)| host)
+                        .to_string(),
+                    _ => String::new(),
+                })
+                .unwrap_or_default()
+                .into()
+        }
+        "is_empty" => match arg() {
+            Variable::String(value) => value.is_empty(),
+            Variable::Array(items) => items.is_empty(),
+            _ => false,
+        }
+        .into(),
+        "is_number" => matches!(arg(), Variable::Integer(_) | Variable::Float(_)).into(),
+        "is_ip_addr" => arg().to_str().parse::<IpAddr>().is_ok().into(),
+        "is_ipv4_addr" => matches!(arg().to_str().parse::<IpAddr>(), Ok(IpAddr::V4(_))).into(),
+        "is_ipv6_addr" => matches!(arg().to_str().parse::<IpAddr>(), Ok(IpAddr::V6(_))).into(),
+        "ip_reverse_name" => match arg().to_str().parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => ip
+                .octets()
+                .iter()
+                .rev()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(".")
+                .into(),
+            Ok(IpAddr::V6(ip)) => ip
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|o| [o & 0x0f, o >> 4])
+                .map(|n| format!("{n:x}"))
+                .collect::<Vec<_>>()
+                .join(".")
+                .into(),
+            Err(_) => Variable::default(),
+        },
+        "trim" => map_str(arg(), |s| s.trim().to_string()),
+        "trim_end" => map_str(arg(), |s| s.trim_end().to_string()),
+        "trim_start" => map_str(arg(), |s| s.trim_start().to_string()),
+        "len" => match arg() {
+            Variable::Array(items) => items.len(),
+            value => value.to_str().chars().count(),
+        }
+        .into(),
+        "to_lowercase" => map_str(arg(), |s| s.to_lowercase()),
+        "to_uppercase" => map_str(arg(), |s| s.to_uppercase()),
+        "is_uppercase" => {
+            let value = arg();
+            let value = value.to_str();
+            value
+                .chars()
+                .filter(|c| c.is_alphabetic())
+                .all(|c| c.is_uppercase())
+                .into()
+        }
+        "is_lowercase" => {
+            let value = arg();
+            let value = value.to_str();
+            value
+                .chars()
+                .filter(|c| c.is_alphabetic())
+                .all(|c| c.is_lowercase())
+                .into()
+        }
+        "has_digits" => arg().to_str().chars().any(|c| c.is_ascii_digit()).into(),
+        "count_spaces" => count_chars(arg(), char::is_whitespace),
+        "count_uppercase" => count_chars(arg(), char::is_uppercase),
+        "count_lowercase" => count_chars(arg(), char::is_lowercase),
+        "count_chars" => count_chars(arg(), |_| true),
+        "contains" => match arg() {
+            Variable::Array(items) => {
+                let needle = arg();
+                items.iter().any(|item| item.is_equal(&needle))
+            }
+            value => value.to_str().contains(arg().to_str().as_ref()),
+        }
+        .into(),
+        "contains_ignore_case" => match arg() {
+            Variable::Array(items) => {
+                let needle = arg().to_str().to_lowercase();
+                items
+                    .iter()
+                    .any(|item| item.to_str().to_lowercase() == needle)
+            }
+            value => value
+                .to_str()
+                .to_lowercase()
+                .contains(&arg().to_str().to_lowercase()),
+        }
+        .into(),
+        "eq_ignore_case" => {
+            let left = arg();
+            let right = arg();
+            left.to_str()
+                .to_lowercase()
+                .eq(&right.to_str().to_lowercase())
+                .into()
+        }
+        "starts_with" => {
+            let value = arg();
+            let prefix = arg();
+            value.to_str().starts_with(prefix.to_str().as_ref()).into()
+        }
+        "ends_with" => {
+            let value = arg();
+            let suffix = arg();
+            value.to_str().ends_with(suffix.to_str().as_ref()).into()
+        }
+        "lines" => split_str(arg(), |s| s.lines().map(|l| l.to_string()).collect()),
+        "substring" => {
+            let value = arg();
+            let from = arg().to_integer().unwrap_or_default().max(0) as usize;
+            let len = arg().to_integer().unwrap_or_default().max(0) as usize;
+            value
+                .to_str()
+                .chars()
+                .skip(from)
+                .take(len)
+                .collect::<String>()
+                .into()
+        }
+        "strip_prefix" => {
+            let value = arg();
+            let prefix = arg();
+            let value = value.to_str();
+            value
+                .strip_prefix(prefix.to_str().as_ref())
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+                .into()
+        }
+        "strip_suffix" => {
+            let value = arg();
+            let suffix = arg();
+            let value = value.to_str();
+            value
+                .strip_suffix(suffix.to_str().as_ref())
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+                .into()
+        }
+        "split" | "rsplit" | "split_once" | "rsplit_once" => {
+            let value = arg();
+            let separator = arg();
+            let (value, separator) = (value.to_str(), separator.to_str());
+            let items: Vec<String> = match name {
+                "split" => value.split(separator.as_ref()).map(Into::into).collect(),
+                "rsplit" => value.rsplit(separator.as_ref()).map(Into::into).collect(),
+                "split_once" => value
+                    .split_once(separator.as_ref())
+                    .map(|(a, b)| vec![a.to_string(), b.to_string()])
+                    .unwrap_or_default(),
+                _ => value
+                    .rsplit_once(separator.as_ref())
+                    .map(|(a, b)| vec![a.to_string(), b.to_string()])
+                    .unwrap_or_default(),
+            };
+            Variable::Array(items.into_iter().map(Variable::from).collect())
+        }
+        "split_words" => split_str(arg(), |s| {
+            s.split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_string())
+                .collect()
+        }),
+        _ => {
+            return Err(format!(
+                "Function {name:?} depends on server state and cannot be evaluated here"
+            ))
+        }
+    })
+}
+
+fn map_str(value: Variable<'_>, f: impl FnOnce(&str) -> String) -> Variable<'static> {
+    Variable::String(Cow::Owned(f(value.to_str().as_ref())))
+}
+
+fn split_str(value: Variable<'_>, f: impl FnOnce(&str) -> Vec<String>) -> Variable<'static> {
+    Variable::Array(
+        f(value.to_str().as_ref())
+            .into_iter()
+            .map(Variable::from)
+            .collect(),
+    )
+}
+
+fn count_chars(value: Variable<'_>, f: impl Fn(char) -> bool) -> Variable<'static> {
+    value.to_str().chars().filter(|c| f(*c)).count().into()
+}
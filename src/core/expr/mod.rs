@@ -25,6 +25,15 @@ use std::{borrow::Cow, time::Duration};
 
 use regex::Regex;
 
+use self::{parser::ExpressionParser, tokenizer::Tokenizer};
+
+use super::schema::ExpressionValidator;
+
+pub mod checker;
+pub mod completion;
+pub mod eval;
+pub mod formatter;
+pub mod functions;
 pub mod parser;
 pub mod tokenizer;
 
@@ -47,7 +56,13 @@ pub enum ExpressionItem {
     ArrayBuild(u32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub offset: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone)]
 pub enum Variable<'x> {
     String(Cow<'x, str>),
     Integer(i64),
@@ -335,6 +350,56 @@ impl PartialEq for Token {
 
 impl Eq for Token {}
 
+impl ExpressionValidator {
+    pub fn parse(&self, expr: &str) -> Result<Expression, String> {
+        self.parser(expr).parse().map_err(|err| err.error)
+    }
+
+    // Error offsets are in characters
+    pub fn check(&self, expr: &str, is_result: bool) -> Result<Expression, CompileError> {
+        self.parser(expr)
+            .parse_with_offsets()
+            .and_then(|(expression, offsets)| {
+                let typ = expression.check(self, &offsets)?;
+                if is_result && !expression.items.is_empty() && !self.returns.accepts(typ) {
+                    Err(CompileError {
+                        offset: offsets.last().copied().unwrap_or_default(),
+                        error: format!(
+                            "Expected {}, found {} value",
+                            self.returns.name(),
+                            typ.name()
+                        ),
+                    })
+                } else {
+                    Ok(expression)
+                }
+            })
+            .map_err(|err| CompileError {
+                offset: expr
+                    .get(..err.offset)
+                    .map_or(err.offset, |prefix| prefix.chars().count()),
+                error: err.error,
+            })
+    }
+
+    fn parser<'x>(
+        &'x self,
+        expr: &'x str,
+    ) -> ExpressionParser<'x, impl Fn(&str) -> Result<Token, String> + 'x> {
+        ExpressionParser::new(Tokenizer::new(expr, move |token| {
+            if let Some(idx) = self.variables.iter().position(|v| *v == token) {
+                Ok(Token::Variable(idx as u32))
+            } else if self.constants.contains(&token) {
+                Ok(Token::Constant(Constant::String(token.to_string())))
+            } else {
+                Duration::parse_value(token)
+                    .map(|d| Token::Constant(Constant::Integer(d.as_millis() as i64)))
+                    .ok_or_else(|| format!("Invalid variable or function name {:?}", token))
+            }
+        }))
+    }
+}
+
 pub trait ParseValue: Sized {
     fn parse_value(value: &str) -> Option<Self>;
 }
@@ -21,7 +21,9 @@
  * for more details.
 */
 
-use super::{tokenizer::Tokenizer, BinaryOperator, Expression, ExpressionItem, Token};
+use super::{
+    tokenizer::Tokenizer, BinaryOperator, CompileError, Expression, ExpressionItem, Token,
+};
 
 pub struct ExpressionParser<'x, F>
 where
@@ -29,8 +31,10 @@ where
 {
     pub(crate) tokenizer: Tokenizer<'x, F>,
     pub(crate) output: Vec<ExpressionItem>,
-    operator_stack: Vec<(Token, Option<usize>)>,
+    pub(crate) offsets: Vec<usize>,
+    operator_stack: Vec<(Token, Option<usize>, usize)>,
     arg_count: Vec<i32>,
+    error_offset: Option<usize>,
 }
 
 pub(crate) const ID_ARRAY_ACCESS: u32 = u32::MAX;
@@ -44,34 +48,52 @@ where
         Self {
             tokenizer,
             output: Vec::new(),
+            offsets: Vec::new(),
             operator_stack: Vec::new(),
             arg_count: Vec::new(),
+            error_offset: None,
         }
     }
 
-    pub fn parse(mut self) -> Result<Expression, String> {
+    pub fn parse(self) -> Result<Expression, CompileError> {
+        self.parse_with_offsets().map(|(expr, _)| expr)
+    }
+
+    pub fn parse_with_offsets(mut self) -> Result<(Expression, Vec<usize>), CompileError> {
+        match self.parse_tokens() {
+            Ok(expr) => Ok((expr, self.offsets)),
+            Err(error) => Err(CompileError {
+                offset: self.error_offset.unwrap_or_else(|| self.tokenizer.offset()),
+                error,
+            }),
+        }
+    }
+
+    fn parse_tokens(&mut self) -> Result<Expression, String> {
         let mut last_is_var_or_fnc = false;
 
         while let Some(token) = self.tokenizer.next()? {
             let mut is_var_or_fnc = false;
+            let offset = self.tokenizer.offset();
             match token {
                 Token::Variable(v) => {
                     self.inc_arg_count();
                     is_var_or_fnc = true;
-                    self.output.push(ExpressionItem::Variable(v))
+                    self.push(ExpressionItem::Variable(v), offset)
                 }
                 Token::Constant(c) => {
                     self.inc_arg_count();
-                    self.output.push(ExpressionItem::Constant(c))
+                    self.push(ExpressionItem::Constant(c), offset)
                 }
                 Token::Capture(c) => {
                     self.inc_arg_count();
-                    self.output.push(ExpressionItem::Capture(c))
+                    self.push(ExpressionItem::Capture(c), offset)
                 }
                 Token::UnaryOperator(uop) => {
-                    self.operator_stack.push((Token::UnaryOperator(uop), None))
+                    self.operator_stack
+                        .push((Token::UnaryOperator(uop), None, offset))
                 }
-                Token::OpenParen => self.operator_stack.push((token, None)),
+                Token::OpenParen => self.operator_stack.push((token, None, offset)),
                 Token::CloseParen | Token::CloseBracket => {
                     let expect_token = if matches!(token, Token::CloseParen) {
                         Token::OpenParen
@@ -80,24 +102,25 @@ where
                     };
                     loop {
                         match self.operator_stack.pop() {
-                            Some((t, _)) if t == expect_token => {
+                            Some((t, _, _)) if t == expect_token => {
                                 break;
                             }
-                            Some((Token::BinaryOperator(bop), jmp_pos)) => {
+                            Some((Token::BinaryOperator(bop), jmp_pos, op_offset)) => {
                                 self.update_jmp_pos(jmp_pos);
-                                self.output.push(ExpressionItem::BinaryOperator(bop))
+                                self.push(ExpressionItem::BinaryOperator(bop), op_offset)
                             }
-                            Some((Token::UnaryOperator(uop), _)) => {
-                                self.output.push(ExpressionItem::UnaryOperator(uop))
+                            Some((Token::UnaryOperator(uop), _, op_offset)) => {
+                                self.push(ExpressionItem::UnaryOperator(uop), op_offset)
                             }
                             _ => return Err("Mismatched parentheses".to_string()),
                         }
                     }
 
                     match self.operator_stack.last() {
-                        Some((Token::Function { id, num_args, name }, _)) => {
+                        Some((Token::Function { id, num_args, name }, _, fnc_offset)) => {
                             let got_args = self.arg_count.pop().unwrap();
                             if got_args != *num_args as i32 {
+                                self.error_offset = Some(*fnc_offset);
                                 return Err(if *id != u32::MAX {
                                     format!(
                                         "Expression function {:?} expected {} arguments, got {}",
@@ -116,16 +139,19 @@ where
                                     num_args: *num_args,
                                 },
                             };
+                            let fnc_offset = *fnc_offset;
 
                             self.operator_stack.pop();
-                            self.output.push(expr);
+                            self.push(expr, fnc_offset);
                         }
-                        Some((Token::Regex(regex), _)) => {
+                        Some((Token::Regex(regex), _, fnc_offset)) => {
                             if self.arg_count.pop().unwrap() != 1 {
+                                self.error_offset = Some(*fnc_offset);
                                 return Err("Expression function \"matches\" expected 2 arguments"
                                     .to_string());
                             }
-                            self.output.push(ExpressionItem::Regex(regex.clone()));
+                            let (regex, fnc_offset) = (regex.clone(), *fnc_offset);
+                            self.push(ExpressionItem::Regex(regex), fnc_offset);
                             self.operator_stack.pop();
                         }
                         _ => {}
@@ -135,23 +161,27 @@ where
                 }
                 Token::BinaryOperator(bop) => {
                     self.dec_arg_count();
-                    while let Some((top_token, prev_jmp_pos)) = self.operator_stack.last() {
+                    while let Some((top_token, prev_jmp_pos, op_offset)) =
+                        self.operator_stack.last()
+                    {
                         match top_token {
                             Token::BinaryOperator(top_bop) => {
                                 if bop.precedence() <= top_bop.precedence() {
                                     let top_bop = *top_bop;
                                     let jmp_pos = *prev_jmp_pos;
+                                    let op_offset = *op_offset;
                                     self.update_jmp_pos(jmp_pos);
                                     self.operator_stack.pop();
-                                    self.output.push(ExpressionItem::BinaryOperator(top_bop));
+                                    self.push(ExpressionItem::BinaryOperator(top_bop), op_offset);
                                 } else {
                                     break;
                                 }
                             }
                             Token::UnaryOperator(top_uop) => {
                                 let top_uop = *top_uop;
+                                let op_offset = *op_offset;
                                 self.operator_stack.pop();
-                                self.output.push(ExpressionItem::UnaryOperator(top_uop));
+                                self.push(ExpressionItem::UnaryOperator(top_uop), op_offset);
                             }
                             _ => break,
                         }
@@ -160,31 +190,30 @@ where
                     // Add jump instruction for short-circuiting
                     let jmp_pos = match bop {
                         BinaryOperator::And => {
-                            self.output
-                                .push(ExpressionItem::JmpIf { val: false, pos: 0 });
+                            self.push(ExpressionItem::JmpIf { val: false, pos: 0 }, offset);
                             Some(self.output.len() - 1)
                         }
                         BinaryOperator::Or => {
-                            self.output
-                                .push(ExpressionItem::JmpIf { val: true, pos: 0 });
+                            self.push(ExpressionItem::JmpIf { val: true, pos: 0 }, offset);
                             Some(self.output.len() - 1)
                         }
                         _ => None,
                     };
 
                     self.operator_stack
-                        .push((Token::BinaryOperator(bop), jmp_pos));
+                        .push((Token::BinaryOperator(bop), jmp_pos, offset));
                 }
                 Token::Function { id, name, num_args } => {
                     self.inc_arg_count();
                     self.arg_count.push(0);
                     self.operator_stack
-                        .push((Token::Function { id, name, num_args }, None))
+                        .push((Token::Function { id, name, num_args }, None, offset))
                 }
                 Token::Regex(regex) => {
                     self.inc_arg_count();
                     self.arg_count.push(0);
-                    self.operator_stack.push((Token::Regex(regex), None))
+                    self.operator_stack
+                        .push((Token::Regex(regex), None, offset))
                 }
                 Token::OpenBracket => {
                     // Array functions
@@ -202,22 +231,25 @@ where
                             num_args,
                         },
                         None,
+                        offset,
                     ));
-                    self.operator_stack.push((token, None));
+                    self.operator_stack.push((token, None, offset));
                 }
                 Token::Comma => {
-                    while let Some((token, jmp_pos)) = self.operator_stack.last() {
+                    while let Some((token, jmp_pos, op_offset)) = self.operator_stack.last() {
                         match token {
                             Token::OpenParen => break,
                             Token::BinaryOperator(bop) => {
                                 let bop = *bop;
                                 let jmp_pos = *jmp_pos;
+                                let op_offset = *op_offset;
                                 self.update_jmp_pos(jmp_pos);
-                                self.output.push(ExpressionItem::BinaryOperator(bop));
+                                self.push(ExpressionItem::BinaryOperator(bop), op_offset);
                                 self.operator_stack.pop();
                             }
                             Token::UnaryOperator(uop) => {
-                                self.output.push(ExpressionItem::UnaryOperator(*uop));
+                                let (uop, op_offset) = (*uop, *op_offset);
+                                self.push(ExpressionItem::UnaryOperator(uop), op_offset);
                                 self.operator_stack.pop();
                             }
                             _ => break,
@@ -228,30 +260,42 @@ where
             last_is_var_or_fnc = is_var_or_fnc;
         }
 
-        while let Some((token, jmp_pos)) = self.operator_stack.pop() {
+        while let Some((token, jmp_pos, op_offset)) = self.operator_stack.pop() {
             match token {
                 Token::BinaryOperator(bop) => {
                     self.update_jmp_pos(jmp_pos);
-                    self.output.push(ExpressionItem::BinaryOperator(bop))
+                    self.push(ExpressionItem::BinaryOperator(bop), op_offset)
+                }
+                Token::UnaryOperator(uop) => {
+                    self.push(ExpressionItem::UnaryOperator(uop), op_offset)
+                }
+                _ => {
+                    self.error_offset = Some(op_offset);
+                    return Err("Invalid token on the operator stack".to_string());
                 }
-                Token::UnaryOperator(uop) => self.output.push(ExpressionItem::UnaryOperator(uop)),
-                _ => return Err("Invalid token on the operator stack".to_string()),
             }
         }
 
         if self.operator_stack.is_empty() {
-            Ok(Expression { items: self.output })
+            Ok(Expression {
+                items: std::mem::take(&mut self.output),
+            })
         } else {
             Err("Invalid expression".to_string())
         }
     }
 
+    fn push(&mut self, item: ExpressionItem, offset: usize) {
+        self.output.push(item);
+        self.offsets.push(offset);
+    }
+
     fn inc_arg_count(&mut self) {
         if let Some(x) = self.arg_count.last_mut() {
             *x = x.saturating_add(1);
             let op_pos = self.operator_stack.len().saturating_sub(2);
             match self.operator_stack.get_mut(op_pos) {
-                Some((Token::Function { num_args, id, .. }, _)) if *id == ID_ARRAY_BUILD => {
+                Some((Token::Function { num_args, id, .. }, _, _)) if *id == ID_ARRAY_BUILD => {
                     *num_args += 1;
                 }
                 _ => {}
@@ -279,7 +323,7 @@ where
 }
 
 impl BinaryOperator {
-    fn precedence(&self) -> i32 {
+    pub(crate) fn precedence(&self) -> i32 {
         match self {
             BinaryOperator::Multiply | BinaryOperator::Divide => 7,
             BinaryOperator::Add | BinaryOperator::Subtract => 6,
@@ -291,3 +335,83 @@ impl BinaryOperator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        expr::{BinaryOperator, Constant, ExpressionItem, UnaryOperator},
+        schema::{ExpressionType, ExpressionValidator},
+    };
+
+    const VALIDATOR: ExpressionValidator = ExpressionValidator {
+        variables: &["a", "b", "c"],
+        constants: &["relaxed"],
+        returns: ExpressionType::Any,
+    };
+
+    #[test]
+    fn postfix_order() {
+        assert_eq!(
+            VALIDATOR.parse("a + b * c").unwrap().items,
+            vec![
+                ExpressionItem::Variable(0),
+                ExpressionItem::Variable(1),
+                ExpressionItem::Variable(2),
+                ExpressionItem::BinaryOperator(BinaryOperator::Multiply),
+                ExpressionItem::BinaryOperator(BinaryOperator::Add),
+            ]
+        );
+        assert_eq!(
+            VALIDATOR.parse("(a + b) * c").unwrap().items,
+            vec![
+                ExpressionItem::Variable(0),
+                ExpressionItem::Variable(1),
+                ExpressionItem::BinaryOperator(BinaryOperator::Add),
+                ExpressionItem::Variable(2),
+                ExpressionItem::BinaryOperator(BinaryOperator::Multiply),
+            ]
+        );
+        assert_eq!(
+            VALIDATOR.parse("!a == relaxed").unwrap().items,
+            vec![
+                ExpressionItem::Variable(0),
+                ExpressionItem::UnaryOperator(UnaryOperator::Not),
+                ExpressionItem::Constant(Constant::String("relaxed".to_string())),
+                ExpressionItem::BinaryOperator(BinaryOperator::Eq),
+            ]
+        );
+    }
+
+    #[test]
+    fn item_offsets() {
+        let (expr, offsets) = VALIDATOR.parser("a + b * c").parse_with_offsets().unwrap();
+        assert_eq!(expr.items.len(), offsets.len());
+        assert_eq!(offsets, vec![0, 4, 8, 6, 2]);
+
+        let (_, offsets) = VALIDATOR
+            .parser("len(a) > 10")
+            .parse_with_offsets()
+            .unwrap();
+        assert_eq!(offsets, vec![4, 0, 9, 7]);
+    }
+
+    #[test]
+    fn error_offsets() {
+        let err = VALIDATOR.parse("a + unknown").unwrap_err();
+        assert_eq!(err, "Invalid variable or function name \"unknown\"");
+
+        let err = VALIDATOR.check("a + unknown", false).unwrap_err();
+        assert_eq!(err.offset, 4);
+
+        let err = VALIDATOR.check("b + len(a, c)", false).unwrap_err();
+        assert_eq!(err.offset, 4);
+        assert!(
+            err.error.contains("expected 1 arguments, got 2"),
+            "{}",
+            err.error
+        );
+
+        let err = VALIDATOR.check("(a + b", false).unwrap_err();
+        assert_eq!(err.error, "Unmatched open parenthesis");
+    }
+}
@@ -33,9 +33,12 @@ where
 {
     pub(crate) iter: Peekable<Iter<'x, u8>>,
     token_map: F,
+    len: usize,
     buf: Vec<u8>,
+    buf_start: usize,
+    token_start: usize,
     depth: u32,
-    next_token: Vec<Token>,
+    next_token: Vec<(Token, usize)>,
     has_number: bool,
     has_dot: bool,
     has_alpha: bool,
@@ -51,7 +54,10 @@ where
     pub fn new(expr: &'x str, token_map: F) -> Self {
         Self {
             iter: expr.as_bytes().iter().peekable(),
+            len: expr.len(),
             buf: Vec::new(),
+            buf_start: 0,
+            token_start: 0,
             depth: 0,
             next_token: Vec::with_capacity(2),
             has_number: false,
@@ -65,13 +71,18 @@ where
 
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<Token>, String> {
-        if let Some(token) = self.next_token.pop() {
+        if let Some((token, offset)) = self.next_token.pop() {
+            self.token_start = offset;
             return Ok(Some(token));
         } else if self.is_eof {
             return Ok(None);
         }
 
         while let Some(&ch) = self.iter.next() {
+            let pos = self.len - self.iter.len() - 1;
+            if self.buf.is_empty() {
+                self.buf_start = pos;
+            }
             match ch {
                 b'A'..=b'Z' | b'a'..=b'z' | b'_' | b'$' => {
                     self.buf.push(ch);
@@ -102,6 +113,7 @@ where
                     self.buf.push(ch);
                 }
                 _ => {
+                    self.token_start = self.buf_start;
                     let (prev_token, ch) = if ch == b'(' && self.buf.eq(b"matches") {
                         // Parse regular expressions
                         let stop_ch = self.find_char(&[b'\"', b'\''])?;
@@ -119,6 +131,9 @@ where
                     } else {
                         (None, ch)
                     };
+                    if prev_token.is_none() {
+                        self.token_start = pos;
+                    }
                     let token = match ch {
                         b'&' => {
                             if matches!(self.iter.peek(), Some(b'&')) {
@@ -147,6 +162,7 @@ where
                         }
                         b')' => {
                             if self.depth == 0 {
+                                self.token_start = pos;
                                 return Err("Unmatched close parenthesis".to_string());
                             }
                             self.depth -= 1;
@@ -203,7 +219,7 @@ where
                     );
 
                     return if prev_token.is_some() {
-                        self.next_token.push(token);
+                        self.next_token.push((token, pos));
                         Ok(prev_token)
                     } else {
                         Ok(Some(token))
@@ -215,12 +231,17 @@ where
         if self.depth > 0 {
             Err("Unmatched open parenthesis".to_string())
         } else if !self.buf.is_empty() {
+            self.token_start = self.buf_start;
             self.parse_buf().map(Some)
         } else {
             Ok(None)
         }
     }
 
+    pub fn offset(&self) -> usize {
+        self.token_start
+    }
+
     fn find_char(&mut self, chars: &[u8]) -> Result<u8, String> {
         for &ch in self.iter.by_ref() {
             if !ch.is_ascii_whitespace() {
@@ -0,0 +1,63 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::Array,
+    wasm_bindgen::{JsCast, JsValue},
+    Blob, BlobPropertyBag, File, HtmlAnchorElement, Url,
+};
+
+pub fn download_file(
+    file_name: &str,
+    content_type: &str,
+    contents: impl AsRef<str>,
+) -> Result<(), String> {
+    let blob = Blob::new_with_str_sequence_and_options(
+        &Array::of1(&JsValue::from_str(contents.as_ref())),
+        BlobPropertyBag::new().type_(content_type),
+    )
+    .map_err(js_error)?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let anchor = leptos::document()
+        .create_element("a")
+        .map_err(js_error)?
+        .unchecked_into::<HtmlAnchorElement>();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    Url::revoke_object_url(&url).map_err(js_error)
+}
+
+pub async fn read_file(file: File) -> Result<String, String> {
+    JsFuture::from(file.text())
+        .await
+        .map_err(js_error)?
+        .as_string()
+        .ok_or_else(|| "File contents are not valid text".to_string())
+}
+
+fn js_error(err: JsValue) -> String {
+    err.as_string().unwrap_or_else(|| format!("{err:?}"))
+}
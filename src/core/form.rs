@@ -26,17 +26,16 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
 
 use ahash::AHashMap;
 use leptos::RwSignal;
 
 use crate::pages::config::{Settings, SettingsValues};
 
-use super::expr::parser::ExpressionParser;
-use super::expr::tokenizer::Tokenizer;
-use super::expr::{Constant, ParseValue, Token};
-use super::schema::{NumberType, SchemaType, Type};
+use super::expr::eval::ResolveVariable;
+use super::expr::formatter::format_expression;
+use super::expr::Variable;
+use super::schema::{ExpressionValidator, NumberType, SchemaType, Type};
 
 use super::schema::{InputCheck, Schema, Transformer, Validator};
 
@@ -74,6 +73,7 @@ pub struct ExpressionIfThen {
 pub struct FormError {
     pub id: FormErrorType,
     pub error: String,
+    pub offset: Option<usize>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -120,6 +120,7 @@ impl FormData {
             FormError {
                 id: FormErrorType::None,
                 error: error.into(),
+                offset: None,
             },
         );
     }
@@ -332,6 +333,27 @@ impl FormData {
         self.errors.remove(id);
     }
 
+    pub fn expr_format(&mut self, id: &str) {
+        match self.values.get_mut(id) {
+            Some(FormValue::Expression(expr)) => expr.format(),
+            Some(FormValue::Value(value)) => {
+                if let Ok(formatted) = format_expression(value) {
+                    *value = formatted;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn format_expressions(&mut self) {
+        let schema = self.schema.clone();
+        for field in schema.fields.values() {
+            if matches!(field.typ_, Type::Expression) {
+                self.expr_format(field.id);
+            }
+        }
+    }
+
     fn cascading_reset(&mut self, id: &str) {
         let schema = self.schema.clone();
         let mut ids = vec![id.to_string()];
@@ -513,6 +535,7 @@ impl FormData {
                                         FormError {
                                             id: FormErrorType::Array(idx),
                                             error: err.to_string(),
+                                            offset: None,
                                         },
                                     );
                                 }
@@ -572,28 +595,8 @@ impl FormData {
                                 })
                                 .chain([(ExpressionError::Else, &expr.else_)])
                             {
-                                match ExpressionParser::new(Tokenizer::new(expr_value, |token| {
-                                    if validator.variables.contains(&token) {
-                                        Ok(Token::Variable(0))
-                                    } else if validator.constants.contains(&token) {
-                                        Ok(Token::Constant(Constant::Integer(0)))
-                                    } else {
-                                        Duration::parse_value(token)
-                                            .map(|d| {
-                                                Token::Constant(Constant::Integer(
-                                                    d.as_secs() as i64
-                                                ))
-                                            })
-                                            .ok_or_else(|| {
-                                                format!(
-                                                    "Invalid variable or function name {:?}",
-                                                    token
-                                                )
-                                            })
-                                    }
-                                }))
-                                .parse()
-                                {
+                                let is_result = !matches!(expr_item, ExpressionError::If(_));
+                                match validator.check(expr_value, is_result) {
                                     Ok(expr) => {
                                         if matches!(expr_item, ExpressionError::Else) {
                                             has_expression = true;
@@ -604,18 +607,20 @@ impl FormData {
                                                     id: FormErrorType::Expression(expr_item),
                                                     error: "This expression cannot be empty"
                                                         .to_string(),
+                                                    offset: None,
                                                 },
                                             );
                                             has_expression = true;
                                             break;
                                         }
                                     }
-                                    Err(error) => {
+                                    Err(err) => {
                                         self.errors.insert(
                                             field.id.to_string(),
                                             FormError {
                                                 id: FormErrorType::Expression(expr_item),
-                                                error,
+                                                error: err.error,
+                                                offset: Some(err.offset),
                                             },
                                         );
                                         has_expression = true;
@@ -631,6 +636,7 @@ impl FormData {
                                 FormError {
                                     id: FormErrorType::Expression(ExpressionError::Else),
                                     error: "This field is required".to_string(),
+                                    offset: None,
                                 },
                             );
                         }
@@ -952,6 +958,91 @@ impl Expression {
     pub fn is_empty(&self) -> bool {
         self.if_thens.is_empty() && self.else_.is_empty()
     }
+
+    // Conditions that fail to parse are left untouched
+    pub fn format(&mut self) {
+        for value in self
+            .if_thens
+            .iter_mut()
+            .flat_map(|if_then| [&mut if_then.if_, &mut if_then.then_])
+            .chain([&mut self.else_])
+        {
+            if let Ok(formatted) = format_expression(value) {
+                *value = formatted;
+            }
+        }
+    }
+
+    // Returns the index of the matching if/then branch, `None` for the else branch
+    pub fn eval(
+        &self,
+        validator: &ExpressionValidator,
+        variables: &impl ResolveVariable,
+    ) -> Result<(Option<usize>, String), FormError> {
+        let mut captures = Vec::new();
+
+        for (idx, if_then) in self.if_thens.iter().enumerate() {
+            let matched = validator
+                .parse(&if_then.if_)
+                .and_then(|expr| {
+                    expr.eval(variables, &mut captures)
+                        .map(|result| result.to_bool())
+                })
+                .map_err(|error| FormError {
+                    id: FormErrorType::Expression(ExpressionError::If(idx)),
+                    error,
+                    offset: None,
+                })?;
+
+            if matched {
+                return validator
+                    .parse(&if_then.then_)
+                    .and_then(|expr| {
+                        expr.eval(variables, &mut captures)
+                            .map(|result| (Some(idx), result.to_string()))
+                    })
+                    .map_err(|error| FormError {
+                        id: FormErrorType::Expression(ExpressionError::Then(idx)),
+                        error,
+                        offset: None,
+                    });
+            }
+        }
+
+        validator
+            .parse(&self.else_)
+            .and_then(|expr| {
+                expr.eval(variables, &mut captures)
+                    .map(|result| (None, result.to_string()))
+            })
+            .map_err(|error| FormError {
+                id: FormErrorType::Expression(ExpressionError::Else),
+                error,
+                offset: None,
+            })
+    }
+}
+
+impl Variable<'static> {
+    pub fn from_sample(value: &str) -> Self {
+        let value = value.trim();
+        if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Variable::Array(
+                items
+                    .split(',')
+                    .map(|item| item.trim())
+                    .filter(|item| !item.is_empty())
+                    .map(Variable::from_sample)
+                    .collect(),
+            )
+        } else if let Ok(value) = value.parse::<i64>() {
+            Variable::Integer(value)
+        } else if let Ok(value) = value.parse::<f64>() {
+            Variable::Float(value)
+        } else {
+            Variable::String(Cow::Owned(value.to_string()))
+        }
+    }
 }
 
 impl ExpressionIfThen {
@@ -22,8 +22,10 @@
 */
 
 pub mod expr;
+pub mod file;
 pub mod form;
 pub mod http;
 pub mod oauth;
+pub mod polling;
 pub mod schema;
 pub mod url;
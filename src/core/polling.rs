@@ -0,0 +1,241 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::{leptos_dom::helpers::TimeoutHandle, *};
+
+use crate::STATE_POLLING_KEY;
+
+pub const POLLING_INTERVALS: &[(&str, &str)] = &[
+    ("0", "Auto-refresh off"),
+    ("5", "Every 5 seconds"),
+    ("10", "Every 10 seconds"),
+    ("30", "Every 30 seconds"),
+    ("60", "Every minute"),
+    ("300", "Every 5 minutes"),
+];
+
+const MAX_BACKOFF: u64 = 300;
+const MAX_BACKOFF_EXP: u32 = 6;
+
+#[derive(Clone, Copy)]
+pub struct Poller {
+    pub interval: RwSignal<u64>,
+    pub paused: RwSignal<bool>,
+    pub errors: RwSignal<u32>,
+    pub hidden: RwSignal<bool>,
+    page: &'static str,
+    tick: RwSignal<u64>,
+    on_tick: Callback<()>,
+}
+
+// The interval is kept per page and doubles after each failed request
+pub fn use_polling(page: &'static str, on_tick: impl Fn() + 'static) -> Poller {
+    let poller = Poller {
+        interval: create_rw_signal(
+            LocalStorage::get::<HashMap<String, u64>>(STATE_POLLING_KEY)
+                .ok()
+                .and_then(|intervals| intervals.get(page).copied())
+                .unwrap_or_default(),
+        ),
+        paused: create_rw_signal(false),
+        errors: create_rw_signal(0),
+        hidden: create_rw_signal(is_document_hidden()),
+        page,
+        tick: create_rw_signal(0),
+        on_tick: Callback::new(move |_| on_tick()),
+    };
+
+    // Refresh immediately when the tab becomes visible again
+    let visibility_listener = window_event_listener_untyped("visibilitychange", move |_| {
+        let hidden = is_document_hidden();
+        poller.hidden.set(hidden);
+        if !hidden && poller.is_active_untracked() {
+            poller.refresh();
+        }
+    });
+    on_cleanup(move || visibility_listener.remove());
+
+    let timer = store_value(None::<TimeoutHandle>);
+    create_effect(move |_| {
+        poller.tick.get();
+        if let Some(handle) = timer.get_value() {
+            handle.clear();
+        }
+        timer.set_value(
+            poller
+                .delay()
+                .and_then(|delay| set_timeout_with_handle(move || poller.refresh(), delay).ok()),
+        );
+    });
+    on_cleanup(move || {
+        if let Some(handle) = timer.get_value() {
+            handle.clear();
+        }
+    });
+
+    poller
+}
+
+impl Poller {
+    pub fn refresh(&self) {
+        self.on_tick.call(());
+        self.tick.update(|tick| *tick += 1);
+    }
+
+    pub fn watch<S: Clone + 'static, T: 'static, E: 'static>(
+        &self,
+        resource: Resource<S, Result<T, E>>,
+    ) {
+        let poller = *self;
+        create_effect(move |_| {
+            resource.with(|result| {
+                if let Some(result) = result {
+                    poller.report(result);
+                }
+            });
+        });
+    }
+
+    pub fn report<T, E>(&self, result: &Result<T, E>) {
+        if result.is_ok() {
+            if self.errors.get_untracked() > 0 {
+                self.errors.set(0);
+            }
+        } else if self.is_active_untracked() {
+            self.errors.update(|errors| *errors += 1);
+        }
+    }
+
+    pub fn set_interval(&self, interval: u64) {
+        self.interval.set(interval);
+        self.errors.set(0);
+
+        let mut intervals =
+            LocalStorage::get::<HashMap<String, u64>>(STATE_POLLING_KEY).unwrap_or_default();
+        if interval > 0 {
+            intervals.insert(self.page.to_string(), interval);
+        } else {
+            intervals.remove(self.page);
+        }
+        if let Err(err) = LocalStorage::set(STATE_POLLING_KEY, intervals) {
+            log::error!("Failed to save polling interval to local storage: {}", err);
+        }
+    }
+
+    pub fn toggle_pause(&self) {
+        self.paused.update(|paused| *paused = !*paused);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.interval.get() > 0 && !self.paused.get() && !self.hidden.get()
+    }
+
+    fn is_active_untracked(&self) -> bool {
+        untrack(|| self.is_active())
+    }
+
+    // `None` when polling is stopped
+    pub fn delay(&self) -> Option<Duration> {
+        if self.is_active() {
+            let interval = self.interval.get();
+            let errors = self.errors.get().min(MAX_BACKOFF_EXP);
+            Some(Duration::from_secs(if errors > 0 {
+                (interval << errors).min(MAX_BACKOFF.max(interval))
+            } else {
+                interval
+            }))
+        } else {
+            None
+        }
+    }
+}
+
+fn is_document_hidden() -> bool {
+    document().hidden()
+}
+
+// Reports the rows that are new or changed since the last refresh
+pub struct ChangeTracker<K: 'static, V: 'static> {
+    scope: StoredValue<Option<u64>>,
+    previous: StoredValue<HashMap<K, V>>,
+    changed: StoredValue<HashSet<K>>,
+}
+
+impl<K: Hash + Eq + Clone + 'static, V: PartialEq + 'static> ChangeTracker<K, V> {
+    pub fn new() -> Self {
+        ChangeTracker {
+            scope: store_value(None),
+            previous: store_value(HashMap::new()),
+            changed: store_value(HashSet::new()),
+        }
+    }
+
+    // Rows are only highlighted when the page and filters did not change
+    pub fn update(&self, scope: impl Hash, items: impl IntoIterator<Item = (K, V)>) {
+        let mut hasher = DefaultHasher::new();
+        scope.hash(&mut hasher);
+        let scope = Some(hasher.finish());
+        let is_same_scope = self.scope.get_value() == scope;
+        self.scope.set_value(scope);
+
+        let items = items.into_iter().collect::<HashMap<_, _>>();
+        let changed = if is_same_scope {
+            self.previous.with_value(|previous| {
+                items
+                    .iter()
+                    .filter(|(key, value)| previous.get(*key).map_or(true, |prev| prev != *value))
+                    .map(|(key, _)| key.clone())
+                    .collect()
+            })
+        } else {
+            HashSet::new()
+        };
+        self.changed.set_value(changed);
+        self.previous.set_value(items);
+    }
+
+    pub fn is_changed(&self, key: &K) -> bool {
+        self.changed.with_value(|changed| changed.contains(key))
+    }
+}
+
+impl<K: Hash + Eq + Clone + 'static, V: PartialEq + 'static> Default for ChangeTracker<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: 'static, V: 'static> Clone for ChangeTracker<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: 'static, V: 'static> Copy for ChangeTracker<K, V> {}
@@ -218,6 +218,19 @@ pub enum Validator {
 pub struct ExpressionValidator {
     pub variables: &'static [&'static str],
     pub constants: &'static [&'static str],
+    pub returns: ExpressionType,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ExpressionType {
+    #[default]
+    Any,
+    Boolean,
+    Integer,
+    String,
+    Duration,
+    Size,
+    Array(&'static ExpressionType),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -926,9 +939,15 @@ impl ExpressionValidator {
         ExpressionValidator {
             variables,
             constants,
+            returns: ExpressionType::Any,
         }
     }
 
+    pub fn returns(mut self, returns: ExpressionType) -> Self {
+        self.returns = returns;
+        self
+    }
+
     pub fn constants(mut self, constants: &'static [&'static str]) -> Self {
         self.constants = constants;
         self
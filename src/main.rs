@@ -47,19 +47,31 @@ use crate::{
     pages::{
         account::{crypto::ManageCrypto, password::ChangePassword},
         authorize::Authorize,
-        config::{edit::SettingsEdit, list::SettingsList, search::SettingsSearch},
+        config::{
+            edit::SettingsEdit, history::SettingsHistory, list::SettingsList, raw::SettingsRaw,
+            reload::init_pending_errors, search::SettingsSearch, transfer::SettingsTransfer,
+            wizard::SettingsWizard,
+        },
         directory::{
             domains::{display::DomainDisplay, edit::DomainCreate, list::DomainList},
             principals::{edit::PrincipalEdit, list::PrincipalList},
         },
         login::Login,
-        manage::{logs::Logs, maintenance::Maintenance},
+        manage::{
+            logs::{list::Logs, tail::LogTail},
+            maintenance::Maintenance,
+        },
         notfound::NotFound,
         queue::{
             messages::{list::QueueList, manage::QueueManage},
             reports::{display::ReportDisplay, list::ReportList},
+            stats::QueueStatistics,
+        },
+        reports::{
+            analytics::{dmarc::DmarcAnalytics, senders::SenderInventory, tls::TlsAnalytics},
+            display::IncomingReportDisplay,
+            list::IncomingReportList,
         },
-        reports::{display::IncomingReportDisplay, list::IncomingReportList},
     },
 };
 
@@ -69,6 +81,11 @@ pub mod pages;
 
 pub const STATE_STORAGE_KEY: &str = "webadmin_state";
 pub const STATE_LOGIN_NAME_KEY: &str = "webadmin_login_name";
+pub const STATE_FORMAT_EXPR_KEY: &str = "webadmin_format_expressions";
+pub const STATE_HISTORY_KEY: &str = "webadmin_settings_history";
+pub const STATE_POLLING_KEY: &str = "webadmin_polling_intervals";
+pub const STATE_SENDER_LABELS_KEY: &str = "webadmin_dmarc_sender_labels";
+pub const STATE_ARF_TRIAGE_KEY: &str = "webadmin_arf_triage";
 
 fn main() {
     console_error_panic_hook::set_once();
@@ -92,6 +109,7 @@ pub fn App() -> impl IntoView {
     provide_context(build_schemas());
     init_alerts();
     init_modals();
+    init_pending_errors();
 
     // Create a resource to refresh the OAuth token
     let _refresh_token_resource = create_resource(auth_token, move |changed_auth_token| {
@@ -182,6 +200,12 @@ pub fn App() -> impl IntoView {
                         redirect_path="/login"
                         condition=move || is_admin.get()
                     />
+                    <ProtectedRoute
+                        path="/queue"
+                        view=QueueStatistics
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
                     <ProtectedRoute
                         path="/queue/messages"
                         view=QueueList
@@ -219,12 +243,36 @@ pub fn App() -> impl IntoView {
                         redirect_path="/login"
                         condition=move || is_admin.get()
                     />
+                    <ProtectedRoute
+                        path="/analytics/dmarc"
+                        view=DmarcAnalytics
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
+                    <ProtectedRoute
+                        path="/analytics/senders"
+                        view=SenderInventory
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
+                    <ProtectedRoute
+                        path="/analytics/tls"
+                        view=TlsAnalytics
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
                     <ProtectedRoute
                         path="/logs"
                         view=Logs
                         redirect_path="/login"
                         condition=move || is_admin.get()
                     />
+                    <ProtectedRoute
+                        path="/logs/tail"
+                        view=LogTail
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
                     <ProtectedRoute
                         path="/maintenance"
                         view=Maintenance
@@ -259,6 +307,30 @@ pub fn App() -> impl IntoView {
                         redirect_path="/login"
                         condition=move || is_admin.get()
                     />
+                    <ProtectedRoute
+                        path="/transfer"
+                        view=SettingsTransfer
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
+                    <ProtectedRoute
+                        path="/history"
+                        view=SettingsHistory
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
+                    <ProtectedRoute
+                        path="/raw"
+                        view=SettingsRaw
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
+                    <ProtectedRoute
+                        path="/setup"
+                        view=SettingsWizard
+                        redirect_path="/login"
+                        condition=move || is_admin.get()
+                    />
                 </ProtectedRoute>
                 <ProtectedRoute
                     path="/account"
@@ -314,6 +386,9 @@ impl LayoutBuilder {
             .insert()
             .create("Queues")
             .icon(view! { <IconQueueList/> })
+            .create("Statistics")
+            .route("/queue")
+            .insert()
             .create("Messages")
             .route("/queue/messages")
             .insert()
@@ -332,6 +407,15 @@ impl LayoutBuilder {
             .create("Failures")
             .route("/reports/arf")
             .insert()
+            .create("DMARC Analytics")
+            .route("/analytics/dmarc")
+            .insert()
+            .create("Sender Inventory")
+            .route("/analytics/senders")
+            .insert()
+            .create("TLS Analytics")
+            .route("/analytics/tls")
+            .insert()
             .insert()
             .create("Logs")
             .icon(view! { <IconDocumentText/> })
@@ -24,6 +24,7 @@
 use std::sync::Arc;
 
 use ahash::AHashMap;
+use gloo_storage::{LocalStorage, Storage};
 use leptos::*;
 use leptos_router::{use_navigate, use_params_map};
 use serde::{Deserialize, Serialize};
@@ -42,19 +43,26 @@ use crate::{
             Form, FormButtonBar, FormElement, FormItem, FormSection,
         },
         icon::IconRefresh,
-        messages::alert::{use_alerts, Alert},
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
         skeleton::Skeleton,
         Color,
     },
     core::{
         form::{ExternalSources, FormData},
         http::{self, HttpRequest},
-        oauth::use_authorization,
+        oauth::{use_authorization, AuthToken},
     },
     pages::{
-        config::{ReloadSettings, Schema, SchemaType, Schemas, Settings, Type, UpdateSettings},
+        config::{
+            history::update_settings, reload::use_pending_errors, ReloadSettings, Schema,
+            SchemaType, Schemas, Settings, SettingsDiff, Type, UpdateSettings,
+        },
         List,
     },
+    STATE_FORMAT_EXPR_KEY,
 };
 
 #[derive(Clone, Serialize, Deserialize, Default)]
@@ -81,9 +89,12 @@ pub const DEFAULT_SETTINGS_URL: &str = "/settings/network/edit";
 pub fn SettingsEdit() -> impl IntoView {
     let auth = use_authorization();
     let alert = use_alerts();
+    let modal = use_modals();
+    let pending_errors = use_pending_errors();
     let params = use_params_map();
 
     let schemas = expect_context::<Arc<Schemas>>();
+    let schemas_ = schemas.clone();
     let current_schema = create_memo(move |_| {
         if let Some(schema) = params()
             .get("object")
@@ -105,37 +116,7 @@ pub fn SettingsEdit() -> impl IntoView {
 
             async move {
                 // Fetch external sources
-                let mut external_sources = ExternalSources::new();
-                for (schema, field) in schema.external_sources() {
-                    let source_key = format!("{}_{}", schema.id, field.id);
-                    if !external_sources.contains_key(&source_key) {
-                        let items = HttpRequest::get("/api/settings/group")
-                            .with_authorization(&auth)
-                            .with_parameter("prefix", schema.unwrap_prefix())
-                            .with_parameter(
-                                "suffix",
-                                schema.try_unwrap_suffix().unwrap_or_default(),
-                            )
-                            .with_parameter("field", field.id)
-                            .send::<List<Settings>>()
-                            .await?
-                            .items;
-
-                        external_sources.insert(
-                            source_key,
-                            items
-                                .into_iter()
-                                .filter_map(|mut item| {
-                                    (
-                                        item.remove("_id")?,
-                                        item.remove(field.id).unwrap_or_default(),
-                                    )
-                                        .into()
-                                })
-                                .collect::<Vec<_>>(),
-                        );
-                    }
-                }
+                let external_sources = fetch_external_sources(&auth, &schema).await?;
 
                 // Fetch settings
                 match schema.typ {
@@ -226,26 +207,31 @@ pub fn SettingsEdit() -> impl IntoView {
     );
     let (pending, set_pending) = create_signal(false);
     let data = FormData::default().into_signal();
+    let original = create_rw_signal(Settings::new());
+    let format_on_save =
+        create_rw_signal(LocalStorage::get::<bool>(STATE_FORMAT_EXPR_KEY).unwrap_or_default());
+    let has_expressions = create_memo(move |_| {
+        current_schema
+            .get()
+            .fields
+            .values()
+            .any(|field| matches!(field.typ_, Type::Expression))
+    });
 
     let save_changes = create_action(
         move |(changes, reload): &(Arc<Vec<UpdateSettings>>, bool)| {
             let changes = changes.clone();
             let reload = *reload;
             let auth = auth.get();
+            let schemas = schemas_.clone();
             let schema = current_schema.get();
+            let description = data.with_untracked(|data| data.describe_update());
 
             //let c = log::debug!("Saving changes: {:?}", changes);
 
             async move {
                 set_pending.set(true);
-                match HttpRequest::post("/api/settings")
-                    .with_authorization(&auth)
-                    .with_body(changes)
-                    .unwrap()
-                    .send::<Option<String>>()
-                    .await
-                    .map(|_| ())
-                {
+                match update_settings(&auth, &schemas, description, changes).await {
                     Ok(_) => {
                         if reload {
                             match HttpRequest::get(format!(
@@ -299,6 +285,31 @@ pub fn SettingsEdit() -> impl IntoView {
         },
     );
 
+    let confirm_changes = move |changes: Vec<UpdateSettings>, reload: bool| {
+        let schema = current_schema.get();
+        let diff = SettingsDiff::new(&original.get(), &changes);
+        let changes = Arc::new(changes);
+        let (title, button) = if reload {
+            ("Review changes before reloading", "Save & Reload")
+        } else {
+            ("Review changes", "Save changes")
+        };
+
+        modal.set(
+            Modal::with_title(title)
+                .with_message(if diff.is_empty() {
+                    "No settings will be modified."
+                } else {
+                    "The following settings will be modified:"
+                })
+                .with_content(move || settings_diff(&diff, |key| schema.is_secret(key)))
+                .with_button(button)
+                .with_callback(move || {
+                    save_changes.dispatch((changes.clone(), reload));
+                }),
+        );
+    };
+
     view! {
         <Form
             title=Signal::derive(move || current_schema.get().form.title.to_string())
@@ -333,177 +344,63 @@ pub fn SettingsEdit() -> impl IntoView {
                             FetchResult::NotFound => unreachable!(),
                         };
                         let schema = current_schema.get();
-                        let sections = schema.form.sections.iter().cloned();
+                        original
+                            .set(
+                                settings
+                                    .as_ref()
+                                    .map(|settings| schema.full_settings(settings))
+                                    .unwrap_or_default(),
+                            );
                         data.set(
                             FormData::from_settings(schema.clone(), settings)
                                 .with_external_sources(external_sources),
                         );
-                        Some(
-                            sections
-                                .map(|section| {
-                                    let title = section.title.map(|s| s.to_string());
-                                    let section_ = section.clone();
-                                    let hide_section = create_memo(move |_| {
-                                        !section_.display(&data.get())
-                                    });
-                                    let components = section
-                                        .fields
-                                        .iter()
-                                        .cloned()
-                                        .map(|field| {
-                                            let is_disabled = field.readonly && !is_create;
-                                            let field_label = field.label_form;
-                                            let help = field.help;
-                                            let field_ = field.clone();
-                                            let hide_label = create_memo(move |_| {
-                                                !field_.display(&data.get())
-                                            });
-                                            let field_ = field.clone();
-                                            let is_optional = create_memo(move |_| {
-                                                !field_.is_required(&data.get())
-                                            });
-                                            let is_switch = matches!(field.typ_, Type::Boolean);
-                                            let component = match field.typ_ {
-                                                Type::Input => {
-                                                    view! {
-                                                        <InputText
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Array => {
-                                                    view! {
-                                                        <StackedInput
-                                                            add_button_text="Add".to_string()
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Secret => {
-                                                    view! {
-                                                        <InputPassword element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Select { multi: false, .. } => {
-                                                    view! {
-                                                        <Select
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Select { multi: true, .. } => {
-                                                    view! {
-                                                        <CheckboxGroup
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Size => {
-                                                    view! {
-                                                        <InputSize element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Boolean => {
-                                                    view! {
-                                                        <InputSwitch
-                                                            label=field_label
-                                                            tooltip=help.unwrap_or_default()
-                                                            element=FormElement::new(field.id, data)
-                                                        />
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Duration => {
-                                                    view! {
-                                                        <InputDuration element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Rate => {
-                                                    view! {
-                                                        <InputRate element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Expression => {
-                                                    view! {
-                                                        <InputExpression element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Cron => {
-                                                    view! {
-                                                        <SelectCron element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                                Type::Text => {
-                                                    view! {
-                                                        <TextArea element=FormElement::new(field.id, data)/>
-                                                    }
-                                                        .into_view()
-                                                }
-                                            };
-                                            if !is_switch {
-                                                view! {
-                                                    <FormItem
-                                                        label=field_label
-                                                        hide=hide_label
-                                                        is_optional=is_optional
-                                                        tooltip=help.unwrap_or_default()
-                                                    >
-                                                        {component}
-                                                    </FormItem>
-                                                }
-                                            } else {
-                                                view! {
-                                                    <FormItem label="" hide=hide_label is_optional=is_optional>
-                                                        {component}
-                                                    </FormItem>
-                                                }
-                                            }
-                                        })
-                                        .collect_view();
-                                    view! {
-                                        <FormSection
-                                            title=title.unwrap_or_default()
-                                            hide=hide_section
-                                        >
-                                            {components}
-                                        </FormSection>
+                        if !is_create {
+                            let id = params.get_untracked().get("id").cloned();
+                            let issues = pending_errors
+                                .try_update(|pending| pending.take(&schema, id.as_deref()))
+                                .unwrap_or_default();
+                            if !issues.is_empty() {
+                                data.update(|data| {
+                                    for issue in issues {
+                                        if let Some(location) = &issue.location {
+                                            data.new_setting_error(
+                                                location,
+                                                format!("{}: {}", issue.kind, issue.message),
+                                            );
+                                        }
                                     }
-                                        .into_view()
-                                })
-                                .collect_view(),
-                        )
+                                });
+                            }
+                        }
+                        Some(form_sections(data, is_create))
                     }
                 }}
 
             </Transition>
 
             <FormButtonBar>
+                <div class="flex items-center me-auto" class:hidden=move || !has_expressions.get()>
+                    <input
+                        id="format-on-save"
+                        type="checkbox"
+                        class="shrink-0 border-gray-200 rounded text-blue-600 focus:ring-blue-500 dark:bg-gray-800 dark:border-gray-700 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                        prop:checked=format_on_save
+                        on:input=move |_| {
+                            format_on_save.update(|v| *v = !*v);
+                            if let Err(err) = LocalStorage::set(
+                                STATE_FORMAT_EXPR_KEY,
+                                format_on_save.get_untracked(),
+                            ) {
+                                log::error!("Failed to save preference to local storage: {}", err);
+                            }
+                        }
+                    />
+
+                    <label for="format-on-save" class="text-sm text-gray-500 ms-2 dark:text-gray-400">
+                        Format expressions on save
+                    </label>
+                </div>
                 <Button
                     text="Cancel"
                     color=Color::Gray
@@ -520,8 +417,11 @@ pub fn SettingsEdit() -> impl IntoView {
                     color=Color::Gray
                     on_click=Callback::new(move |_| {
                         data.update(|data| {
+                            if format_on_save.get() {
+                                data.format_expressions();
+                            }
                             if data.validate_form() {
-                                save_changes.dispatch((Arc::new(data.build_update()), true));
+                                confirm_changes(data.build_update(), true);
                             }
                         });
                     })
@@ -537,8 +437,11 @@ pub fn SettingsEdit() -> impl IntoView {
                     color=Color::Blue
                     on_click=Callback::new(move |_| {
                         data.update(|data| {
+                            if format_on_save.get() {
+                                data.format_expressions();
+                            }
                             if data.validate_form() {
-                                save_changes.dispatch((Arc::new(data.build_update()), false));
+                                confirm_changes(data.build_update(), false);
                             }
                         });
                     })
@@ -551,7 +454,322 @@ pub fn SettingsEdit() -> impl IntoView {
     }
 }
 
+pub async fn fetch_external_sources(
+    auth: &AuthToken,
+    schema: &Schema,
+) -> http::Result<ExternalSources> {
+    let mut external_sources = ExternalSources::new();
+    for (schema, field) in schema.external_sources() {
+        let source_key = format!("{}_{}", schema.id, field.id);
+        if !external_sources.contains_key(&source_key) {
+            let items = HttpRequest::get("/api/settings/group")
+                .with_authorization(auth)
+                .with_parameter("prefix", schema.unwrap_prefix())
+                .with_parameter("suffix", schema.try_unwrap_suffix().unwrap_or_default())
+                .with_parameter("field", field.id)
+                .send::<List<Settings>>()
+                .await?
+                .items;
+
+            external_sources.insert(
+                source_key,
+                items
+                    .into_iter()
+                    .filter_map(|mut item| {
+                        (
+                            item.remove("_id")?,
+                            item.remove(field.id).unwrap_or_default(),
+                        )
+                            .into()
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    Ok(external_sources)
+}
+
+pub fn form_sections(data: RwSignal<FormData>, is_create: bool) -> View {
+    let schema = data.with_untracked(|data| data.schema.clone());
+    schema
+        .form
+        .sections
+        .iter()
+        .map(|section| {
+            let title = section.title.map(|s| s.to_string());
+            let section_ = section.clone();
+            let hide_section = create_memo(move |_| {
+                !section_.display(&data.get())
+            });
+            let components = section
+                .fields
+                .iter()
+                .cloned()
+                .map(|field| {
+                    let is_disabled = field.readonly && !is_create;
+                    let field_label = field.label_form;
+                    let help = field.help;
+                    let field_ = field.clone();
+                    let hide_label = create_memo(move |_| {
+                        !field_.display(&data.get())
+                    });
+                    let field_ = field.clone();
+                    let is_optional = create_memo(move |_| {
+                        !field_.is_required(&data.get())
+                    });
+                    let is_switch = matches!(field.typ_, Type::Boolean);
+                    let component = match field.typ_ {
+                        Type::Input => {
+                            view! {
+                                <InputText
+                                    element=FormElement::new(field.id, data)
+                                    placeholder=create_memo(move |_| {
+                                        field
+                                            .placeholder(&data.get())
+                                            .unwrap_or_default()
+                                            .to_string()
+                                    })
+
+                                    disabled=is_disabled
+                                />
+                            }
+                                .into_view()
+                        }
+                        Type::Array => {
+                            view! {
+                                <StackedInput
+                                    add_button_text="Add".to_string()
+                                    element=FormElement::new(field.id, data)
+                                    placeholder=create_memo(move |_| {
+                                        field
+                                            .placeholder(&data.get())
+                                            .unwrap_or_default()
+                                            .to_string()
+                                    })
+                                />
+                            }
+                                .into_view()
+                        }
+                        Type::Secret => {
+                            view! {
+                                <InputPassword element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Select { multi: false, .. } => {
+                            view! {
+                                <Select
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                                .into_view()
+                        }
+                        Type::Select { multi: true, .. } => {
+                            view! {
+                                <CheckboxGroup
+                                    element=FormElement::new(field.id, data)
+                                    disabled=is_disabled
+                                />
+                            }
+                                .into_view()
+                        }
+                        Type::Size => {
+                            view! {
+                                <InputSize element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Boolean => {
+                            view! {
+                                <InputSwitch
+                                    label=field_label
+                                    tooltip=help.unwrap_or_default()
+                                    element=FormElement::new(field.id, data)
+                                />
+                            }
+                                .into_view()
+                        }
+                        Type::Duration => {
+                            view! {
+                                <InputDuration element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Rate => {
+                            view! {
+                                <InputRate element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Expression => {
+                            view! {
+                                <InputExpression element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Cron => {
+                            view! {
+                                <SelectCron element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                        Type::Text => {
+                            view! {
+                                <TextArea element=FormElement::new(field.id, data)/>
+                            }
+                                .into_view()
+                        }
+                    };
+                    if !is_switch {
+                        view! {
+                            <FormItem
+                                label=field_label
+                                hide=hide_label
+                                is_optional=is_optional
+                                tooltip=help.unwrap_or_default()
+                            >
+                                {component}
+                            </FormItem>
+                        }
+                    } else {
+                        view! {
+                            <FormItem label="" hide=hide_label is_optional=is_optional>
+                                {component}
+                            </FormItem>
+                        }
+                    }
+                })
+                .collect_view();
+            view! {
+                <FormSection
+                    title=title.unwrap_or_default()
+                    hide=hide_section
+                >
+                    {components}
+                </FormSection>
+            }
+                .into_view()
+        })
+        .collect_view()
+}
+
+pub fn settings_diff(diff: &SettingsDiff, is_secret: impl Fn(&str) -> bool) -> View {
+    let value = |key: &str, value: &str| {
+        if is_secret(key) {
+            "********".to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    let rows = diff
+        .added
+        .iter()
+        .map(|(key, new_value)| {
+            view! {
+                <tr class="bg-green-50 dark:bg-green-800/10">
+                    <td class="px-2 py-1 font-semibold text-green-700 dark:text-green-400">+</td>
+                    <td class="px-2 py-1 break-all">{key.clone()}</td>
+                    <td class="px-2 py-1 break-all text-green-700 dark:text-green-400">
+                        {value(key, new_value)}
+                    </td>
+                </tr>
+            }
+        })
+        .chain(diff.changed.iter().map(|(key, old_value, new_value)| {
+            view! {
+                <tr class="bg-yellow-50 dark:bg-yellow-800/10">
+                    <td class="px-2 py-1 font-semibold text-yellow-700 dark:text-yellow-400">~</td>
+                    <td class="px-2 py-1 break-all">{key.clone()}</td>
+                    <td class="px-2 py-1 break-all">
+                        <span class="line-through text-red-700 dark:text-red-400">
+                            {value(key, old_value)}
+                        </span>
+                        <br/>
+                        <span class="text-green-700 dark:text-green-400">
+                            {value(key, new_value)}
+                        </span>
+                    </td>
+                </tr>
+            }
+        }))
+        .chain(diff.deleted.iter().map(|(key, old_value)| {
+            view! {
+                <tr class="bg-red-50 dark:bg-red-800/10">
+                    <td class="px-2 py-1 font-semibold text-red-700 dark:text-red-400">-</td>
+                    <td class="px-2 py-1 break-all">{key.clone()}</td>
+                    <td class="px-2 py-1 break-all line-through text-red-700 dark:text-red-400">
+                        {value(key, old_value)}
+                    </td>
+                </tr>
+            }
+        }))
+        .collect_view();
+    let cleared = diff
+        .cleared
+        .iter()
+        .map(|prefix| {
+            view! { <li class="font-mono">{format!("{prefix}*")}</li> }
+        })
+        .collect_view();
+    let has_cleared = !diff.cleared.is_empty();
+
+    view! {
+        <table class="mt-3 min-w-full text-xs font-mono text-gray-800 dark:text-gray-300">
+            <tbody class="divide-y divide-gray-200 dark:divide-gray-700">{rows}</tbody>
+        </table>
+        {has_cleared
+            .then(|| {
+                view! {
+                    <p class="mt-4 text-sm text-gray-800 dark:text-gray-400">
+                        The following prefixes will be cleared before the new values are written:
+                    </p>
+                    <ul class="mt-1 list-disc list-inside text-xs text-gray-600 dark:text-gray-400">
+                        {cleared}
+                    </ul>
+                }
+            })}
+    }
+    .into_view()
+}
+
 impl Schema {
+    fn full_settings(&self, settings: &Settings) -> Settings {
+        match &self.typ {
+            SchemaType::Record { prefix, .. } => {
+                let id = settings.get("_id").map(|s| s.as_str()).unwrap_or_default();
+                settings
+                    .iter()
+                    .filter(|(key, _)| *key != "_id")
+                    .map(|(key, value)| (format!("{prefix}.{id}.{key}"), value.clone()))
+                    .collect()
+            }
+            SchemaType::Entry { prefix } => {
+                let mut full_settings = Settings::new();
+                if let (Some(id), Some(value)) = (settings.get("_id"), settings.get("_value")) {
+                    full_settings.insert(format!("{prefix}.{id}"), value.clone());
+                }
+                full_settings
+            }
+            SchemaType::List => settings.clone(),
+        }
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        let field_id = match &self.typ {
+            SchemaType::Record { prefix, .. } => key
+                .strip_prefix(*prefix)
+                .and_then(|key| key.strip_prefix('.'))
+                .and_then(|key| key.split_once('.'))
+                .map_or(key, |(_, field_id)| field_id),
+            _ => key,
+        };
+        self.fields
+            .get(field_id)
+            .map_or(false, |field| matches!(field.typ_, Type::Secret))
+    }
+
     fn list_path(&self) -> Option<String> {
         if !matches!(self.typ, SchemaType::List) {
             format!("/settings/{}", self.id).into()
@@ -0,0 +1,643 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        icon::{IconArrowLeft, IconTrash},
+        list::{
+            header::ColumnList, toolbar::ToolbarButton, Footer, ListItem, ListSection, ListTable,
+            ListTextItem, Toolbar, ZeroResults,
+        },
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::{use_authorization, AuthToken},
+    },
+    pages::{
+        config::{edit::settings_diff, Schemas, Settings, SettingsDiff, UpdateSettings},
+        FormatDateTime,
+    },
+    STATE_HISTORY_KEY,
+};
+
+const MAX_REVISIONS: usize = 100;
+const SECRET_PLACEHOLDER: &str = "********";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub username: String,
+    pub description: String,
+    pub changes: Vec<UpdateSettings>,
+    pub previous: Settings,
+    // Secret keys whose previous values were not stored
+    #[serde(default)]
+    pub masked: Vec<String>,
+}
+
+pub async fn update_settings(
+    auth: &AuthToken,
+    schemas: &Schemas,
+    description: impl Into<String>,
+    changes: Arc<Vec<UpdateSettings>>,
+) -> http::Result<()> {
+    let mut previous = fetch_previous(auth, &changes).await?;
+
+    HttpRequest::post("/api/settings")
+        .with_authorization(auth)
+        .with_body(changes.clone())
+        .unwrap()
+        .send::<Option<String>>()
+        .await?;
+
+    let mut masked = Vec::new();
+    for (key, value) in previous.iter_mut() {
+        if schemas.is_secret(key) {
+            *value = SECRET_PLACEHOLDER.to_string();
+            masked.push(key.clone());
+        }
+    }
+    let mut changes = changes.as_ref().clone();
+    for change in &mut changes {
+        if let UpdateSettings::Insert { prefix, values, .. } = change {
+            for (key, value) in values.iter_mut() {
+                if schemas.is_secret(&full_key(prefix, key)) {
+                    *value = SECRET_PLACEHOLDER.to_string();
+                }
+            }
+        }
+    }
+
+    Revision {
+        id: 0,
+        timestamp: Utc::now(),
+        username: auth.username.to_string(),
+        description: description.into(),
+        changes,
+        previous,
+        masked,
+    }
+    .save();
+
+    Ok(())
+}
+
+async fn fetch_previous(auth: &AuthToken, changes: &[UpdateSettings]) -> http::Result<Settings> {
+    let mut keys = Vec::new();
+    let mut prefixes = Vec::new();
+
+    for change in changes {
+        match change {
+            UpdateSettings::Delete { keys: delete_keys } => {
+                keys.extend(delete_keys.iter().cloned());
+            }
+            UpdateSettings::Clear { prefix } => {
+                prefixes.push(prefix.trim_end_matches('.').to_string());
+            }
+            UpdateSettings::Insert { prefix, values, .. } => {
+                keys.extend(values.iter().map(|(key, _)| full_key(prefix, key)));
+            }
+        }
+    }
+
+    if keys.is_empty() && prefixes.is_empty() {
+        return Ok(Settings::new());
+    }
+
+    HttpRequest::get("/api/settings/keys")
+        .with_authorization(auth)
+        .with_parameter("keys", keys.join(","))
+        .with_parameter("prefixes", prefixes.join(","))
+        .send::<AHashMap<String, Option<String>>>()
+        .await
+        .map(|values| {
+            values
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let value = value?;
+                    changes
+                        .iter()
+                        .any(|change| change.affects(&key))
+                        .then_some((key, value))
+                })
+                .collect()
+        })
+}
+
+impl Revision {
+    pub fn load_all() -> Vec<Revision> {
+        LocalStorage::get(STATE_HISTORY_KEY).unwrap_or_default()
+    }
+
+    fn save(mut self) {
+        let mut revisions = Revision::load_all();
+        self.id = revisions.first().map_or(1, |revision| revision.id + 1);
+        revisions.insert(0, self);
+        revisions.truncate(MAX_REVISIONS);
+
+        if let Err(err) = LocalStorage::set(STATE_HISTORY_KEY, revisions) {
+            log::error!("Failed to save revision to local storage: {}", err);
+        }
+    }
+
+    pub fn diff(&self) -> SettingsDiff {
+        SettingsDiff::new(&self.previous, &self.changes)
+    }
+
+    // Reverting a cleared prefix would also wipe the secrets under it
+    pub fn is_revertable(&self) -> bool {
+        !self.changes.iter().any(|change| {
+            matches!(change, UpdateSettings::Clear { prefix }
+                if self.masked.iter().any(|key| key.starts_with(prefix.as_str())))
+        })
+    }
+
+    // Secrets are not stored, so masked keys are left as they are
+    pub fn inverse(&self) -> Vec<UpdateSettings> {
+        let mut updates = Vec::new();
+        let mut cleared = Vec::new();
+        let mut delete_keys = Vec::new();
+
+        for change in &self.changes {
+            if let UpdateSettings::Clear { prefix } = change {
+                updates.push(UpdateSettings::Clear {
+                    prefix: prefix.clone(),
+                });
+                cleared.push(prefix.as_str());
+            }
+        }
+
+        for change in &self.changes {
+            if let UpdateSettings::Insert { prefix, values, .. } = change {
+                for (key, _) in values {
+                    let key = full_key(prefix, key);
+                    if !self.previous.contains_key(&key)
+                        && !cleared.iter().any(|prefix| key.starts_with(prefix))
+                        && !delete_keys.contains(&key)
+                    {
+                        delete_keys.push(key);
+                    }
+                }
+            }
+        }
+
+        if !delete_keys.is_empty() {
+            updates.push(UpdateSettings::Delete { keys: delete_keys });
+        }
+
+        let mut values = self
+            .previous
+            .iter()
+            .filter(|(key, _)| !self.masked.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        if !values.is_empty() {
+            values.sort_unstable();
+            updates.push(UpdateSettings::Insert {
+                prefix: None,
+                values,
+                assert_empty: false,
+            });
+        }
+
+        updates
+    }
+}
+
+impl UpdateSettings {
+    fn affects(&self, key: &str) -> bool {
+        match self {
+            UpdateSettings::Delete { keys } => keys.iter().any(|k| k == key),
+            UpdateSettings::Clear { prefix } => key.starts_with(prefix.as_str()),
+            UpdateSettings::Insert { prefix, values, .. } => {
+                values.iter().any(|(k, _)| full_key(prefix, k) == key)
+            }
+        }
+    }
+}
+
+fn full_key(prefix: &Option<String>, key: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}.{key}"),
+        None => key.to_string(),
+    }
+}
+
+#[component]
+pub fn SettingsHistory() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let schemas = expect_context::<Arc<Schemas>>();
+
+    let revisions = create_rw_signal(Revision::load_all());
+    let expanded = create_rw_signal(None::<u64>);
+    let schemas_ = schemas.clone();
+    let is_secret: Arc<dyn Fn(&str) -> bool> = Arc::new(move |key: &str| schemas_.is_secret(key));
+
+    let revert_action = create_action(move |revision: &Arc<Revision>| {
+        let revision = revision.clone();
+        let auth = auth.get();
+        let schemas = schemas.clone();
+
+        async move {
+            match update_settings(
+                &auth,
+                &schemas,
+                format!("Reverted revision #{}", revision.id),
+                Arc::new(revision.inverse()),
+            )
+            .await
+            {
+                Ok(_) => {
+                    revisions.set(Revision::load_all());
+                    alert.set(Alert::success(format!(
+                        "Revision #{} reverted. Reload the configuration to apply the changes.",
+                        revision.id
+                    )));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+    let revert = Callback::new(move |revision: Arc<Revision>| {
+        if !revision.is_revertable() {
+            alert.set(Alert::warning(format!(
+                "Revision #{} cannot be reverted as it cleared secret values that are not stored in the history.",
+                revision.id
+            )));
+            return;
+        }
+        let secrets = if !revision.masked.is_empty() {
+            " Secret values are not stored in the history and will be left as is."
+        } else {
+            ""
+        };
+        modal.set(
+            Modal::with_title("Confirm revert")
+                .with_message(format!(
+                    "Are you sure you want to revert revision #{}? The affected keys will be restored to the values they had before {}.{secrets}",
+                    revision.id,
+                    revision.timestamp.format_date_time()
+                ))
+                .with_button("Revert")
+                .with_dangerous_callback(move || {
+                    revert_action.dispatch(revision.clone());
+                }),
+        )
+    });
+
+    view! {
+        <ListSection>
+            <ListTable
+                title="History"
+                subtitle="Configuration changes saved from this browser"
+            >
+                <Toolbar slot>
+                    <ToolbarButton
+                        text="Clear history"
+                        color=Color::Red
+                        on_click=Callback::new(move |_| {
+                            modal
+                                .set(
+                                    Modal::with_title("Confirm deletion")
+                                        .with_message(
+                                            "Are you sure you want to delete all revisions? This action cannot be undone.",
+                                        )
+                                        .with_button("Clear history")
+                                        .with_dangerous_callback(move || {
+                                            LocalStorage::delete(STATE_HISTORY_KEY);
+                                            revisions.set(Vec::new());
+                                        }),
+                                )
+                        })
+                    >
+
+                        <IconTrash/>
+                    </ToolbarButton>
+                </Toolbar>
+
+                {move || {
+                    let is_secret = is_secret.clone();
+                    if !revisions.with(|revisions| revisions.is_empty()) {
+                        view! {
+                            <ColumnList headers=vec![
+                                "Revision".to_string(),
+                                "Date".to_string(),
+                                "User".to_string(),
+                                "Description".to_string(),
+                                "Changes".to_string(),
+                                "".to_string(),
+                            ]>
+
+                                <For
+                                    each=move || {
+                                        revisions.get().into_iter().map(Arc::new).collect::<Vec<_>>()
+                                    }
+
+                                    key=|revision| revision.id
+                                    children=move |revision| {
+                                        let is_secret = is_secret.clone();
+                                        view! {
+                                            <RevisionItem revision expanded revert is_secret/>
+                                        }
+                                    }
+                                />
+
+                            </ColumnList>
+                        }
+                            .into_view()
+                    } else {
+                        view! {
+                            <ZeroResults
+                                title="No revisions"
+                                subtitle="Configuration changes saved from this browser will be listed here."
+                            />
+                        }
+                            .into_view()
+                    }
+                }}
+
+                <Footer slot>
+                    <div></div>
+                </Footer>
+            </ListTable>
+        </ListSection>
+    }
+}
+
+#[component]
+fn RevisionItem(
+    revision: Arc<Revision>,
+    expanded: RwSignal<Option<u64>>,
+    revert: Callback<Arc<Revision>>,
+    is_secret: Arc<dyn Fn(&str) -> bool>,
+) -> impl IntoView {
+    let id = revision.id;
+    let diff = Arc::new(revision.diff());
+    let changes = [
+        (diff.added.len(), "added"),
+        (diff.changed.len(), "changed"),
+        (diff.deleted.len(), "deleted"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, action)| format!("{count} {action}"))
+    .collect::<Vec<_>>()
+    .join(", ");
+    let is_expanded = create_memo(move |_| expanded.get() == Some(id));
+    let timestamp = revision.timestamp;
+    let username = revision.username.clone();
+    let description = revision.description.clone();
+
+    view! {
+        <tr>
+            <ListTextItem>{format!("#{id}")}</ListTextItem>
+            <ListTextItem>
+                <span title=timestamp.format_date_time()>
+                    {HumanTime::from(timestamp).to_string()}
+                </span>
+            </ListTextItem>
+            <ListTextItem>{username}</ListTextItem>
+            <ListTextItem>{description}</ListTextItem>
+            <ListTextItem>
+                {if !changes.is_empty() { changes } else { "No changes".to_string() }}
+            </ListTextItem>
+            <ListItem subclass="px-6 py-1.5 flex justify-end gap-x-3">
+                <button
+                    type="button"
+                    class="inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                    on:click=move |_| {
+                        expanded.update(|expanded| {
+                            *expanded = if *expanded == Some(id) { None } else { Some(id) };
+                        });
+                    }
+                >
+
+                    {move || if is_expanded.get() { "Hide" } else { "Inspect" }}
+                </button>
+                <button
+                    type="button"
+                    class="inline-flex items-center gap-x-1 text-sm text-red-600 decoration-2 hover:underline font-medium dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                    on:click=move |_| revert.call(revision.clone())
+                >
+                    <IconArrowLeft size=16 attr:class="flex-shrink-0 size-3"/>
+                    Revert
+                </button>
+            </ListItem>
+        </tr>
+        <Show when=move || is_expanded.get()>
+            <tr class="bg-gray-50 dark:bg-slate-800">
+                <td colspan="6" class="px-6 pb-4">
+                    {
+                        let is_secret = is_secret.clone();
+                        settings_diff(&diff, move |key| is_secret(key))
+                    }
+                </td>
+            </tr>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn apply(settings: &Settings, updates: &[UpdateSettings]) -> Settings {
+        let mut settings = settings.clone();
+        for update in updates {
+            match update {
+                UpdateSettings::Delete { keys } => {
+                    for key in keys {
+                        settings.remove(key);
+                    }
+                }
+                UpdateSettings::Clear { prefix } => {
+                    settings.retain(|key, _| !key.starts_with(prefix.as_str()));
+                }
+                UpdateSettings::Insert { prefix, values, .. } => {
+                    for (key, value) in values {
+                        settings.insert(full_key(prefix, key), value.clone());
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    fn revision(original: &Settings, changes: Vec<UpdateSettings>) -> Revision {
+        Revision {
+            id: 1,
+            timestamp: Utc::now(),
+            username: "admin".to_string(),
+            description: "Test".to_string(),
+            previous: original
+                .iter()
+                .filter(|(key, _)| changes.iter().any(|change| change.affects(key)))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            changes,
+            masked: Vec::new(),
+        }
+    }
+
+    fn assert_reverts(original: &Settings, changes: Vec<UpdateSettings>) {
+        let revision = revision(original, changes);
+        let updated = apply(original, &revision.changes);
+        assert_ne!(&updated, original);
+        assert_eq!(&apply(&updated, &revision.inverse()), original);
+    }
+
+    #[test]
+    fn inverse_of_insert() {
+        let original = settings(&[("server.hostname", "mx.example.org")]);
+        assert_reverts(
+            &original,
+            vec![UpdateSettings::Insert {
+                prefix: Some("server".to_string()),
+                values: vec![
+                    ("hostname".to_string(), "mail.example.org".to_string()),
+                    ("max-connections".to_string(), "8192".to_string()),
+                ],
+                assert_empty: false,
+            }],
+        );
+
+        let inverse = revision(
+            &original,
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("tracer.level".to_string(), "info".to_string())],
+                assert_empty: true,
+            }],
+        )
+        .inverse();
+        assert!(matches!(
+            inverse.as_slice(),
+            [UpdateSettings::Delete { keys }] if keys == &["tracer.level".to_string()]
+        ));
+    }
+
+    #[test]
+    fn inverse_of_delete() {
+        let original = settings(&[
+            ("queue.retry", "5m"),
+            ("queue.notify", "1d"),
+            ("server.hostname", "mx.example.org"),
+        ]);
+        assert_reverts(
+            &original,
+            vec![UpdateSettings::Delete {
+                keys: vec!["queue.retry".to_string(), "queue.notify".to_string()],
+            }],
+        );
+    }
+
+    #[test]
+    fn inverse_of_clear_and_insert() {
+        let original = settings(&[
+            ("listener.smtp.bind.0", "[::]:25"),
+            ("listener.smtp.bind.1", "[::]:2525"),
+            ("listener.smtp.protocol", "smtp"),
+            ("listener.imap.protocol", "imap"),
+        ]);
+        assert_reverts(
+            &original,
+            vec![
+                UpdateSettings::Clear {
+                    prefix: "listener.smtp.".to_string(),
+                },
+                UpdateSettings::Insert {
+                    prefix: Some("listener.smtp".to_string()),
+                    values: vec![
+                        ("bind.0".to_string(), "[::]:587".to_string()),
+                        ("tls.implicit".to_string(), "false".to_string()),
+                    ],
+                    assert_empty: false,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn inverse_skips_masked_secrets() {
+        let original = settings(&[
+            ("directory.ldap.bind.secret", SECRET_PLACEHOLDER),
+            ("directory.ldap.url", "ldap://localhost"),
+        ]);
+        let mut revision = revision(
+            &original,
+            vec![UpdateSettings::Insert {
+                prefix: Some("directory.ldap".to_string()),
+                values: vec![
+                    ("bind.secret".to_string(), SECRET_PLACEHOLDER.to_string()),
+                    ("url".to_string(), "ldaps://localhost".to_string()),
+                ],
+                assert_empty: false,
+            }],
+        );
+        revision.masked = vec!["directory.ldap.bind.secret".to_string()];
+        assert!(revision.is_revertable());
+        assert!(matches!(
+            revision.inverse().as_slice(),
+            [UpdateSettings::Insert { values, .. }]
+                if values == &[("directory.ldap.url".to_string(), "ldap://localhost".to_string())]
+        ));
+
+        revision.changes.insert(
+            0,
+            UpdateSettings::Clear {
+                prefix: "directory.ldap.".to_string(),
+            },
+        );
+        assert!(!revision.is_revertable());
+    }
+}
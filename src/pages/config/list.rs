@@ -49,7 +49,7 @@ use crate::{
         url::UrlBuilder,
     },
     pages::{
-        config::{ReloadSettings, SchemaType, Schemas, SettingsValues},
+        config::{history::update_settings, ReloadSettings, SchemaType, Schemas, SettingsValues},
         maybe_plural, List,
     },
 };
@@ -59,6 +59,7 @@ use super::{Schema, Settings, UpdateSettings};
 #[component]
 pub fn SettingsList() -> impl IntoView {
     let schemas = expect_context::<Arc<Schemas>>();
+    let schemas_ = schemas.clone();
     let query = use_query_map();
     let page = create_memo(move |_| {
         query
@@ -147,6 +148,7 @@ pub fn SettingsList() -> impl IntoView {
     let delete_action = create_action(move |items: &Arc<HashSet<String>>| {
         let items = items.clone();
         let auth = auth.get();
+        let schemas = schemas_.clone();
         let schema = current_schema.get();
 
         async move {
@@ -169,13 +171,11 @@ pub fn SettingsList() -> impl IntoView {
                 }
             }
 
-            match HttpRequest::post("/api/settings")
-                .with_authorization(&auth)
-                .with_body(updates)
-                .unwrap()
-                .send::<()>()
-                .await
-            {
+            let description = format!(
+                "Deleted {}",
+                maybe_plural(items.len(), schema.name_singular, schema.name_plural)
+            );
+            match update_settings(&auth, &schemas, description, Arc::new(updates)).await {
                 Ok(_) => {
                     settings.refetch();
                     alert.set(Alert::success(format!(
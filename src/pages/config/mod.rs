@@ -22,9 +22,14 @@
 */
 
 pub mod edit;
+pub mod history;
 pub mod list;
+pub mod raw;
+pub mod reload;
 pub mod schema;
 pub mod search;
+pub mod transfer;
+pub mod wizard;
 
 use std::{collections::BTreeMap, str::FromStr};
 
@@ -32,8 +37,9 @@ use crate::{
     components::{
         form::input::{Duration, Rate},
         icon::{
-            IconCircleStack, IconCodeBracket, IconInbox, IconInboxArrowDown, IconInboxStack,
-            IconKey, IconServerStack, IconShieldCheck,
+            IconArrowRightCircle, IconArrowsUpDown, IconCircleStack, IconClock, IconCodeBracket,
+            IconInbox, IconInboxArrowDown, IconInboxStack, IconKey, IconServerStack,
+            IconShieldCheck, IconWrench,
         },
         layout::{LayoutBuilder, MenuItem},
     },
@@ -49,7 +55,7 @@ use serde::{Deserialize, Serialize};
 
 pub type Settings = AHashMap<String, String>;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum UpdateSettings {
     Delete {
@@ -196,6 +202,125 @@ impl FormData {
 
         updates
     }
+
+    pub fn describe_update(&self) -> String {
+        let action = if self.is_update { "Updated" } else { "Created" };
+        match &self.schema.typ {
+            SchemaType::Record { .. } | SchemaType::Entry { .. } => format!(
+                "{action} {} {:?}",
+                self.schema.name_singular,
+                self.value_as_str("_id").unwrap_or_default()
+            ),
+            SchemaType::List => format!("Updated {}", self.schema.form.title),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SettingsDiff {
+    pub added: Vec<(String, String)>,
+    pub changed: Vec<(String, String, String)>,
+    pub deleted: Vec<(String, String)>,
+    pub cleared: Vec<String>,
+}
+
+impl SettingsDiff {
+    // `original` is keyed by full keys
+    pub fn new(original: &Settings, updates: &[UpdateSettings]) -> Self {
+        let mut diff = SettingsDiff::default();
+        let mut current = original.clone();
+
+        for update in updates {
+            match update {
+                UpdateSettings::Delete { keys } => {
+                    for key in keys {
+                        current.remove(key);
+                    }
+                }
+                UpdateSettings::Clear { prefix } => {
+                    current.retain(|key, _| !key.starts_with(prefix.as_str()));
+                    diff.cleared.push(prefix.clone());
+                }
+                UpdateSettings::Insert { prefix, values, .. } => {
+                    for (key, value) in values {
+                        let key = match prefix {
+                            Some(prefix) => format!("{prefix}.{key}"),
+                            None => key.clone(),
+                        };
+                        current.insert(key, value.clone());
+                    }
+                }
+            }
+        }
+
+        for (key, value) in &current {
+            match original.get(key) {
+                Some(old_value) if old_value != value => {
+                    diff.changed
+                        .push((key.clone(), old_value.clone(), value.clone()));
+                }
+                Some(_) => {}
+                None => diff.added.push((key.clone(), value.clone())),
+            }
+        }
+        for (key, value) in original {
+            if !current.contains_key(key) {
+                diff.deleted.push((key.clone(), value.clone()));
+            }
+        }
+
+        diff.added.sort_unstable();
+        diff.changed.sort_unstable();
+        diff.deleted.sort_unstable();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.deleted.is_empty()
+    }
+}
+
+impl Schema {
+    pub fn prefix_len(&self) -> usize {
+        match self.typ {
+            SchemaType::Record { prefix, .. } | SchemaType::Entry { prefix } => prefix.len(),
+            SchemaType::List => 0,
+        }
+    }
+
+    // `key` is relative to the object
+    pub fn has_field(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+            || key
+                .char_indices()
+                .filter(|(_, ch)| *ch == '.')
+                .any(|(pos, _)| self.fields.contains_key(&key[..pos]))
+    }
+
+    pub fn claims_key(&self, key: &str) -> bool {
+        match self.typ {
+            SchemaType::Record { prefix, .. } => key
+                .strip_prefix(prefix)
+                .and_then(|key| key.strip_prefix('.'))
+                .and_then(|key| key.split_once('.'))
+                .map_or(false, |(_, key)| self.has_field(key)),
+            SchemaType::Entry { prefix } => key
+                .strip_prefix(prefix)
+                .map_or(false, |key| key.len() > 1 && key.starts_with('.')),
+            SchemaType::List => self.has_field(key),
+        }
+    }
+}
+
+impl Schemas {
+    pub fn claims_key(&self, key: &str) -> bool {
+        self.schemas.values().any(|schema| schema.claims_key(key))
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.schemas.values().any(|schema| schema.is_secret(key))
+    }
 }
 
 pub trait SettingsValues {
@@ -510,6 +635,143 @@ impl LayoutBuilder {
             .route("/script")
             .insert()
             .insert()
+            // Setup Wizard
+            .create("Setup Wizard")
+            .icon(view! { <IconArrowRightCircle/> })
+            .route("/setup")
+            .insert()
+            // Raw Settings
+            .create("Raw Settings")
+            .icon(view! { <IconWrench/> })
+            .route("/raw")
+            .insert()
+            // Import & Export
+            .create("Import & Export")
+            .icon(view! { <IconArrowsUpDown/> })
+            .route("/transfer")
+            .insert()
+            // History
+            .create("History")
+            .icon(view! { <IconClock/> })
+            .route("/history")
+            .insert()
             .menu_items
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn insert(prefix: Option<&str>, values: &[(&str, &str)]) -> UpdateSettings {
+        UpdateSettings::Insert {
+            prefix: prefix.map(|prefix| prefix.to_string()),
+            values: values
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            assert_empty: false,
+        }
+    }
+
+    #[test]
+    fn diff_added_changed_deleted() {
+        let original = settings(&[
+            ("server.hostname", "mx.example.org"),
+            ("server.port", "25"),
+            ("queue.retry", "5m"),
+        ]);
+        let diff = SettingsDiff::new(
+            &original,
+            &[
+                UpdateSettings::Delete {
+                    keys: vec!["queue.retry".to_string()],
+                },
+                insert(
+                    Some("server"),
+                    &[("hostname", "mail.example.org"), ("port", "25")],
+                ),
+                insert(None, &[("tracer.level", "info")]),
+            ],
+        );
+
+        assert_eq!(
+            diff,
+            SettingsDiff {
+                added: vec![("tracer.level".to_string(), "info".to_string())],
+                changed: vec![(
+                    "server.hostname".to_string(),
+                    "mx.example.org".to_string(),
+                    "mail.example.org".to_string()
+                )],
+                deleted: vec![("queue.retry".to_string(), "5m".to_string())],
+                cleared: vec![],
+            }
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn diff_clear_and_reinsert() {
+        let original = settings(&[
+            ("listener.smtp.bind.0", "[::]:25"),
+            ("listener.smtp.bind.1", "[::]:2525"),
+            ("listener.smtp.protocol", "smtp"),
+        ]);
+        let diff = SettingsDiff::new(
+            &original,
+            &[
+                UpdateSettings::Clear {
+                    prefix: "listener.smtp.".to_string(),
+                },
+                insert(
+                    Some("listener.smtp"),
+                    &[("bind.0", "[::]:25"), ("protocol", "smtp")],
+                ),
+            ],
+        );
+
+        assert_eq!(diff.added, vec![]);
+        assert_eq!(diff.changed, vec![]);
+        assert_eq!(
+            diff.deleted,
+            vec![("listener.smtp.bind.1".to_string(), "[::]:2525".to_string())]
+        );
+        assert_eq!(diff.cleared, vec!["listener.smtp.".to_string()]);
+    }
+
+    #[test]
+    fn diff_no_changes() {
+        let original = settings(&[("server.hostname", "mx.example.org")]);
+        let diff = SettingsDiff::new(
+            &original,
+            &[insert(None, &[("server.hostname", "mx.example.org")])],
+        );
+
+        assert!(diff.is_empty());
+        assert!(SettingsDiff::new(&original, &[]).is_empty());
+    }
+
+    #[test]
+    fn diff_sorted_output() {
+        let diff = SettingsDiff::new(
+            &Settings::default(),
+            &[insert(None, &[("c", "3"), ("a", "1"), ("b", "2")])],
+        );
+
+        assert_eq!(
+            diff.added
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+    }
+}
@@ -0,0 +1,587 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use leptos::*;
+use leptos_router::{use_navigate, use_query_map};
+use serde::Deserialize;
+
+use crate::{
+    components::{
+        badge::Badge,
+        icon::{IconAdd, IconRefresh},
+        list::{
+            header::ColumnList,
+            toolbar::{SearchBox, ToolbarButton},
+            Footer, ListItem, ListSection, ListTable, ListTextItem, Toolbar, ZeroResults,
+        },
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        url::UrlBuilder,
+    },
+    pages::{
+        config::{history::update_settings, Schemas, Settings, UpdateSettings},
+        maybe_plural,
+    },
+};
+
+#[derive(Clone, Deserialize, Default)]
+struct FetchSettings {
+    items: Settings,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum RawEntry {
+    Folder {
+        key: String,
+        total: usize,
+        orphans: usize,
+    },
+    Key {
+        key: String,
+        value: String,
+        is_orphan: bool,
+        is_secret: bool,
+    },
+}
+
+#[component]
+pub fn SettingsRaw() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let query = use_query_map();
+
+    let prefix = create_memo(move |_| {
+        query.with(|q| {
+            q.get("prefix")
+                .map(|s| s.trim().trim_matches('.').to_string())
+                .unwrap_or_default()
+        })
+    });
+    let filter = create_memo(move |_| {
+        query.with(|q| {
+            q.get("filter").and_then(|s| {
+                let s = s.trim();
+                if !s.is_empty() {
+                    Some(s.to_string())
+                } else {
+                    None
+                }
+            })
+        })
+    });
+    let edit_key = create_rw_signal(String::new());
+    let edit_value = create_rw_signal(String::new());
+
+    let settings = create_resource(
+        move || prefix.get(),
+        move |prefix| {
+            let auth = auth.get_untracked();
+
+            async move {
+                HttpRequest::get("/api/settings/list")
+                    .with_authorization(&auth)
+                    .with_parameter("prefix", &prefix)
+                    .send::<FetchSettings>()
+                    .await
+                    .map(|list| {
+                        if !prefix.is_empty() {
+                            list.items
+                                .into_iter()
+                                .map(|(key, value)| (format!("{prefix}.{key}"), value))
+                                .collect::<BTreeMap<_, _>>()
+                        } else {
+                            list.items.into_iter().collect::<BTreeMap<_, _>>()
+                        }
+                    })
+            }
+        },
+    );
+
+    let schemas_ = schemas.clone();
+    let apply_changes = create_action(
+        move |(changes, description): &(Arc<Vec<UpdateSettings>>, String)| {
+            let changes = changes.clone();
+            let description = description.clone();
+            let auth = auth.get();
+            let schemas = schemas_.clone();
+
+            async move {
+                match update_settings(&auth, &schemas, description.clone(), changes).await {
+                    Ok(_) => {
+                        settings.refetch();
+                        alert.set(Alert::success(format!("{description}.")));
+                    }
+                    Err(http::Error::Unauthorized) => {
+                        use_navigate()("/login", Default::default());
+                    }
+                    Err(err) => {
+                        alert.set(Alert::from(err));
+                    }
+                }
+            }
+        },
+    );
+
+    let edit_entry = Callback::new(move |entry: Option<(String, String, bool)>| {
+        let is_create = entry.is_none();
+        let (key, value, is_secret) = entry.unwrap_or_else(|| {
+            let prefix = prefix.get();
+            (
+                if !prefix.is_empty() {
+                    format!("{prefix}.")
+                } else {
+                    String::new()
+                },
+                String::new(),
+                false,
+            )
+        });
+        edit_key.set(key);
+        edit_value.set(value);
+
+        modal.set(
+            Modal::with_title(if is_create { "Create key" } else { "Edit key" })
+                .with_content(move || {
+                    view! {
+                        <div class="space-y-3">
+                            <input
+                                type="text"
+                                class="py-2 px-3 block w-full font-mono border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                placeholder="Key"
+                                prop:value=move || edit_key.get()
+                                disabled=!is_create
+                                on:input=move |ev| edit_key.set(event_target_value(&ev))
+                            />
+                            <textarea
+                                class="py-3 px-4 block w-full font-mono border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                                rows="6"
+                                placeholder=if is_secret {
+                                    "Leave blank to keep the current value"
+                                } else {
+                                    "Value"
+                                }
+                                prop:value=move || edit_value.get()
+                                on:input=move |ev| edit_value.set(event_target_value(&ev))
+                            ></textarea>
+                        </div>
+                    }
+                    .into_view()
+                })
+                .with_button(if is_create { "Create" } else { "Save" })
+                .with_callback(move || {
+                    let key = edit_key.get().trim().trim_matches('.').to_string();
+                    if key.is_empty() || key.contains(char::is_whitespace) {
+                        alert.set(Alert::error(format!("Invalid key {key:?}")));
+                        return;
+                    }
+                    let value = edit_value.get();
+                    if is_secret && value.is_empty() {
+                        return;
+                    }
+                    let description = format!(
+                        "{} key {key:?}",
+                        if is_create { "Created" } else { "Updated" }
+                    );
+                    apply_changes.dispatch((
+                        Arc::new(vec![UpdateSettings::Insert {
+                            prefix: None,
+                            values: vec![(key, value)],
+                            assert_empty: is_create,
+                        }]),
+                        description,
+                    ));
+                }),
+        );
+    });
+
+    let delete_entry = Callback::new(move |entry: RawEntry| {
+        let (message, changes, description) = match entry {
+            RawEntry::Folder { key, total, .. } => (
+                format!(
+                    "Are you sure you want to delete {} under {key:?}? This action cannot be undone.",
+                    maybe_plural(total, "key", "keys")
+                ),
+                UpdateSettings::Clear {
+                    prefix: format!("{key}."),
+                },
+                format!("Deleted {} under {key:?}", maybe_plural(total, "key", "keys")),
+            ),
+            RawEntry::Key { key, .. } => (
+                format!("Are you sure you want to delete {key:?}? This action cannot be undone."),
+                UpdateSettings::Delete {
+                    keys: vec![key.clone()],
+                },
+                format!("Deleted key {key:?}"),
+            ),
+        };
+        let changes = Arc::new(vec![changes]);
+
+        modal.set(
+            Modal::with_title("Confirm deletion")
+                .with_message(message)
+                .with_button("Delete")
+                .with_dangerous_callback(move || {
+                    apply_changes.dispatch((changes.clone(), description.clone()));
+                }),
+        );
+    });
+
+    view! {
+        <ListSection>
+            <ListTable
+                title="Raw settings"
+                subtitle=Signal::derive(move || {
+                    let prefix = prefix.get();
+                    if !prefix.is_empty() {
+                        format!("Browsing keys under {prefix:?}")
+                    } else {
+                        "Browse and edit any key stored on the server".to_string()
+                    }
+                })
+            >
+
+                <Toolbar slot>
+                    <SearchBox
+                        value=filter
+                        on_search=move |value| {
+                            use_navigate()(
+                                &UrlBuilder::new("/settings/raw")
+                                    .with_optional_parameter(
+                                        "prefix",
+                                        Some(prefix.get()).filter(|p| !p.is_empty()),
+                                    )
+                                    .with_parameter("filter", value)
+                                    .finish(),
+                                Default::default(),
+                            );
+                        }
+                    />
+
+                    <ToolbarButton
+                        text="Refresh"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            settings.refetch();
+                        })
+                    >
+
+                        <IconRefresh/>
+                    </ToolbarButton>
+
+                    <ToolbarButton
+                        text="Create key"
+                        color=Color::Blue
+                        on_click=Callback::new(move |_| {
+                            edit_entry.call(None);
+                        })
+                    >
+
+                        <IconAdd size=16 attr:class="flex-shrink-0 size-3"/>
+                    </ToolbarButton>
+
+                </Toolbar>
+
+                <Transition fallback=Skeleton>
+                    {
+                        let schemas = schemas.clone();
+                        move || match settings.get() {
+                            None => None,
+                            Some(Err(http::Error::Unauthorized)) => {
+                                use_navigate()("/login", Default::default());
+                                Some(view! { <div></div> }.into_view())
+                            }
+                            Some(Err(err)) => {
+                                alert.set(Alert::from(err));
+                                Some(view! { <Skeleton/> }.into_view())
+                            }
+                            Some(Ok(settings)) => {
+                                let prefix = prefix.get();
+                                let entries = raw_entries(&schemas, &prefix, settings, filter.get());
+                                let parent = (!prefix.is_empty())
+                                    .then(|| {
+                                        let url = UrlBuilder::new("/settings/raw")
+                                            .with_optional_parameter(
+                                                "prefix",
+                                                prefix.rsplit_once('.').map(|(parent, _)| parent),
+                                            )
+                                            .finish();
+                                        view! {
+                                            <tr>
+                                                <ListItem>
+                                                    <a
+                                                        class="text-sm font-mono text-blue-600 decoration-2 hover:underline dark:text-blue-500"
+                                                        href=url
+                                                    >
+                                                        ".."
+                                                    </a>
+                                                </ListItem>
+                                                <ListTextItem>""</ListTextItem>
+                                                <ListTextItem>""</ListTextItem>
+                                            </tr>
+                                        }
+                                    });
+                                if entries.is_empty() && parent.is_none() {
+                                    return Some(
+                                        view! {
+                                            <ZeroResults
+                                                title="No results"
+                                                subtitle="Your search did not yield any results."
+                                            />
+                                        }
+                                            .into_view(),
+                                    );
+                                }
+                                Some(
+                                    view! {
+                                        <ColumnList headers=vec![
+                                            "Key".to_string(),
+                                            "Value".to_string(),
+                                            "".to_string(),
+                                        ]>
+                                            {parent}
+                                            {entries
+                                                .into_iter()
+                                                .map(|entry| {
+                                                    view! {
+                                                        <RawEntryItem
+                                                            entry
+                                                            on_edit=edit_entry
+                                                            on_delete=delete_entry
+                                                        />
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </ColumnList>
+                                    }
+                                        .into_view(),
+                                )
+                            }
+                        }
+                    }
+
+                </Transition>
+
+                <Footer slot>
+                    <div></div>
+                </Footer>
+            </ListTable>
+        </ListSection>
+    }
+}
+
+#[component]
+fn RawEntryItem(
+    entry: RawEntry,
+    on_edit: Callback<Option<(String, String, bool)>>,
+    on_delete: Callback<RawEntry>,
+) -> impl IntoView {
+    let entry_ = entry.clone();
+    let on_delete = move |_| on_delete.call(entry_.clone());
+
+    match entry {
+        RawEntry::Folder {
+            key,
+            total,
+            orphans,
+        } => {
+            let url = UrlBuilder::new("/settings/raw")
+                .with_parameter("prefix", &key)
+                .finish();
+            let name = key.rsplit_once('.').map_or(key.as_str(), |(_, name)| name);
+            let name = format!("{name}.");
+
+            view! {
+                <tr>
+                    <ListItem>
+                        <a
+                            class="text-sm font-mono text-blue-600 decoration-2 hover:underline dark:text-blue-500"
+                            href=url
+                        >
+                            {name}
+                        </a>
+                    </ListItem>
+                    <ListItem>
+                        <span class="text-sm text-gray-500">{maybe_plural(total, "key", "keys")}</span>
+                        {(orphans > 0)
+                            .then(|| {
+                                view! {
+                                    <span class="ms-2">
+                                        <Badge color=Color::Yellow>
+                                            {maybe_plural(orphans, "orphan", "orphans")}
+                                        </Badge>
+                                    </span>
+                                }
+                            })}
+                    </ListItem>
+                    <ListItem subclass="px-6 py-1.5 flex justify-end gap-x-3">
+                        <button
+                            type="button"
+                            class="text-sm text-red-600 decoration-2 hover:underline font-medium"
+                            on:click=on_delete
+                        >
+                            Delete
+                        </button>
+                    </ListItem>
+                </tr>
+            }
+        }
+        RawEntry::Key {
+            key,
+            value,
+            is_orphan,
+            is_secret,
+        } => {
+            let display_value = if is_secret {
+                "********".to_string()
+            } else if value.chars().count() > 80 {
+                format!("{}…", value.chars().take(80).collect::<String>())
+            } else {
+                value.clone()
+            };
+            let name = key.clone();
+            // Secrets are never sent back to the edit form
+            let edit = if is_secret {
+                (key, String::new(), true)
+            } else {
+                (key, value, false)
+            };
+            let row_class = if is_orphan {
+                "bg-yellow-50 dark:bg-yellow-800/10"
+            } else {
+                ""
+            };
+
+            view! {
+                <tr class=row_class>
+                    <ListItem>
+                        <span class="text-sm font-mono text-gray-800 dark:text-gray-200">{name}</span>
+                        {is_orphan
+                            .then(|| {
+                                view! {
+                                    <span class="ms-2">
+                                        <Badge color=Color::Yellow>Orphan</Badge>
+                                    </span>
+                                }
+                            })}
+                    </ListItem>
+                    <ListItem>
+                        <span class="text-sm font-mono text-gray-500 whitespace-pre">{display_value}</span>
+                    </ListItem>
+                    <ListItem subclass="px-6 py-1.5 flex justify-end gap-x-3">
+                        <button
+                            type="button"
+                            class="text-sm text-blue-600 decoration-2 hover:underline font-medium"
+                            on:click=move |_| on_edit.call(Some(edit.clone()))
+                        >
+                            Edit
+                        </button>
+                        <button
+                            type="button"
+                            class="text-sm text-red-600 decoration-2 hover:underline font-medium"
+                            on:click=on_delete
+                        >
+                            Delete
+                        </button>
+                    </ListItem>
+                </tr>
+            }
+        }
+    }
+}
+
+// Lists the direct children of the prefix, or all matches when filtering
+fn raw_entries(
+    schemas: &Schemas,
+    prefix: &str,
+    settings: BTreeMap<String, String>,
+    filter: Option<String>,
+) -> Vec<RawEntry> {
+    let is_secret = |key: &str| schemas.schemas.values().any(|schema| schema.is_secret(key));
+
+    if let Some(filter) = filter.map(|f| f.to_lowercase()) {
+        return settings
+            .into_iter()
+            .filter(|(key, value)| {
+                key.to_lowercase().contains(&filter)
+                    || (!is_secret(key) && value.to_lowercase().contains(&filter))
+            })
+            .map(|(key, value)| RawEntry::Key {
+                is_orphan: !schemas.claims_key(&key),
+                is_secret: is_secret(&key),
+                key,
+                value,
+            })
+            .collect();
+    }
+
+    let mut folders: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut keys = Vec::new();
+    let skip = if prefix.is_empty() {
+        0
+    } else {
+        prefix.len() + 1
+    };
+
+    for (key, value) in settings {
+        let is_orphan = !schemas.claims_key(&key);
+        match key.get(skip..).and_then(|name| name.split_once('.')) {
+            Some((name, _)) => {
+                let folder = folders
+                    .entry(key[..skip + name.len()].to_string())
+                    .or_default();
+                folder.0 += 1;
+                folder.1 += is_orphan as usize;
+            }
+            None => {
+                keys.push(RawEntry::Key {
+                    is_secret: is_secret(&key),
+                    key,
+                    value,
+                    is_orphan,
+                });
+            }
+        }
+    }
+
+    folders
+        .into_iter()
+        .map(|(key, (total, orphans))| RawEntry::Folder {
+            key,
+            total,
+            orphans,
+        })
+        .chain(keys)
+        .collect()
+}
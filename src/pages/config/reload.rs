@@ -0,0 +1,397 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{badge::Badge, Color},
+    core::{
+        form::{ExpressionError, FormData, FormError, FormErrorType},
+        schema::{Field, Schema, SchemaType, Schemas, Type},
+    },
+};
+
+use super::{ConfigError, ConfigWarning, ReloadSettings};
+
+#[derive(Debug, Clone)]
+pub struct SettingLocation {
+    pub schema: Arc<Schema>,
+    pub id: Option<String>,
+    pub field: Arc<Field>,
+    pub subkey: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReloadIssue {
+    pub key: String,
+    pub kind: &'static str,
+    pub message: String,
+    pub is_error: bool,
+    pub location: Option<SettingLocation>,
+}
+
+// Reload errors waiting to be shown in the edit form of their object
+#[derive(Debug, Clone, Default)]
+pub struct PendingErrors {
+    issues: Vec<ReloadIssue>,
+}
+
+pub fn init_pending_errors() {
+    provide_context(create_rw_signal(PendingErrors::default()));
+}
+
+pub fn use_pending_errors() -> RwSignal<PendingErrors> {
+    expect_context::<RwSignal<PendingErrors>>()
+}
+
+impl PendingErrors {
+    pub fn take(&mut self, schema: &Schema, id: Option<&str>) -> Vec<ReloadIssue> {
+        let (issues, pending) = std::mem::take(&mut self.issues)
+            .into_iter()
+            .partition(|issue| {
+                issue.location.as_ref().map_or(false, |location| {
+                    location.schema.id == schema.id && location.id.as_deref() == id
+                })
+            });
+        self.issues = pending;
+        issues
+    }
+}
+
+impl ReloadSettings {
+    // Errors first
+    pub fn issues(&self, schemas: &Schemas) -> Vec<ReloadIssue> {
+        self.errors
+            .iter()
+            .map(|(key, error)| {
+                let (kind, message) = match error {
+                    ConfigError::Parse { error } => ("Parse error", error.clone()),
+                    ConfigError::Build { error } => ("Build error", error.clone()),
+                    ConfigError::Macro { error } => ("Macro error", error.clone()),
+                };
+                ReloadIssue {
+                    key: key.clone(),
+                    kind,
+                    message,
+                    is_error: true,
+                    location: schemas.locate_key(key),
+                }
+            })
+            .chain(self.warnings.iter().map(|(key, warning)| {
+                let message = match warning {
+                    ConfigWarning::Missing => "Missing setting".to_string(),
+                    ConfigWarning::AppliedDefault { default } => {
+                        format!("Applied default value {default:?}")
+                    }
+                };
+                ReloadIssue {
+                    key: key.clone(),
+                    kind: "Warning",
+                    message,
+                    is_error: false,
+                    location: schemas.locate_key(key),
+                }
+            }))
+            .collect()
+    }
+}
+
+impl SettingLocation {
+    pub fn edit_url(&self) -> String {
+        match &self.id {
+            Some(id) => format!("/settings/{}/{id}/edit", self.schema.id),
+            None => format!("/settings/{}/edit", self.schema.id),
+        }
+    }
+
+    pub fn object_name(&self) -> String {
+        match &self.id {
+            Some(id) => format!("{} {id:?}", self.schema.name_singular),
+            None => self.schema.name_plural.to_string(),
+        }
+    }
+}
+
+impl Schemas {
+    // Exact field matches win over record suffixes, then the longest prefix wins
+    pub fn locate_key(&self, key: &str) -> Option<SettingLocation> {
+        self.schemas
+            .values()
+            .filter_map(|schema| {
+                schema
+                    .locate_key(key)
+                    .map(|(location, is_exact)| (location, is_exact, schema.prefix_len()))
+            })
+            .max_by(|(a, a_exact, a_len), (b, b_exact, b_len)| {
+                a_exact
+                    .cmp(b_exact)
+                    .then(a_len.cmp(b_len))
+                    .then_with(|| b.schema.id.cmp(a.schema.id))
+            })
+            .map(|(location, ..)| location)
+    }
+}
+
+impl Schema {
+    fn locate_key(self: &Arc<Self>, key: &str) -> Option<(SettingLocation, bool)> {
+        let (id, field, subkey, is_exact) = match self.typ {
+            SchemaType::Record { prefix, suffix } => {
+                let key = key.strip_prefix(prefix)?.strip_prefix('.')?;
+                let (id, key) = key.split_once('.').unwrap_or((key, ""));
+                if id.is_empty() {
+                    return None;
+                }
+                if let Some((field, subkey)) = self.field_for_key(key) {
+                    (Some(id), field, subkey, true)
+                } else {
+                    let (field, _) = self.field_for_key(suffix)?;
+                    (Some(id), field, None, false)
+                }
+            }
+            SchemaType::Entry { prefix } => {
+                let id = key.strip_prefix(prefix)?.strip_prefix('.')?;
+                if id.is_empty() {
+                    return None;
+                }
+                (Some(id), self.fields.get("_value")?, None, true)
+            }
+            SchemaType::List => {
+                let (field, subkey) = self.field_for_key(key)?;
+                (None, field, subkey, true)
+            }
+        };
+
+        Some((
+            SettingLocation {
+                schema: self.clone(),
+                id: id.map(|id| id.to_string()),
+                field: field.clone(),
+                subkey: subkey.map(|subkey| subkey.to_string()),
+            },
+            is_exact,
+        ))
+    }
+
+    // Also returns the remainder of the key for multi-value fields
+    fn field_for_key<'x>(&self, key: &'x str) -> Option<(&Arc<Field>, Option<&'x str>)> {
+        if let Some(field) = self.fields.get(key) {
+            return Some((field, None));
+        }
+        key.char_indices()
+            .rev()
+            .filter(|(_, ch)| *ch == '.')
+            .find_map(|(pos, _)| {
+                self.fields
+                    .get(&key[..pos])
+                    .map(|field| (field, Some(&key[pos + 1..])))
+            })
+    }
+}
+
+impl FormData {
+    // Points at the expression clause or array item when the key identifies one
+    pub fn new_setting_error(&mut self, location: &SettingLocation, error: impl Into<String>) {
+        let subkey = location.subkey.as_deref().unwrap_or_default();
+        let id = match location.field.typ_ {
+            Type::Expression => match subkey.split_once('.') {
+                Some((pos, "if")) => pos.parse().map_or(FormErrorType::None, |pos| {
+                    FormErrorType::Expression(ExpressionError::If(pos))
+                }),
+                Some((pos, "then")) => pos.parse().map_or(FormErrorType::None, |pos| {
+                    FormErrorType::Expression(ExpressionError::Then(pos))
+                }),
+                _ => FormErrorType::Expression(ExpressionError::Else),
+            },
+            Type::Array | Type::Select { multi: true, .. } => subkey
+                .parse()
+                .map_or(FormErrorType::None, FormErrorType::Array),
+            _ => FormErrorType::None,
+        };
+
+        self.errors.insert(
+            location.field.id.to_string(),
+            FormError {
+                id,
+                error: error.into(),
+                offset: None,
+            },
+        );
+    }
+}
+
+#[component]
+pub fn ReloadIssues(#[prop(into)] issues: Signal<Vec<ReloadIssue>>) -> impl IntoView {
+    let pending = use_pending_errors();
+
+    let open_form = move |issue: ReloadIssue| {
+        if let Some(location) = &issue.location {
+            let url = location.edit_url();
+            let (schema, id) = (location.schema.clone(), location.id.clone());
+
+            // Queue every error on the same object so they show up together
+            pending.set(PendingErrors {
+                issues: issues
+                    .get_untracked()
+                    .into_iter()
+                    .filter(|issue| {
+                        issue.is_error
+                            && issue.location.as_ref().map_or(false, |location| {
+                                location.schema.id == schema.id && location.id == id
+                            })
+                    })
+                    .collect(),
+            });
+            use_navigate()(&url, Default::default());
+        }
+    };
+
+    view! {
+        <Show when=move || !issues.get().is_empty()>
+            <div class="mt-6 bg-white border shadow-sm rounded-xl overflow-hidden dark:bg-slate-900 dark:border-gray-800">
+                <div class="px-4 py-3 border-b border-gray-200 dark:border-gray-700">
+                    <h3 class="font-semibold text-gray-800 dark:text-gray-200">
+                        Configuration issues
+                    </h3>
+                    <p class="text-sm text-gray-500">
+                        Select an issue to open the form of the setting that caused it.
+                    </p>
+                </div>
+                <ul class="divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || issues.get().into_iter().enumerate()
+                        key=|(idx, issue)| (*idx, issue.key.clone())
+                        children=move |(_, issue)| {
+                            let color = if issue.is_error { Color::Red } else { Color::Yellow };
+                            let target = issue
+                                .location
+                                .as_ref()
+                                .map(|location| {
+                                    format!("{} › {}", location.object_name(), location.field.label_form)
+                                });
+                            let has_location = issue.location.is_some();
+                            let kind = issue.kind;
+                            let key = issue.key.clone();
+                            let message = issue.message.clone();
+
+                            view! {
+                                <li class="flex items-start gap-x-4 px-4 py-3">
+                                    <span class="mt-0.5 flex-shrink-0">
+                                        <Badge color=color>{kind}</Badge>
+                                    </span>
+                                    <div class="grow min-w-0">
+                                        <p class="text-sm font-mono text-gray-800 break-all dark:text-gray-200">
+                                            {key}
+                                        </p>
+                                        <p class="text-sm text-gray-500">{message}</p>
+                                        {target
+                                            .map(|target| {
+                                                view! {
+                                                    <p class="text-xs text-gray-400">{target}</p>
+                                                }
+                                            })}
+                                    </div>
+                                    {has_location
+                                        .then(|| {
+                                            view! {
+                                                <button
+                                                    type="button"
+                                                    class="flex-shrink-0 text-sm text-blue-600 decoration-2 hover:underline font-medium"
+                                                    on:click=move |_| open_form(issue.clone())
+                                                >
+                                                    Fix
+                                                </button>
+                                            }
+                                        })}
+                                </li>
+                            }
+                        }
+                    />
+                </ul>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_schemas;
+
+    fn locate(key: &str) -> Option<(String, Option<String>, String, Option<String>)> {
+        build_schemas().locate_key(key).map(|location| {
+            (
+                location.schema.id.to_string(),
+                location.id,
+                location.field.id.to_string(),
+                location.subkey,
+            )
+        })
+    }
+
+    fn some(
+        schema: &str,
+        id: Option<&str>,
+        field: &str,
+        subkey: Option<&str>,
+    ) -> Option<(String, Option<String>, String, Option<String>)> {
+        Some((
+            schema.to_string(),
+            id.map(|id| id.to_string()),
+            field.to_string(),
+            subkey.map(|subkey| subkey.to_string()),
+        ))
+    }
+
+    #[test]
+    fn locate_record_fields() {
+        assert_eq!(
+            locate("server.listener.smtp.protocol"),
+            some("listener", Some("smtp"), "protocol", None)
+        );
+        assert_eq!(
+            locate("server.listener.smtp.bind.1"),
+            some("listener", Some("smtp"), "bind", Some("1"))
+        );
+        // Unknown keys under a record fall back to the record suffix
+        assert_eq!(
+            locate("server.listener.smtp.unknown"),
+            some("listener", Some("smtp"), "protocol", None)
+        );
+        assert_eq!(locate("server.listener"), None);
+    }
+
+    #[test]
+    fn locate_list_fields() {
+        assert_eq!(
+            locate("server.run-as.user"),
+            some("system", None, "server.run-as.user", None)
+        );
+        assert_eq!(
+            locate("session.rcpt.relay.0.if"),
+            some("smtp-in-rcpt", None, "session.rcpt.relay", Some("0.if"))
+        );
+        assert_eq!(locate("unknown.setting"), None);
+    }
+}
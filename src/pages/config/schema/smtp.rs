@@ -44,7 +44,12 @@ impl Builder<Schemas, ()> {
             .typ(Type::Expression)
             .input_check(
                 [],
-                [Validator::Required, Validator::IsValidExpression(host_vars)],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(
+                        host_vars.returns(ExpressionType::Array(&ExpressionType::Duration)),
+                    ),
+                ],
             )
             .new_field("queue.schedule.notify")
             .label("Notify")
@@ -55,7 +60,12 @@ impl Builder<Schemas, ()> {
             .default("[1d, 3d]")
             .input_check(
                 [],
-                [Validator::Required, Validator::IsValidExpression(rcpt_vars)],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(
+                        rcpt_vars.returns(ExpressionType::Array(&ExpressionType::Duration)),
+                    ),
+                ],
             )
             .new_field("queue.schedule.expire")
             .label("Expire")
@@ -64,6 +74,13 @@ impl Builder<Schemas, ()> {
                 " it expires and is returned to the sender"
             ))
             .default("5d")
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(rcpt_vars.returns(ExpressionType::Duration)),
+                ],
+            )
             .build()
             .new_field("report.dsn.from-name")
             .label("From Name")
@@ -316,7 +333,10 @@ impl Builder<Schemas, ()> {
             .typ(Type::Expression)
             .input_check(
                 [],
-                [Validator::Required, Validator::IsValidExpression(rcpt_vars)],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(rcpt_vars.returns(ExpressionType::Integer)),
+                ],
             )
             .new_field("queue.outbound.limits.multihomed")
             .label("Multi-homed IPs")
@@ -333,7 +353,10 @@ impl Builder<Schemas, ()> {
             .default("3m")
             .input_check(
                 [],
-                [Validator::Required, Validator::IsValidExpression(host_vars)],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(host_vars.returns(ExpressionType::Duration)),
+                ],
             )
             .new_field("queue.outbound.timeouts.greeting")
             .label("Greeting")
@@ -906,7 +929,7 @@ impl Builder<Schemas, ()> {
                 [],
                 [
                     Validator::Required,
-                    Validator::IsValidExpression(has_conn_vars),
+                    Validator::IsValidExpression(has_conn_vars.returns(ExpressionType::Duration)),
                 ],
             )
             .default("5m")
@@ -915,9 +938,23 @@ impl Builder<Schemas, ()> {
             .label("Bytes Limit")
             .default("262144000")
             .help("The maximum number of bytes that can be transferred per session")
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(has_conn_vars.returns(ExpressionType::Size)),
+                ],
+            )
             .new_field("session.duration")
             .label("Duration")
             .default("10m")
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(has_conn_vars.returns(ExpressionType::Duration)),
+                ],
+            )
             .help("The maximum duration of a session")
             .build()
             .new_form_section()
@@ -1264,6 +1301,13 @@ impl Builder<Schemas, ()> {
             .label("Size")
             .help("Maximum size of a message in bytes")
             .default("104857600")
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(has_rcpt_vars.returns(ExpressionType::Size)),
+                ],
+            )
             .new_field("session.data.limits.received-headers")
             .label("Received Headers")
             .help(concat!(
@@ -1271,6 +1315,13 @@ impl Builder<Schemas, ()> {
                 "which helps to prevent message loops"
             ))
             .default("50")
+            .input_check(
+                [],
+                [
+                    Validator::Required,
+                    Validator::IsValidExpression(has_rcpt_vars),
+                ],
+            )
             .new_field("session.data.add-headers.received")
             .label("Received")
             .help("Whether to add a Received header to the message")
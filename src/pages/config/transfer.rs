@@ -0,0 +1,882 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use leptos::*;
+use leptos_router::use_navigate;
+use web_sys::HtmlInputElement;
+
+use crate::{
+    components::{
+        form::{button::Button, Form, FormButtonBar, FormItem, FormSection},
+        icon::{IconArrowDownTray, IconArrowUpTray, IconRefresh},
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        Color,
+    },
+    core::{
+        file::{download_file, read_file},
+        form::FormData,
+        http::{self, HttpRequest},
+        oauth::{use_authorization, AuthToken},
+    },
+    pages::config::{
+        history::update_settings, ReloadSettings, Schema, SchemaType, Schemas, Settings,
+        UpdateSettings,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FileFormat {
+    #[default]
+    Toml,
+    KeyValue,
+}
+
+#[derive(Clone, Default)]
+pub struct Import {
+    pub file_name: String,
+    pub updates: Arc<Vec<UpdateSettings>>,
+    pub objects: Vec<String>,
+    pub errors: Vec<String>,
+    pub ignored: Vec<String>,
+}
+
+#[component]
+pub fn SettingsTransfer() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let schemas = expect_context::<Arc<Schemas>>();
+
+    let mut export_options = schemas
+        .schemas
+        .values()
+        .filter(|schema| !schema.fields.is_empty())
+        .map(|schema| {
+            let label = if !schema.form.title.is_empty() {
+                format!("{} ({})", schema.form.title, schema.id)
+            } else {
+                schema.id.to_string()
+            };
+            (schema.id.to_string(), label)
+        })
+        .collect::<Vec<_>>();
+    export_options.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    let export_schema = create_rw_signal(String::new());
+    let export_format = create_rw_signal(FileFormat::Toml);
+    let import = create_rw_signal(None::<Import>);
+    let (pending, set_pending) = create_signal(false);
+
+    let schemas_ = schemas.clone();
+    let export_settings = create_action(move |(schema_id, format): &(String, FileFormat)| {
+        let auth = auth.get();
+        let schema = schemas_.schemas.get(schema_id.as_str()).cloned();
+        let format = *format;
+
+        async move {
+            set_pending.set(true);
+            let result = fetch_settings(&auth, schema.as_deref()).await;
+            set_pending.set(false);
+
+            match result {
+                Ok(settings) if !settings.is_empty() => {
+                    let name = schema.as_ref().map_or("settings", |schema| schema.id);
+                    let result = match format {
+                        FileFormat::Toml => download_file(
+                            &format!("{name}.toml"),
+                            "application/toml",
+                            settings_to_toml(&settings),
+                        ),
+                        FileFormat::KeyValue => download_file(
+                            &format!("{name}.cfg"),
+                            "text/plain",
+                            settings_to_key_values(&settings),
+                        ),
+                    };
+                    if let Err(err) = result {
+                        alert.set(Alert::error(format!("Failed to download file: {err}")));
+                    }
+                }
+                Ok(_) => {
+                    alert.set(Alert::warning("There are no settings to export"));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let schemas_ = schemas.clone();
+    let apply_import = create_action(move |(file, reload): &(Import, bool)| {
+        let changes = file.updates.clone();
+        let description = format!("Imported {:?}", file.file_name);
+        let reload = *reload;
+        let auth = auth.get();
+        let schemas = schemas_.clone();
+
+        async move {
+            set_pending.set(true);
+            let result = match update_settings(&auth, &schemas, description, changes).await {
+                Ok(_) if reload => HttpRequest::get("/api/reload/")
+                    .with_authorization(&auth)
+                    .send::<ReloadSettings>()
+                    .await
+                    .map(Some),
+                Ok(_) => Ok(None),
+                Err(err) => Err(err),
+            };
+            set_pending.set(false);
+
+            match result {
+                Ok(Some(result)) if !result.errors.is_empty() => {
+                    alert.set(Alert::from(result));
+                }
+                Ok(Some(_)) => {
+                    import.set(None);
+                    alert.set(Alert::success(
+                        "Settings successfully imported and reloaded",
+                    ));
+                }
+                Ok(None) => {
+                    import.set(None);
+                    alert.set(Alert::success("Settings successfully imported"));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let confirm_import = move |reload: bool| {
+        if let Some(import) = import.get() {
+            let total = import.objects.len();
+            modal.set(
+                Modal::with_title("Confirm import")
+                    .with_message(format!(
+                        "Are you sure you want to import {total} {} from {:?}? Existing settings for {} will be replaced.",
+                        if total == 1 { "object" } else { "objects" },
+                        import.file_name,
+                        if total == 1 { "this object" } else { "these objects" },
+                    ))
+                    .with_button(if reload { "Import & Reload" } else { "Import" })
+                    .with_dangerous_callback(move || {
+                        apply_import.dispatch((import.clone(), reload));
+                    }),
+            );
+        }
+    };
+
+    let can_import = create_memo(move |_| {
+        import.with(|import| {
+            import.as_ref().map_or(false, |import| {
+                import.errors.is_empty() && !import.updates.is_empty()
+            })
+        })
+    });
+
+    view! {
+        <Form
+            title="Import & Export"
+            subtitle="Move configuration subtrees between servers as TOML or key=value files"
+        >
+
+            <FormSection title="Export".to_string()>
+                <FormItem label="Settings">
+                    <select
+                        class="py-2 px-3 pe-9 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                        on:change=move |ev| {
+                            export_schema.set(event_target_value(&ev));
+                        }
+                    >
+
+                        <option value="" selected=move || export_schema.get().is_empty()>
+                            "All settings"
+                        </option>
+                        {export_options
+                            .into_iter()
+                            .map(|(id, label)| {
+                                let id_ = id.clone();
+                                view! {
+                                    <option selected=move || export_schema.get() == id value=id_>
+                                        {label}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </FormItem>
+                <FormItem label="Format">
+                    <FormatSelect format=export_format/>
+                </FormItem>
+            </FormSection>
+
+            <FormButtonBar>
+                <Button
+                    text="Export"
+                    color=Color::Blue
+                    on_click=Callback::new(move |_| {
+                        export_settings.dispatch((export_schema.get(), export_format.get()));
+                    })
+
+                    disabled=pending
+                >
+                    <IconArrowDownTray/>
+                </Button>
+            </FormButtonBar>
+
+            <FormSection title="Import".to_string()>
+                <FormItem label="File">
+                    <input
+                        type="file"
+                        accept=".toml,.cfg,.conf,.properties,.txt"
+                        class="block w-full border border-gray-200 shadow-sm rounded-lg text-sm focus:z-10 focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600 file:bg-gray-50 file:border-0 file:me-4 file:py-2 file:px-4 dark:file:bg-gray-700 dark:file:text-gray-400"
+                        on:change=move |ev| {
+                            let file = event_target::<HtmlInputElement>(&ev)
+                                .files()
+                                .and_then(|files| files.get(0));
+                            let schemas = schemas.clone();
+                            import.set(None);
+                            if let Some(file) = file {
+                                let file_name = file.name();
+                                spawn_local(async move {
+                                    let result = read_file(file)
+                                        .await
+                                        .and_then(|contents| {
+                                            if file_name.ends_with(".toml") {
+                                                parse_toml(&contents)
+                                            } else {
+                                                parse_key_values(&contents)
+                                            }
+                                        });
+                                    match result {
+                                        Ok(settings) if !settings.is_empty() => {
+                                            import
+                                                .set(
+                                                    Some(Import {
+                                                        file_name,
+                                                        ..Import::new(&schemas, settings)
+                                                    }),
+                                                );
+                                        }
+                                        Ok(_) => {
+                                            alert.set(Alert::warning("The file contains no settings"));
+                                        }
+                                        Err(err) => {
+                                            alert
+                                                .set(
+                                                    Alert::error(
+                                                        format!("Failed to parse {file_name:?}: {err}"),
+                                                    ),
+                                                );
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                    />
+
+                    <p class="mt-2 text-xs text-gray-500 dark:text-gray-400">
+                        "Files ending in .toml are parsed as TOML, anything else as key = value lines."
+                    </p>
+                </FormItem>
+            </FormSection>
+
+            {move || {
+                import
+                    .get()
+                    .map(|import| {
+                        view! {
+                            <div class="mt-6 space-y-4">
+                                <ImportSummary
+                                    title="Objects"
+                                    items=import.objects.clone()
+                                    text_class="text-gray-800 dark:text-gray-200"
+                                />
+                                <ImportSummary
+                                    title="Validation errors"
+                                    items=import.errors.clone()
+                                    text_class="text-red-600 dark:text-red-400"
+                                />
+                                <ImportSummary
+                                    title="Keys not covered by any schema (skipped)"
+                                    items=import.ignored.clone()
+                                    text_class="text-yellow-700 dark:text-yellow-400"
+                                />
+                                <div>
+                                    <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                                        "Update batch"
+                                    </h3>
+                                    <pre class="mt-2 p-3 max-h-96 overflow-auto text-xs font-mono bg-gray-50 border border-gray-200 rounded-lg text-gray-800 dark:bg-slate-800 dark:border-gray-700 dark:text-gray-300">
+                                        {describe_updates(&import.updates)}
+                                    </pre>
+                                </div>
+                            </div>
+                        }
+                    })
+            }}
+
+            <FormButtonBar>
+                <Button
+                    text="Import & Reload"
+                    color=Color::Gray
+                    on_click=Callback::new(move |_| confirm_import(true))
+                    disabled=Signal::derive(move || pending.get() || !can_import.get())
+                >
+                    <IconRefresh/>
+                </Button>
+                <Button
+                    text="Import"
+                    color=Color::Blue
+                    on_click=Callback::new(move |_| confirm_import(false))
+                    disabled=Signal::derive(move || pending.get() || !can_import.get())
+                >
+                    <IconArrowUpTray/>
+                </Button>
+            </FormButtonBar>
+
+        </Form>
+    }
+}
+
+#[component]
+fn FormatSelect(format: RwSignal<FileFormat>) -> impl IntoView {
+    view! {
+        <select
+            class="py-2 px-3 pe-9 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+            on:change=move |ev| {
+                format
+                    .set(
+                        if event_target_value(&ev) == "toml" {
+                            FileFormat::Toml
+                        } else {
+                            FileFormat::KeyValue
+                        },
+                    );
+            }
+        >
+
+            <option value="toml" selected=move || format.get() == FileFormat::Toml>
+                "TOML"
+            </option>
+            <option value="kv" selected=move || format.get() == FileFormat::KeyValue>
+                "Flat key = value"
+            </option>
+        </select>
+    }
+}
+
+#[component]
+fn ImportSummary(
+    title: &'static str,
+    items: Vec<String>,
+    text_class: &'static str,
+) -> impl IntoView {
+    (!items.is_empty()).then(|| {
+        view! {
+            <div>
+                <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                    {format!("{title} ({})", items.len())}
+                </h3>
+                <ul class=format!("mt-1 list-disc list-inside text-xs font-mono {text_class}")>
+                    {items
+                        .into_iter()
+                        .map(|item| view! { <li>{item}</li> })
+                        .collect_view()}
+                </ul>
+            </div>
+        }
+    })
+}
+
+async fn fetch_settings(auth: &AuthToken, schema: Option<&Schema>) -> http::Result<Settings> {
+    #[derive(serde::Deserialize)]
+    struct FetchSettings {
+        items: Settings,
+    }
+
+    let prefix = match schema {
+        Some(schema) => match schema.typ {
+            SchemaType::Record { prefix, .. } | SchemaType::Entry { prefix } => prefix,
+            SchemaType::List => {
+                let mut keys = Vec::new();
+                let mut prefixes = Vec::new();
+
+                for field in schema.fields.values() {
+                    if field.is_multivalue() {
+                        prefixes.push(field.id);
+                    }
+                    keys.push(field.id);
+                }
+
+                return HttpRequest::get("/api/settings/keys")
+                    .with_authorization(auth)
+                    .with_parameter("keys", keys.join(","))
+                    .with_parameter("prefixes", prefixes.join(","))
+                    .send::<Settings>()
+                    .await;
+            }
+        },
+        None => "",
+    };
+
+    HttpRequest::get("/api/settings/list")
+        .with_authorization(auth)
+        .with_parameter("prefix", prefix)
+        .send::<FetchSettings>()
+        .await
+        .map(|list| {
+            if !prefix.is_empty() {
+                list.items
+                    .into_iter()
+                    .map(|(key, value)| (format!("{prefix}.{key}"), value))
+                    .collect()
+            } else {
+                list.items
+            }
+        })
+}
+
+impl Import {
+    pub fn new(schemas: &Schemas, mut settings: Settings) -> Self {
+        let mut import = Import::default();
+        let mut updates = Vec::new();
+        let mut schemas = schemas
+            .schemas
+            .values()
+            .filter(|schema| !schema.fields.is_empty())
+            .collect::<Vec<_>>();
+
+        // Match the most specific prefixes first
+        schemas.sort_by(|a, b| {
+            b.prefix_len()
+                .cmp(&a.prefix_len())
+                .then_with(|| a.id.cmp(b.id))
+        });
+
+        for schema in schemas {
+            let mut objects: BTreeMap<String, Settings> = BTreeMap::new();
+
+            match schema.typ {
+                SchemaType::Record { prefix, .. } => {
+                    let prefix = format!("{prefix}.");
+                    for (key, value) in take_keys(&mut settings, |key| key.starts_with(&prefix)) {
+                        match key[prefix.len()..].split_once('.') {
+                            Some((id, key)) if schema.has_field(key) => {
+                                objects
+                                    .entry(id.to_string())
+                                    .or_default()
+                                    .insert(key.to_string(), value);
+                            }
+                            _ => import.ignored.push(key),
+                        }
+                    }
+                }
+                SchemaType::Entry { prefix } => {
+                    let prefix = format!("{prefix}.");
+                    for (key, value) in take_keys(&mut settings, |key| key.starts_with(&prefix)) {
+                        let mut entry = Settings::new();
+                        entry.insert("_value".to_string(), value);
+                        objects.insert(key[prefix.len()..].to_string(), entry);
+                    }
+                }
+                SchemaType::List => {
+                    let values = take_keys(&mut settings, |key| schema.has_field(key));
+                    if !values.is_empty() {
+                        objects.insert(String::new(), values.into_iter().collect());
+                    }
+                }
+            }
+
+            for (id, mut values) in objects {
+                let label = if !id.is_empty() {
+                    values.insert("_id".to_string(), id.clone());
+                    format!("{} {id:?}", schema.name_singular)
+                } else if !schema.form.title.is_empty() {
+                    schema.form.title.to_string()
+                } else {
+                    schema.id.to_string()
+                };
+
+                let mut data = FormData::from_settings(schema.clone(), Some(values));
+                if data.validate_form() {
+                    updates.extend(data.build_update());
+                } else {
+                    let mut errors = data
+                        .errors
+                        .iter()
+                        .map(|(field, err)| format!("{label}: {field}: {}", err.error))
+                        .collect::<Vec<_>>();
+                    errors.sort();
+                    import.errors.extend(errors);
+                }
+                import.objects.push(label);
+            }
+        }
+
+        import.ignored.extend(settings.into_keys());
+        import.ignored.sort();
+        import.updates = Arc::new(updates);
+        import
+    }
+}
+
+fn take_keys(settings: &mut Settings, filter: impl Fn(&str) -> bool) -> Vec<(String, String)> {
+    let keys = settings
+        .keys()
+        .filter(|key| filter(key))
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.into_iter()
+        .filter_map(|key| settings.remove(&key).map(|value| (key, value)))
+        .collect()
+}
+
+fn describe_updates(updates: &[UpdateSettings]) -> String {
+    let mut result = String::new();
+    for update in updates {
+        match update {
+            UpdateSettings::Delete { keys } => {
+                for key in keys {
+                    result.push_str(&format!("delete {key}\n"));
+                }
+            }
+            UpdateSettings::Clear { prefix } => {
+                result.push_str(&format!("clear  {prefix}*\n"));
+            }
+            UpdateSettings::Insert { prefix, values, .. } => {
+                for (key, value) in values {
+                    let key = match prefix {
+                        Some(prefix) => format!("{prefix}.{key}"),
+                        None => key.to_string(),
+                    };
+                    result.push_str(&format!("insert {key} = {}\n", escape_value(value)));
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn settings_to_key_values(settings: &Settings) -> String {
+    let mut result = String::new();
+    for (key, value) in settings.iter().collect::<BTreeMap<_, _>>() {
+        result.push_str(&format!("{key} = {}\n", escape_value(value)));
+    }
+    result
+}
+
+pub fn parse_key_values(contents: &str) -> Result<Settings, String> {
+    let mut settings = Settings::new();
+    for (line_num, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Expected 'key = value' at line {}", line_num + 1))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("Missing key at line {}", line_num + 1));
+        }
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        settings.insert(key.to_string(), unescape_value(value));
+    }
+    Ok(settings)
+}
+
+pub fn settings_to_toml(settings: &Settings) -> String {
+    let settings = settings
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect::<BTreeMap<_, _>>();
+    let mut root = Vec::new();
+    let mut tables: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+
+    for (&key, &value) in &settings {
+        match key.rsplit_once('.') {
+            // A table can't be declared when one of its parents also holds a
+            // value, such keys are written at the top level as quoted keys.
+            Some((table, name))
+                if !table
+                    .char_indices()
+                    .filter(|(_, ch)| *ch == '.')
+                    .map(|(pos, _)| &table[..pos])
+                    .chain([table])
+                    .any(|parent| settings.contains_key(parent)) =>
+            {
+                tables.entry(table).or_default().push((name, value));
+            }
+            _ => root.push((key, value)),
+        }
+    }
+
+    let mut result = String::new();
+    for (key, value) in root {
+        result.push_str(&format!("{} = {}\n", toml_key(key), toml_string(value)));
+    }
+    for (table, values) in tables {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&format!(
+            "[{}]\n",
+            table.split('.').map(toml_key).collect::<Vec<_>>().join(".")
+        ));
+        for (key, value) in values {
+            result.push_str(&format!("{} = {}\n", toml_key(key), toml_string(value)));
+        }
+    }
+    result
+}
+
+pub fn parse_toml(contents: &str) -> Result<Settings, String> {
+    let table = contents
+        .parse::<toml::Table>()
+        .map_err(|err| err.to_string())?;
+    let mut settings = Settings::new();
+    for (key, value) in table {
+        flatten_toml(key, value, &mut settings);
+    }
+    Ok(settings)
+}
+
+fn flatten_toml(key: String, value: toml::Value, settings: &mut Settings) {
+    match value {
+        toml::Value::String(value) => {
+            settings.insert(key, value);
+        }
+        toml::Value::Table(table) => {
+            for (sub_key, value) in table {
+                flatten_toml(format!("{key}.{sub_key}"), value, settings);
+            }
+        }
+        toml::Value::Array(values) => {
+            let pad_len = values.len().saturating_sub(1).to_string().len();
+            for (idx, value) in values.into_iter().enumerate() {
+                flatten_toml(format!("{key}.{idx:0>pad_len$}"), value, settings);
+            }
+        }
+        value => {
+            settings.insert(key, value.to_string());
+        }
+    }
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+        key.to_string()
+    } else {
+        toml_basic_string(key)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    if value.contains('\n')
+        && !value.contains("'''")
+        && !value.ends_with('\'')
+        && !value
+            .chars()
+            .any(|ch| ch.is_control() && ch != '\n' && ch != '\t')
+    {
+        format!("'''\n{value}'''")
+    } else {
+        toml_basic_string(value)
+    }
+}
+
+fn toml_basic_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            ch if ch.is_control() => result.push_str(&format!("\\u{:04X}", ch as u32)),
+            ch => result.push(ch),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('t') => result.push('\t'),
+                Some(ch) => result.push(ch),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn samples() -> Settings {
+        settings(&[
+            ("server.hostname", "mx.example.org"),
+            ("server.listener.smtp.bind", "[::]:25"),
+            ("lookup.default.domain", "example.org"),
+            ("quoted", "say \"hello\" to C:\\dir"),
+            ("tabs", "a\tb\rc"),
+            ("multi-line", "line one\nline two\n"),
+            ("multi-quote", "it's\n'''\n"),
+            ("ends-quote", "first\nlast'"),
+            ("control", "bell\u{7}\nnext"),
+            ("leading-space", "  padded  "),
+            ("empty", ""),
+            ("key with spaces", "value"),
+            ("dotted.\"key\"", "value"),
+        ])
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let settings = samples();
+        assert_eq!(parse_toml(&settings_to_toml(&settings)).unwrap(), settings);
+    }
+
+    #[test]
+    fn toml_table_value_collisions() {
+        let settings = settings(&[
+            ("a", "1"),
+            ("a.b", "2"),
+            ("a.b.c", "3"),
+            ("x.y", "4"),
+            ("x.y.z", "5"),
+            ("x.w", "6"),
+        ]);
+        let toml = settings_to_toml(&settings);
+        assert_eq!(
+            toml,
+            concat!(
+                "a = \"1\"\n",
+                "\"a.b\" = \"2\"\n",
+                "\"a.b.c\" = \"3\"\n",
+                "\"x.y.z\" = \"5\"\n",
+                "\n",
+                "[x]\n",
+                "w = \"6\"\n",
+                "y = \"4\"\n",
+            )
+        );
+        assert_eq!(parse_toml(&toml).unwrap(), settings);
+    }
+
+    #[test]
+    fn toml_multi_line_literals() {
+        assert_eq!(
+            settings_to_toml(&settings(&[("text", "line one\nline two\n")])),
+            "text = '''\nline one\nline two\n'''\n"
+        );
+        assert_eq!(
+            settings_to_toml(&settings(&[("text", "first\nlast'")])),
+            "text = \"first\\nlast'\"\n"
+        );
+        assert_eq!(
+            settings_to_toml(&settings(&[("text", "a'''\nb")])),
+            "text = \"a'''\\nb\"\n"
+        );
+    }
+
+    #[test]
+    fn toml_arrays_and_scalars() {
+        let parsed = parse_toml("list = [\"a\", \"b\"]\nport = 25\nenable = true\n").unwrap();
+        assert_eq!(
+            parsed,
+            settings(&[
+                ("list.0", "a"),
+                ("list.1", "b"),
+                ("port", "25"),
+                ("enable", "true"),
+            ])
+        );
+    }
+
+    #[test]
+    fn key_values_round_trip() {
+        let settings = samples();
+        assert_eq!(
+            parse_key_values(&settings_to_key_values(&settings)).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn key_values_escapes() {
+        assert_eq!(escape_value("a\\nb\nc\td\r"), "a\\\\nb\\nc\\td\\r");
+        assert_eq!(unescape_value("a\\\\nb\\nc\\td\\r"), "a\\nb\nc\td\r");
+        assert_eq!(unescape_value("trailing\\"), "trailing\\");
+        assert_eq!(
+            parse_key_values("# comment\n\nkey = a = b\n").unwrap(),
+            settings(&[("key", "a = b")])
+        );
+        assert!(parse_key_values("missing separator").is_err());
+        assert!(parse_key_values(" = value").is_err());
+    }
+}
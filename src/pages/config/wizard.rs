@@ -0,0 +1,465 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        form::{
+            button::Button, input::InputText, Form, FormButtonBar, FormElement, FormItem,
+            FormSection,
+        },
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        form::{ExternalSources, FormData},
+        http::{self, HttpRequest, ManagementApiError},
+        oauth::use_authorization,
+    },
+    pages::config::{
+        edit::{fetch_external_sources, form_sections, settings_diff},
+        history::update_settings,
+        reload::{ReloadIssue, ReloadIssues},
+        ReloadSettings, Schemas, Settings, SettingsDiff, UpdateSettings,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    schema: &'static str,
+    title: &'static str,
+    description: &'static str,
+}
+
+const STEPS: &[Step] = &[
+    Step {
+        schema: "network",
+        title: "Network",
+        description: "Set the hostname of this server and the URL it is reachable at.",
+    },
+    Step {
+        schema: "store",
+        title: "Storage",
+        description: "Choose the backend where messages, blobs and indexes are stored.",
+    },
+    Step {
+        schema: "directory",
+        title: "Directory",
+        description: "Choose where accounts, groups and mailing lists are stored.",
+    },
+    Step {
+        schema: "authentication",
+        title: "Administrator",
+        description: "Create the administrator account used to manage this server.",
+    },
+    Step {
+        schema: "acme",
+        title: "TLS certificates",
+        description: "Obtain TLS certificates automatically from an ACME provider.",
+    },
+    Step {
+        schema: "domains",
+        title: "Domain",
+        description: "The first local domain this server will accept mail for.",
+    },
+    Step {
+        schema: "signature",
+        title: "DKIM signature",
+        description: "Sign outgoing messages for the new domain.",
+    },
+];
+
+#[component]
+pub fn SettingsWizard() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let schemas = expect_context::<Arc<Schemas>>();
+
+    let steps = STEPS
+        .iter()
+        .map(|step| FormData::from_settings(schemas.get(step.schema), None).into_signal())
+        .collect::<Arc<[_]>>();
+    let current = create_rw_signal(0usize);
+    let sources = create_rw_signal(Arc::new(ExternalSources::new()));
+    let (pending, set_pending) = create_signal(false);
+    let issues = create_rw_signal(Vec::<ReloadIssue>::new());
+
+    let schemas_ = schemas.clone();
+    let fetch_sources = create_resource(
+        || (),
+        move |_| {
+            let auth = auth.get_untracked();
+            let schemas = schemas_.clone();
+
+            async move {
+                let mut external_sources = ExternalSources::new();
+                for step in STEPS {
+                    external_sources
+                        .extend(fetch_external_sources(&auth, &schemas.get(step.schema)).await?);
+                }
+                Ok(external_sources)
+            }
+        },
+    );
+
+    // Offer the objects created in earlier steps as options, and carry over
+    // the values the user already entered.
+    let steps_ = steps.clone();
+    let prepare_step = move |idx: usize| {
+        let Some(data) = steps_.get(idx) else {
+            return;
+        };
+        let value = |schema: &str, field: &str| {
+            STEPS
+                .iter()
+                .position(|step| step.schema == schema)
+                .and_then(|pos| steps_[pos].with_untracked(|data| data.value::<String>(field)))
+                .filter(|value| !value.is_empty())
+        };
+        let mut external_sources = (*sources.get_untracked()).clone();
+        let schema = data.with_untracked(|data| data.schema.clone());
+        for (source_schema, source_field) in schema.external_sources() {
+            if let (Some(id), label) = (
+                value(source_schema.id, "_id"),
+                value(source_schema.id, source_field.id),
+            ) {
+                let options = external_sources
+                    .entry(format!("{}_{}", source_schema.id, source_field.id))
+                    .or_default();
+                if !options.iter().any(|(option, _)| option == &id) {
+                    options.push((id, label.unwrap_or_default()));
+                }
+            }
+        }
+
+        data.update(|data| {
+            data.external_sources = Arc::new(external_sources);
+            match schema.id {
+                "acme" if data.value_is_empty("domains") => {
+                    if let Some(hostname) = value("network", "lookup.default.hostname") {
+                        data.array_set("domains", [hostname]);
+                    }
+                }
+                "authentication" if data.value_is_empty("storage.directory") => {
+                    if let Some(id) = value("directory", "_id") {
+                        data.set("storage.directory", id);
+                    }
+                }
+                "signature" if data.value_is_empty("domain") => {
+                    if let Some(domain) = value("domains", "domain") {
+                        data.set("domain", domain);
+                    }
+                }
+                _ => (),
+            }
+        });
+    };
+
+    // Combine every step into a single batch, pointing the default stores
+    // at the new store and directory.
+    let steps_ = steps.clone();
+    let build_update = move || {
+        let mut updates = Vec::new();
+        let mut defaults = Vec::new();
+        for (step, data) in STEPS.iter().zip(steps_.iter()) {
+            data.with_untracked(|data| {
+                match step.schema {
+                    "domains" => return,
+                    "store" => {
+                        let id = data.value_as_str("_id").unwrap_or_default();
+                        for key in [
+                            "storage.data",
+                            "storage.blob",
+                            "storage.fts",
+                            "storage.lookup",
+                        ] {
+                            defaults.push((key.to_string(), id.to_string()));
+                        }
+                    }
+                    _ => (),
+                }
+                updates.extend(data.build_update());
+            });
+        }
+        updates.push(UpdateSettings::Insert {
+            prefix: None,
+            values: defaults,
+            assert_empty: false,
+        });
+        updates
+    };
+
+    let domain = STEPS
+        .iter()
+        .position(|step| step.schema == "domains")
+        .map(|pos| steps[pos])
+        .unwrap();
+    let finish = create_action(move |changes: &Arc<Vec<UpdateSettings>>| {
+        let changes = changes.clone();
+        let auth = auth.get();
+        let schemas = schemas.clone();
+        let name = domain.with_untracked(|data| data.value::<String>("domain").unwrap_or_default());
+
+        async move {
+            set_pending.set(true);
+            issues.set(Vec::new());
+
+            let result =
+                match update_settings(&auth, &schemas, "Completed setup wizard", changes).await {
+                    Ok(_) => {
+                        HttpRequest::get("/api/reload")
+                            .with_authorization(&auth)
+                            .send::<ReloadSettings>()
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+            let result = match result {
+                Ok(reload) if !reload.errors.is_empty() => {
+                    issues.set(reload.issues(&schemas));
+                    alert.set(Alert::from(reload));
+                    Ok(false)
+                }
+                Ok(_) => match HttpRequest::post(format!("/api/domain/{name}"))
+                    .with_authorization(&auth)
+                    .send::<()>()
+                    .await
+                {
+                    Ok(_)
+                    | Err(http::Error::Server(ManagementApiError::FieldAlreadyExists { .. })) => {
+                        Ok(true)
+                    }
+                    Err(err) => Err(err),
+                },
+                Err(err) => Err(err),
+            };
+
+            set_pending.set(false);
+
+            match result {
+                Ok(true) => {
+                    use_navigate()(
+                        &format!("/manage/directory/domains/{name}/view"),
+                        Default::default(),
+                    );
+                }
+                Ok(false) => (),
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let is_review = create_memo(move |_| current.get() == STEPS.len());
+    let steps_ = steps.clone();
+    let prepare_step_ = prepare_step.clone();
+    let next_step = move |_| {
+        let idx = current.get();
+        if steps_[idx]
+            .try_update(|data| data.validate_form() && validate_admin(data))
+            .unwrap_or_default()
+        {
+            current.set(idx + 1);
+            prepare_step_(idx + 1);
+        }
+    };
+    let prepare_step_ = prepare_step.clone();
+    let previous_step = move |_| {
+        let idx = current.get().saturating_sub(1);
+        current.set(idx);
+        prepare_step_(idx);
+    };
+
+    let steps_ = steps.clone();
+    let build_update_ = build_update.clone();
+    let step_view = move || {
+        let idx = current.get();
+        if let Some(data) = steps_.get(idx).copied() {
+            if STEPS[idx].schema == "domains" {
+                view! {
+                    <FormSection>
+                        <FormItem label="Domain name">
+                            <InputText
+                                placeholder="example.org"
+                                element=FormElement::new("domain", data)
+                            />
+                        </FormItem>
+                    </FormSection>
+                }
+                .into_view()
+            } else {
+                form_sections(data, true)
+            }
+        } else {
+            let diff = SettingsDiff::new(&Settings::new(), &build_update_());
+            let schemas = expect_context::<Arc<Schemas>>();
+            view! {
+                <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                    The following settings will be created and the configuration reloaded.
+                </p>
+                {settings_diff(
+                    &diff,
+                    |key| schemas.is_secret(key),
+                )}
+            }
+            .into_view()
+        }
+    };
+
+    let step_list = STEPS
+        .iter()
+        .enumerate()
+        .map(|(idx, step)| {
+            let prepare_step = prepare_step.clone();
+            view! {
+                <li class="group flex items-center gap-x-2 shrink basis-0 flex-1">
+                    <button
+                        type="button"
+                        class="inline-flex items-center gap-x-2 text-xs font-medium disabled:pointer-events-none"
+                        disabled=move || idx >= current.get()
+                        on:click=move |_| {
+                            current.set(idx);
+                            prepare_step(idx);
+                        }
+                    >
+
+                        <span
+                            class="size-7 flex justify-center items-center flex-shrink-0 rounded-full"
+                            class=("bg-blue-600", move || idx <= current.get())
+                            class=("text-white", move || idx <= current.get())
+                            class=("bg-gray-100", move || idx > current.get())
+                            class=("text-gray-800", move || idx > current.get())
+                        >
+                            {idx + 1}
+                        </span>
+                        <span class="hidden sm:inline text-gray-800 dark:text-gray-200">
+                            {step.title}
+                        </span>
+                    </button>
+                    <div class="w-full h-px flex-1 bg-gray-200 group-last:hidden dark:bg-gray-700"></div>
+                </li>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <Form
+            title=Signal::derive(move || {
+                STEPS.get(current.get()).map_or("Review", |step| step.title).to_string()
+            })
+
+            subtitle=Signal::derive(move || {
+                STEPS
+                    .get(current.get())
+                    .map_or("Confirm the initial configuration of your server.", |step| {
+                        step.description
+                    })
+                    .to_string()
+            })
+        >
+
+            <ul class="mb-8 flex flex-row gap-x-2">{step_list}</ul>
+
+            <Transition fallback=Skeleton>
+
+                {
+                    let prepare_step = prepare_step.clone();
+                    let step_view = step_view.clone();
+                    move || match fetch_sources.get() {
+                        None => None,
+                        Some(Err(http::Error::Unauthorized)) => {
+                            use_navigate()("/login", Default::default());
+                            Some(view! { <div></div> }.into_view())
+                        }
+                        Some(Err(err)) => {
+                            alert.set(Alert::from(err));
+                            Some(view! { <div></div> }.into_view())
+                        }
+                        Some(Ok(fetched)) => {
+                            sources.set(Arc::new(fetched));
+                            prepare_step(current.get_untracked());
+                            let step_view = step_view.clone();
+                            Some(view! { <div>{step_view}</div> }.into_view())
+                        }
+                    }
+                }
+
+            </Transition>
+
+            <ReloadIssues issues=issues/>
+
+            <FormButtonBar>
+                <Button
+                    text="Back"
+                    color=Color::Gray
+                    on_click=previous_step
+                    disabled=Signal::derive(move || current.get() == 0 || pending.get())
+                />
+                <Show when=move || !is_review.get()>
+                    <Button text="Next" color=Color::Blue on_click=next_step.clone()/>
+                </Show>
+                <Show when=move || is_review.get()>
+                    <Button
+                        text="Finish setup"
+                        color=Color::Blue
+                        on_click={
+                            let build_update = build_update.clone();
+                            Callback::new(move |_| {
+                                finish.dispatch(Arc::new(build_update()));
+                            })
+                        }
+
+                        disabled=pending
+                    />
+                </Show>
+            </FormButtonBar>
+
+        </Form>
+    }
+}
+
+// The fallback administrator is optional in the settings but required here,
+// it is the only account available until the directory is populated.
+fn validate_admin(data: &mut FormData) -> bool {
+    if data.schema.id != "authentication" {
+        return true;
+    }
+    for field in [
+        "authentication.fallback-admin.user",
+        "authentication.fallback-admin.secret",
+    ] {
+        if data.value_is_empty(field) {
+            data.new_error(field, "This field is required");
+        }
+    }
+    data.errors.is_empty()
+}
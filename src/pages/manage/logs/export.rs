@@ -0,0 +1,301 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use chrono::Utc;
+use leptos::*;
+use regex::{Captures, Regex};
+
+use crate::{
+    components::progress::ProgressCard,
+    core::{http, oauth::AuthToken},
+    pages::{
+        manage::logs::{fetch_all_logs, LogEntry, LogFilter},
+        maybe_plural,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Log,
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportState {
+    Running,
+    Stopped,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportProgress {
+    pub state: ExportState,
+    pub read: u64,
+    pub total: u64,
+    pub exported: usize,
+}
+
+struct Redactor {
+    email: Regex,
+    ipv4: Regex,
+    ipv6: Regex,
+}
+
+impl LogFormat {
+    pub fn file_name(&self) -> String {
+        format!(
+            "logs-{}.{}",
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            match self {
+                LogFormat::Log => "log",
+                LogFormat::Ndjson => "ndjson",
+            }
+        )
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LogFormat::Log => "text/plain",
+            LogFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn write_entry(&self, out: &mut String, entry: &LogEntry) {
+        match self {
+            LogFormat::Log => {
+                out.push_str(&format!(
+                    "{} {:<5} {}\n",
+                    entry.timestamp.to_rfc3339(),
+                    entry.level,
+                    entry.message
+                ));
+            }
+            LogFormat::Ndjson => {
+                if let Ok(line) = serde_json::to_string(entry) {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+}
+
+impl Redactor {
+    fn new() -> Self {
+        // Regex has no lookbehind, so the character before an address is
+        // matched too and the one after it is checked when replacing.
+        Redactor {
+            email: Regex::new(r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9\-]+(?:\.[A-Za-z0-9\-]+)+").unwrap(),
+            ipv4: Regex::new(concat!(
+                r"(?:^|[^0-9A-Za-z.]|[^0-9]\.)",
+                r"(?P<address>(?:[0-9]{1,3}\.){3}[0-9]{1,3})(?::(?P<port>[0-9]{1,5}))?"
+            ))
+            .unwrap(),
+            ipv6: Regex::new(concat!(
+                r"(?:^|[^0-9A-Za-z:.]|[G-Zg-z_]:)",
+                r"(?P<address>[0-9A-Fa-f]*:[0-9A-Fa-f]*:[0-9A-Fa-f:.]*)"
+            ))
+            .unwrap(),
+        }
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let text = self.email.replace_all(text, "[email]");
+        let text = replace_addresses(&self.ipv6, &text, |caps, next| {
+            let candidate = &caps["address"];
+            let address = candidate.trim_end_matches(['.', ':']);
+            (Ipv6Addr::from_str(address).is_ok() && !next.starts_with(is_word_char))
+                .then(|| format!("[ip]{}", &candidate[address.len()..]))
+        });
+        replace_addresses(&self.ipv4, &text, |caps, next| {
+            let is_embedded = next.starts_with(is_word_char)
+                || next.strip_prefix('.').map_or(false, |next| {
+                    next.starts_with(|ch: char| ch.is_ascii_digit())
+                });
+            (Ipv4Addr::from_str(&caps["address"]).is_ok() && !is_embedded).then(|| {
+                caps.name("port").map_or_else(
+                    || "[ip]".to_string(),
+                    |port| format!("[ip]:{}", port.as_str()),
+                )
+            })
+        })
+    }
+}
+
+fn replace_addresses(
+    regex: &Regex,
+    text: &str,
+    redact: impl Fn(&Captures, &str) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for caps in regex.captures_iter(text) {
+        let start = caps.name("address").unwrap().start();
+        let end = caps.get(0).unwrap().end();
+        if let Some(replacement) = redact(&caps, &text[end..]) {
+            result.push_str(&text[last_end..start]);
+            result.push_str(&replacement);
+            last_end = end;
+        }
+    }
+    result.push_str(&text[last_end..]);
+    result
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+// Returns `None` when the export was stopped
+pub async fn export_logs(
+    auth: AuthToken,
+    filter: LogFilter,
+    format: LogFormat,
+    redact: bool,
+    progress: RwSignal<Option<ExportProgress>>,
+    stop: RwSignal<bool>,
+) -> http::Result<Option<(String, usize)>> {
+    stop.set(false);
+    progress.set(Some(ExportProgress {
+        state: ExportState::Running,
+        read: 0,
+        total: 0,
+        exported: 0,
+    }));
+
+    let redactor = redact.then(Redactor::new);
+    let mut contents = String::new();
+    let mut exported = 0;
+    let result = fetch_all_logs(&auth, &filter, |logs, read, total| {
+        for mut entry in logs {
+            if let Some(redactor) = &redactor {
+                entry.message = redactor.redact(&entry.message);
+            }
+            format.write_entry(&mut contents, &entry);
+            exported += 1;
+        }
+        update(progress, |p| {
+            p.read = read;
+            p.total = total;
+            p.exported = exported;
+        });
+        !stop.get_untracked()
+    })
+    .await;
+
+    if let Err(err) = result {
+        progress.set(None);
+        return Err(err);
+    }
+
+    if stop.get_untracked() {
+        update(progress, |p| p.state = ExportState::Stopped);
+        Ok(None)
+    } else {
+        update(progress, |p| p.state = ExportState::Done);
+        Ok(Some((contents, exported)))
+    }
+}
+
+fn update(progress: RwSignal<Option<ExportProgress>>, f: impl FnOnce(&mut ExportProgress)) {
+    progress.update(|progress| {
+        if let Some(progress) = progress {
+            f(progress);
+        }
+    });
+}
+
+#[component]
+pub fn ExportStatus(
+    progress: RwSignal<Option<ExportProgress>>,
+    #[prop(into)] on_stop: Callback<(), ()>,
+) -> impl IntoView {
+    view! {
+        {move || {
+            progress
+                .get()
+                .map(|p| {
+                    let percent = (p.read * 100).checked_div(p.total).unwrap_or_default();
+                    let title = match p.state {
+                        ExportState::Running => {
+                            format!("Exporting log entries… {} of {} read", p.read, p.total)
+                        }
+                        ExportState::Stopped => "Export stopped, no file was downloaded".to_string(),
+                        ExportState::Done => "Export completed".to_string(),
+                    };
+                    let summary = format!(
+                        "{} matching the selected criteria.",
+                        maybe_plural(p.exported, "entry", "entries"),
+                    );
+                    let is_running = p.state == ExportState::Running;
+
+                    view! {
+                        <ProgressCard
+                            title
+                            summary
+                            percent
+                            is_running
+                            on_stop
+                            on_dismiss=Callback::new(move |_| progress.set(None))
+                        />
+                    }
+                })
+        }}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_addresses() {
+        let redactor = Redactor::new();
+        for (text, expected) in [
+            ("ip:10.0.0.1", "ip:[ip]"),
+            ("addr:1.2.3.4", "addr:[ip]"),
+            ("a.1.2.3.4", "a.[ip]"),
+            ("Connected to 192.168.1.1:25.", "Connected to [ip]:25."),
+            ("(10.0.0.1, 10.0.0.2)", "([ip], [ip])"),
+            ("remote.ip = ::1", "remote.ip = [ip]"),
+            ("addr:2001:db8::1.", "addr:[ip]."),
+            ("[::1]:8080", "[[ip]]:8080"),
+            ("mapped ::ffff:10.0.0.1 address", "mapped [ip] address"),
+            ("from user@example.org", "from [email]"),
+            ("version 1.2.3.4.5", "version 1.2.3.4.5"),
+            ("v1.2.3.4", "v1.2.3.4"),
+            ("999.1.1.1", "999.1.1.1"),
+            ("took 12:30:45", "took 12:30:45"),
+            ("at 2024-05-01T10:00:00Z", "at 2024-05-01T10:00:00Z"),
+            ("mac 00:11:22:33:44:55", "mac 00:11:22:33:44:55"),
+            ("feed::1x", "feed::1x"),
+        ] {
+            assert_eq!(redactor.redact(text), expected, "{text}");
+        }
+    }
+}
@@ -0,0 +1,588 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use ahash::AHashMap;
+use leptos::*;
+use leptos_router::*;
+
+use crate::{
+    components::{
+        badge::Badge,
+        icon::{IconArrowDownTray, IconXMark},
+        list::{
+            header::ColumnList,
+            pagination::Pagination,
+            row::HIGHLIGHT_ROW_CLASS,
+            toolbar::{FilterInput, FilterToggles, PollingControl, SearchBox, ToolbarButton},
+            Filters, Footer, ListItem, ListSection, ListTable, Toolbar, ZeroResults,
+        },
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        file::download_file,
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        polling::{use_polling, ChangeTracker},
+    },
+    pages::{
+        manage::logs::{
+            export::{export_logs, ExportProgress, ExportState, ExportStatus, LogFormat},
+            fetch_all_logs, highlight_segments, LogEntry, LogFilter, LOG_LEVELS,
+        },
+        maybe_plural, FormatDateTime, List,
+    },
+};
+
+const PAGE_SIZE: u32 = 50;
+const MAX_FIELD_COLUMNS: usize = 4;
+
+#[component]
+pub fn Logs() -> impl IntoView {
+    let query = use_query_map();
+    let page = create_memo(move |_| {
+        query
+            .with(|q| q.get("page").and_then(|page| page.parse::<u32>().ok()))
+            .filter(|&page| page > 0)
+            .unwrap_or(1)
+    });
+    let filter = create_memo(move |_| query.with(LogFilter::from_query));
+
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let logs = create_resource(
+        move || (page(), filter()),
+        move |(page, filter)| {
+            let auth = auth.get_untracked();
+
+            async move {
+                if !filter.has_local_criteria() {
+                    return filter
+                        .apply(
+                            HttpRequest::get("/api/logs")
+                                .with_authorization(&auth)
+                                .with_parameter("page", page.to_string())
+                                .with_parameter("limit", PAGE_SIZE.to_string()),
+                        )
+                        .send::<List<LogEntry>>()
+                        .await;
+                }
+
+                // Only the text filter runs on the server, so the matching
+                // entries are collected first and paginated here.
+                let mut matches = Vec::new();
+                fetch_all_logs(&auth, &filter, |logs, _, _| {
+                    matches.extend(logs);
+                    true
+                })
+                .await?;
+                Ok(List {
+                    total: matches.len() as u64,
+                    items: matches
+                        .into_iter()
+                        .skip(((page - 1) * PAGE_SIZE) as usize)
+                        .take(PAGE_SIZE as usize)
+                        .collect(),
+                })
+            }
+        },
+    );
+
+    let poller = use_polling("logs", move || logs.refetch());
+    poller.watch(logs);
+    let changes = ChangeTracker::<String, ()>::new();
+    let on_change = Callback::new(move |filter: LogFilter| {
+        use_navigate()(&filter.url(None), Default::default());
+    });
+    let set_filter = move |update: &dyn Fn(&mut LogFilter)| {
+        let mut filter = filter.get();
+        update(&mut filter);
+        on_change.call(filter);
+    };
+
+    let export_progress = create_rw_signal(None);
+    let export_stop = create_rw_signal(false);
+    let export_format = create_rw_signal(LogFormat::Log);
+    let export_redact = create_rw_signal(false);
+    let export_action = create_action(move |(format, redact): &(LogFormat, bool)| {
+        let (format, redact) = (*format, *redact);
+        let auth = auth.get();
+        let filter = filter.get();
+
+        async move {
+            match export_logs(auth, filter, format, redact, export_progress, export_stop).await {
+                Ok(Some((contents, exported))) if exported > 0 => {
+                    if let Err(err) =
+                        download_file(&format.file_name(), format.content_type(), contents)
+                    {
+                        alert.set(Alert::error(format!("Failed to download file: {err}")));
+                    } else {
+                        alert.set(Alert::success(format!(
+                            "Exported {}.",
+                            maybe_plural(exported, "log entry", "log entries")
+                        )));
+                    }
+                }
+                Ok(Some(_)) => {
+                    alert.set(Alert::warning("There are no log entries to export"));
+                }
+                Ok(None) => {}
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+    let confirm_export = move || {
+        modal.set(
+            Modal::with_title("Download logs")
+                .with_message(
+                    "Download every log entry matching the current filter and time range, on all pages.",
+                )
+                .with_content(move || {
+                    view! {
+                        <div class="space-y-3">
+                            <select
+                                class="py-2 px-3 pe-9 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                                on:change=move |ev| {
+                                    export_format
+                                        .set(
+                                            if event_target_value(&ev) == "ndjson" {
+                                                LogFormat::Ndjson
+                                            } else {
+                                                LogFormat::Log
+                                            },
+                                        );
+                                }
+                            >
+
+                                <option
+                                    value="log"
+                                    selected=move || export_format.get() == LogFormat::Log
+                                >
+                                    Plain text (.log)
+                                </option>
+                                <option
+                                    value="ndjson"
+                                    selected=move || export_format.get() == LogFormat::Ndjson
+                                >
+                                    Newline-delimited JSON (.ndjson)
+                                </option>
+                            </select>
+                            <label class="flex items-center gap-x-2 text-sm text-gray-600 dark:text-gray-400">
+                                <input
+                                    type="checkbox"
+                                    class="shrink-0 border-gray-300 rounded text-blue-600 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-600 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                                    prop:checked=move || export_redact.get()
+                                    on:change=move |ev| export_redact.set(event_target_checked(&ev))
+                                />
+                                Redact email addresses and IP addresses
+                            </label>
+                        </div>
+                    }
+                        .into_view()
+                })
+                .with_button("Download")
+                .with_callback(move || {
+                    export_action
+                        .dispatch((export_format.get_untracked(), export_redact.get_untracked()));
+                }),
+        );
+    };
+
+    let total_results = create_rw_signal(None::<u32>);
+
+    view! {
+        <ListSection>
+            <ExportStatus progress=export_progress on_stop=move |_| export_stop.set(true)/>
+            <ListTable title="Log files" subtitle="View and search log entries">
+                <Toolbar slot>
+                    <SearchBox
+                        value=Signal::derive(move || filter.get().text)
+                        on_search=Callback::new(move |value: String| {
+                            set_filter(&|filter| filter.text = Some(value.clone()));
+                        })
+                    />
+
+                    <PollingControl poller/>
+
+                    <ToolbarButton
+                        text="Download"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            if export_progress
+                                .get()
+                                .map_or(true, |p: ExportProgress| p.state != ExportState::Running)
+                            {
+                                confirm_export();
+                            }
+                        })
+                    >
+
+                        <IconArrowDownTray/>
+                    </ToolbarButton>
+
+                    <ToolbarButton
+                        text="Live tail"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            use_navigate()(&filter.get().tail_url(), Default::default());
+                        })
+                    />
+
+                </Toolbar>
+
+                <Filters slot>
+                    <LogFilterBar filter=filter on_change=on_change/>
+                </Filters>
+
+                <Transition fallback=Skeleton>
+                    {move || match logs.get() {
+                        None => None,
+                        Some(Err(http::Error::Unauthorized)) => {
+                            use_navigate()("/login", Default::default());
+                            Some(view! { <div></div> }.into_view())
+                        }
+                        Some(Err(err)) => {
+                            total_results.set(Some(0));
+                            alert.set(Alert::from(err));
+                            Some(view! { <Skeleton/> }.into_view())
+                        }
+                        Some(Ok(logs)) if !logs.items.is_empty() => {
+                            total_results.set(Some(logs.total as u32));
+                            let filter = filter.get_untracked();
+                            changes
+                                .update(
+                                    (page.get_untracked(), filter.clone()),
+                                    logs.items.iter().map(|log| (log.id(), ())),
+                                );
+                            let columns = field_columns(&logs.items);
+                            let headers = ["Date", "Level"]
+                                .into_iter()
+                                .map(|header| header.to_string())
+                                .chain(columns.iter().cloned())
+                                .chain(["Message".to_string()])
+                                .collect::<Vec<_>>();
+                            Some(
+                                view! {
+                                    <ColumnList headers=headers>
+
+                                        <For
+                                            each=move || logs.items.clone()
+                                            key=|log| log.id()
+                                            children=move |log| {
+                                                view! {
+                                                    <LogItem
+                                                        highlight=changes.is_changed(&log.id())
+                                                        columns=columns.clone()
+                                                        filter=filter.clone()
+                                                        on_change=on_change
+                                                        log
+                                                    />
+                                                }
+                                            }
+                                        />
+
+                                    </ColumnList>
+                                }
+                                    .into_view(),
+                            )
+                        }
+                        Some(Ok(_)) => {
+                            total_results.set(Some(0));
+                            Some(
+                                view! {
+                                    <ZeroResults
+                                        title="No results"
+                                        subtitle="No log entries were found with the selected criteria."
+                                    />
+                                }
+                                    .into_view(),
+                            )
+                        }
+                    }}
+
+                </Transition>
+
+                <Footer slot>
+
+                    <Pagination
+                        current_page=page
+                        total_results=total_results.read_only()
+                        page_size=PAGE_SIZE
+                        on_page_change=move |page: u32| {
+                            use_navigate()(&filter.get().url(Some(page)), Default::default());
+                        }
+                    />
+
+                </Footer>
+            </ListTable>
+        </ListSection>
+    }
+}
+
+#[component]
+pub fn LogFilterBar(
+    #[prop(into)] filter: Signal<LogFilter>,
+    #[prop(into)] on_change: Callback<LogFilter, ()>,
+) -> impl IntoView {
+    let set_filter = move |update: &dyn Fn(&mut LogFilter)| {
+        let mut filter = filter.get();
+        update(&mut filter);
+        on_change.call(filter);
+    };
+
+    view! {
+        <FilterToggles
+            selected=Signal::derive(move || filter.get().levels)
+            options=LOG_LEVELS
+            on_toggle=Callback::new(move |level: String| {
+                set_filter(&|filter| filter.toggle_level(&level));
+            })
+        />
+        <FilterInput
+            placeholder="From"
+            input_type="datetime-local"
+            value=Signal::derive(move || filter.get().from)
+            on_change=Callback::new(move |value: String| {
+                set_filter(&|filter| filter.from = Some(value.clone()));
+            })
+        />
+        <FilterInput
+            placeholder="To"
+            input_type="datetime-local"
+            value=Signal::derive(move || filter.get().to)
+            on_change=Callback::new(move |value: String| {
+                set_filter(&|filter| filter.to = Some(value.clone()));
+            })
+        />
+
+        <For
+            each=move || filter.get().fields
+            key=|field| field.clone()
+            children=move |(key, value)| {
+                let title = format!("{key} = {value}");
+                view! {
+                    <Badge color=Color::Blue large=true>
+                        {title}
+                        <button
+                            type="button"
+                            class="inline-flex flex-shrink-0 justify-center items-center size-4 rounded-full hover:bg-blue-200 dark:hover:bg-blue-900"
+                            on:click=move |_| {
+                                set_filter(&|filter| filter.toggle_field(&key, &value));
+                            }
+                        >
+
+                            <span class="sr-only">Remove filter</span>
+                            <IconXMark attr:class="flex-shrink-0 size-3"/>
+                        </button>
+                    </Badge>
+                }
+            }
+        />
+
+        <Show when=move || !filter.get().is_empty()>
+            <div class="ms-auto">
+                <ToolbarButton
+                    text="Clear filters"
+                    color=Color::Gray
+                    on_click=Callback::new(move |_| {
+                        on_change.call(LogFilter::default());
+                    })
+                />
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+pub fn LogItem(
+    log: LogEntry,
+    columns: Vec<String>,
+    filter: LogFilter,
+    #[prop(into)] on_change: Callback<LogFilter, ()>,
+    #[prop(optional)] highlight: bool,
+) -> impl IntoView {
+    let timestamp = log.timestamp.format_date_time();
+    let mut fields = log.fields();
+    let column_values = columns
+        .iter()
+        .map(|key| {
+            fields
+                .fields
+                .iter()
+                .position(|(k, _)| k == key)
+                .map(|pos| fields.fields.remove(pos))
+        })
+        .collect::<Vec<_>>();
+    let text = highlight_segments(&fields.text, filter.text.as_deref());
+
+    view! {
+        <tr class=highlight.then_some(HIGHLIGHT_ROW_CLASS)>
+            <ListItem>
+                <span class="text-sm text-gray-500">{timestamp}</span>
+            </ListItem>
+
+            <ListItem>
+
+                {
+                    let color = match log.level.as_str() {
+                        "ERROR" => Color::Red,
+                        "WARN" => Color::Yellow,
+                        "INFO" => Color::Green,
+                        "DEBUG" => Color::Blue,
+                        _ => Color::Gray,
+                    };
+                    view! { <Badge color=color>{log.level}</Badge> }
+                }
+
+            </ListItem>
+
+            {column_values
+                .into_iter()
+                .map(|field| {
+                    let filter = filter.clone();
+                    view! {
+                        <ListItem>
+
+                            {match field {
+                                Some((key, value)) => {
+                                    view! { <FieldValue key value filter=filter.clone() on_change/> }
+                                        .into_view()
+                                }
+                                None => {
+                                    view! { <span class="text-sm text-gray-400">-</span> }
+                                        .into_view()
+                                }
+                            }}
+
+                        </ListItem>
+                    }
+                })
+                .collect_view()}
+
+            <ListItem>
+                <span class="text-sm text-gray-500 text-wrap">
+
+                    {text
+                        .into_iter()
+                        .map(|(segment, is_match)| {
+                            if is_match {
+                                view! {
+                                    <mark class="bg-yellow-200 text-gray-800 rounded dark:bg-yellow-500/40 dark:text-white">
+                                        {segment}
+                                    </mark>
+                                }
+                                    .into_view()
+                            } else {
+                                segment.into_view()
+                            }
+                        })
+                        .collect_view()}
+
+                </span>
+                <div class="flex flex-wrap gap-1 mt-1">
+
+                    {fields
+                        .fields
+                        .into_iter()
+                        .map(|(key, value)| {
+                            let filter = filter.clone();
+                            view! {
+                                <span class="text-xs text-gray-400">
+                                    {format!("{key} = ")}
+                                    <FieldValue key value filter=filter.clone() on_change/>
+                                </span>
+                            }
+                        })
+                        .collect_view()}
+
+                </div>
+            </ListItem>
+
+        </tr>
+    }
+}
+
+#[component]
+fn FieldValue(
+    key: String,
+    value: String,
+    filter: LogFilter,
+    #[prop(into)] on_change: Callback<LogFilter, ()>,
+) -> impl IntoView {
+    let is_active = filter.has_field(&key, &value);
+    let class = if is_active {
+        "text-xs font-medium text-blue-600 bg-yellow-200 rounded px-1 hover:underline dark:text-blue-500 dark:bg-yellow-500/40"
+    } else {
+        "text-xs font-medium text-blue-600 hover:underline dark:text-blue-500"
+    };
+    let label = if value.is_empty() {
+        "\"\"".to_string()
+    } else {
+        value.clone()
+    };
+
+    view! {
+        <button
+            type="button"
+            class=class
+            title=format!("Filter by {key}")
+            on:click=move |_| {
+                let mut filter = filter.clone();
+                filter.toggle_field(&key, &value);
+                on_change.call(filter);
+            }
+        >
+
+            {label}
+        </button>
+    }
+}
+
+// The fields present in most entries of the page, in order of first appearance
+fn field_columns(logs: &[LogEntry]) -> Vec<String> {
+    let mut counts: AHashMap<String, (usize, usize)> = AHashMap::new();
+    for log in logs {
+        for (key, _) in log.fields().fields {
+            let next_pos = counts.len();
+            counts.entry(key).or_insert((0, next_pos)).0 += 1;
+        }
+    }
+
+    let mut columns = counts.into_iter().collect::<Vec<_>>();
+    columns.sort_by(|(_, (a_count, a_pos)), (_, (b_count, b_pos))| {
+        b_count.cmp(a_count).then(a_pos.cmp(b_pos))
+    });
+    columns.truncate(MAX_FIELD_COLUMNS);
+    columns.sort_by_key(|(_, (_, pos))| *pos);
+    columns.into_iter().map(|(key, _)| key).collect()
+}
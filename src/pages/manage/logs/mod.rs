@@ -0,0 +1,541 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+pub mod export;
+pub mod list;
+pub mod tail;
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use chrono::{DateTime, Utc};
+use leptos_router::ParamsMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        http::{self, HttpRequest},
+        oauth::AuthToken,
+        url::UrlBuilder,
+    },
+    pages::{
+        queue::messages::{deserialize_datetime, schedule::parse_local_datetime},
+        List,
+    },
+};
+
+const FETCH_PAGE_SIZE: u32 = 500;
+
+pub const LOG_LEVELS: &[(&str, &str)] = &[
+    ("ERROR", "Error"),
+    ("WARN", "Warning"),
+    ("INFO", "Info"),
+    ("DEBUG", "Debug"),
+    ("TRACE", "Trace"),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LogEntry {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub message: String,
+}
+
+// The free text of a log message and the `key = value` pairs that follow it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFields {
+    pub text: String,
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LogFilter {
+    pub text: Option<String>,
+    pub levels: Vec<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogEntry {
+    pub fn id(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.level.hash(&mut hasher);
+        self.message.hash(&mut hasher);
+        self.timestamp.hash(&mut hasher);
+        hasher.finish().to_string()
+    }
+
+    pub fn fields(&self) -> LogFields {
+        LogFields::parse(&self.message)
+    }
+}
+
+impl LogFields {
+    pub fn parse(message: &str) -> Self {
+        let mut fields = Vec::new();
+        let mut text_end = None;
+        let bytes = message.as_bytes();
+        let mut pos = 0;
+
+        while let Some(eq_pos) = message[pos..].find('=').map(|p| p + pos) {
+            // Key: identifier immediately before the '=', ignoring spaces
+            let mut key_end = eq_pos;
+            while key_end > pos && bytes[key_end - 1] == b' ' {
+                key_end -= 1;
+            }
+            let mut key_start = key_end;
+            while key_start > pos
+                && (bytes[key_start - 1].is_ascii_alphanumeric()
+                    || matches!(bytes[key_start - 1], b'_' | b'.' | b'-'))
+            {
+                key_start -= 1;
+            }
+
+            // Value: quoted string or a token ending at a separator
+            let mut value_start = eq_pos + 1;
+            while value_start < bytes.len() && bytes[value_start] == b' ' {
+                value_start += 1;
+            }
+            let (value, value_end) = if bytes.get(value_start) == Some(&b'"') {
+                let mut value = String::new();
+                let mut escaped = false;
+                let mut end = bytes.len();
+                for (idx, ch) in message[value_start + 1..].char_indices() {
+                    match ch {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            end = value_start + 1 + idx + 1;
+                            break;
+                        }
+                        _ => {
+                            escaped = false;
+                            value.push(ch);
+                        }
+                    }
+                }
+                (value, end)
+            } else {
+                let end = message[value_start..]
+                    .find(|ch: char| ch.is_whitespace() || matches!(ch, ',' | ')' | ']'))
+                    .map_or(message.len(), |p| p + value_start);
+                (message[value_start..end].to_string(), end)
+            };
+
+            if key_start < key_end
+                && bytes[key_start].is_ascii_alphabetic()
+                && (key_start == 0
+                    || !bytes[key_start - 1].is_ascii_graphic()
+                    || matches!(bytes[key_start - 1], b'(' | b',' | b'['))
+            {
+                text_end.get_or_insert(key_start);
+                fields.push((message[key_start..key_end].to_string(), value));
+            }
+            pos = value_end.max(eq_pos + 1);
+        }
+
+        let text = match text_end {
+            Some(end) => message[..end]
+                .trim_end_matches(|ch: char| {
+                    ch.is_whitespace() || matches!(ch, '(' | '[' | ',' | ':')
+                })
+                .to_string(),
+            None => message.to_string(),
+        };
+
+        LogFields { text, fields }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl LogFilter {
+    pub fn from_query(query: &ParamsMap) -> Self {
+        let param = |name: &str| {
+            query
+                .get(name)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        LogFilter {
+            text: param("filter"),
+            levels: param("level")
+                .map(|levels| {
+                    levels
+                        .split(',')
+                        .filter(|level| LOG_LEVELS.iter().any(|(id, _)| id == level))
+                        .map(|level| level.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            from: param("from").filter(|from| parse_local_datetime(from).is_some()),
+            to: param("to").filter(|to| parse_local_datetime(to).is_some()),
+            fields: param("fields")
+                .map(|fields| {
+                    fields
+                        .split(';')
+                        .filter_map(|field| {
+                            let (key, value) = field.split_once('=')?;
+                            let key = key.trim();
+                            (!key.is_empty()).then(|| (key.to_string(), value.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    // The server only filters by text, the first field value narrows down the results otherwise
+    pub fn apply(&self, request: HttpRequest) -> HttpRequest {
+        request.with_optional_parameter(
+            "filter",
+            self.text
+                .as_ref()
+                .or_else(|| self.fields.first().map(|(_, value)| value)),
+        )
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if !self.levels.is_empty() && !self.levels.iter().any(|level| level == &entry.level) {
+            return false;
+        }
+        if let Some(from) = self.from.as_deref().and_then(parse_local_datetime) {
+            if entry.timestamp < from {
+                return false;
+            }
+        }
+        if let Some(to) = self.to.as_deref().and_then(parse_local_datetime) {
+            if entry.timestamp > to {
+                return false;
+            }
+        }
+        if let Some(text) = &self.text {
+            if !entry.message.to_lowercase().contains(&text.to_lowercase()) {
+                return false;
+            }
+        }
+        if !self.fields.is_empty() {
+            let fields = entry.fields();
+            if !self
+                .fields
+                .iter()
+                .all(|(key, value)| fields.get(key) == Some(value.as_str()))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn has_field(&self, key: &str, value: &str) -> bool {
+        self.fields.iter().any(|(k, v)| k == key && v == value)
+    }
+
+    pub fn toggle_field(&mut self, key: &str, value: &str) {
+        if self.has_field(key, value) {
+            self.fields.retain(|(k, v)| !(k == key && v == value));
+        } else {
+            self.fields.retain(|(k, _)| k != key);
+            self.fields.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn toggle_level(&mut self, level: &str) {
+        if let Some(pos) = self.levels.iter().position(|l| l == level) {
+            self.levels.remove(pos);
+        } else {
+            self.levels.push(level.to_string());
+        }
+    }
+
+    pub fn has_local_criteria(&self) -> bool {
+        !self.levels.is_empty()
+            || self.from.is_some()
+            || self.to.is_some()
+            || !self.fields.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none()
+            && self.levels.is_empty()
+            && self.from.is_none()
+            && self.to.is_none()
+            && self.fields.is_empty()
+    }
+
+    pub fn url(&self, page: Option<u32>) -> String {
+        self.build_url("/manage/logs", page)
+    }
+
+    pub fn tail_url(&self) -> String {
+        self.build_url("/manage/logs/tail", None)
+    }
+
+    fn build_url(&self, base_url: &str, page: Option<u32>) -> String {
+        UrlBuilder::new(base_url)
+            .with_optional_parameter("page", page.map(|page| page.to_string()))
+            .with_optional_parameter("filter", self.text.as_ref())
+            .with_optional_parameter(
+                "level",
+                (!self.levels.is_empty()).then(|| self.levels.join(",")),
+            )
+            .with_optional_parameter("from", self.from.as_ref())
+            .with_optional_parameter("to", self.to.as_ref())
+            .with_optional_parameter(
+                "fields",
+                (!self.fields.is_empty()).then(|| {
+                    self.fields
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(";")
+                }),
+            )
+            .finish()
+    }
+}
+
+// The callback receives each page, the entries read so far and the server total
+pub async fn fetch_all_logs(
+    auth: &AuthToken,
+    filter: &LogFilter,
+    mut on_page: impl FnMut(Vec<LogEntry>, u64, u64) -> bool,
+) -> http::Result<()> {
+    let mut page = 1;
+    loop {
+        let logs = filter
+            .apply(
+                HttpRequest::get("/api/logs")
+                    .with_authorization(auth)
+                    .with_parameter("page", page.to_string())
+                    .with_parameter("limit", FETCH_PAGE_SIZE.to_string()),
+            )
+            .send::<List<LogEntry>>()
+            .await?;
+        let read = (page as u64 - 1) * FETCH_PAGE_SIZE as u64 + logs.items.len() as u64;
+        let is_last = logs.items.len() < FETCH_PAGE_SIZE as usize || read >= logs.total;
+        let items = logs
+            .items
+            .into_iter()
+            .filter(|log| filter.matches(log))
+            .collect();
+
+        if !on_page(items, read, logs.total) || is_last {
+            return Ok(());
+        }
+        page += 1;
+    }
+}
+
+pub fn highlight_segments(text: &str, term: Option<&str>) -> Vec<(String, bool)> {
+    let term = term.unwrap_or_default();
+    let lower_text = text.to_lowercase();
+    let lower_term = term.to_lowercase();
+
+    // Lowercasing may change byte offsets for some scripts
+    if term.is_empty() || lower_text.len() != text.len() || lower_term.len() != term.len() {
+        return vec![(text.to_string(), false)];
+    }
+
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    for (start, _) in lower_text.match_indices(&lower_term) {
+        if start < pos {
+            continue;
+        }
+        if start > pos {
+            segments.push((text[pos..start].to_string(), false));
+        }
+        pos = start + term.len();
+        segments.push((text[start..pos].to_string(), true));
+    }
+    if pos < text.len() {
+        segments.push((text[pos..].to_string(), false));
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn query(values: &[(&str, &str)]) -> ParamsMap {
+        let mut query = ParamsMap::new();
+        for (key, value) in values {
+            query.0.insert(key.to_string(), value.to_string());
+        }
+        query
+    }
+
+    #[test]
+    fn parse_log_fields() {
+        for (message, text, expected) in [
+            ("Server started", "Server started", vec![]),
+            (
+                "Delivery attempt failed (queue_id = 42, domain = \"example.org\")",
+                "Delivery attempt failed",
+                fields(&[("queue_id", "42"), ("domain", "example.org")]),
+            ),
+            (
+                "SMTP session: remote.ip=10.0.0.1, span.id=7",
+                "SMTP session",
+                fields(&[("remote.ip", "10.0.0.1"), ("span.id", "7")]),
+            ),
+            (
+                "Rejected [reason = \"say \\\"no\\\" (550)\", code=550]",
+                "Rejected",
+                fields(&[("reason", "say \"no\" (550)"), ("code", "550")]),
+            ),
+            (
+                "Evaluated 1 = 2 as false",
+                "Evaluated 1 = 2 as false",
+                vec![],
+            ),
+            (
+                "url=https://host/?a=b",
+                "",
+                fields(&[("url", "https://host/?a=b")]),
+            ),
+        ] {
+            let parsed = LogFields::parse(message);
+            assert_eq!(parsed.text, text, "{message}");
+            assert_eq!(parsed.fields, expected, "{message}");
+        }
+
+        let parsed = LogFields::parse("Message queued (id = 1, size = 1024)");
+        assert_eq!(parsed.get("size"), Some("1024"));
+        assert_eq!(parsed.get("from"), None);
+    }
+
+    #[test]
+    fn log_filter_query() {
+        let filter = LogFilter::from_query(&query(&[
+            ("filter", " timeout "),
+            ("level", "ERROR,bogus,WARN"),
+            ("to", "not a date"),
+            ("fields", "domain=example.org;=orphan;invalid;queue_id=42"),
+            ("page", "3"),
+        ]));
+        assert_eq!(
+            filter,
+            LogFilter {
+                text: Some("timeout".to_string()),
+                levels: vec!["ERROR".to_string(), "WARN".to_string()],
+                from: None,
+                to: None,
+                fields: fields(&[("domain", "example.org"), ("queue_id", "42")]),
+            }
+        );
+        assert!(filter.has_local_criteria());
+        assert_eq!(
+            filter.url(Some(2)),
+            concat!(
+                "/manage/logs?page=2&filter=timeout&level=ERROR%2CWARN",
+                "&fields=domain%3Dexample.org%3Bqueue_id%3D42"
+            )
+        );
+        assert_eq!(
+            filter.tail_url(),
+            concat!(
+                "/manage/logs/tail?filter=timeout&level=ERROR%2CWARN",
+                "&fields=domain%3Dexample.org%3Bqueue_id%3D42"
+            )
+        );
+
+        let empty = LogFilter::from_query(&query(&[("filter", " "), ("level", "bogus")]));
+        assert!(empty.is_empty());
+        assert_eq!(empty.url(None), "/manage/logs?");
+
+        let text_only = LogFilter {
+            text: Some("timeout".to_string()),
+            ..Default::default()
+        };
+        assert!(!text_only.has_local_criteria());
+    }
+
+    #[test]
+    fn log_filter_url_round_trip() {
+        let filter = LogFilter {
+            text: Some("a&b c".to_string()),
+            levels: vec!["INFO".to_string()],
+            from: None,
+            to: None,
+            fields: fields(&[("remote.ip", "::1"), ("reason", "a=b")]),
+        };
+        let url = filter.url(None);
+        let (_, query_string) = url.split_once('?').unwrap();
+        let parsed = ParamsMap(
+            form_urlencoded::parse(query_string.as_bytes())
+                .into_owned()
+                .collect(),
+        );
+        assert_eq!(LogFilter::from_query(&parsed), filter);
+    }
+
+    #[test]
+    fn highlight_matches() {
+        let segments = |text: &str, term: Option<&str>| {
+            highlight_segments(text, term)
+                .into_iter()
+                .map(|(text, highlight)| if highlight { format!("[{text}]") } else { text })
+                .collect::<String>()
+        };
+
+        assert_eq!(segments("Connection timeout", None), "Connection timeout");
+        assert_eq!(
+            segments("Connection timeout", Some("")),
+            "Connection timeout"
+        );
+        assert_eq!(
+            segments("Timeout after timeout", Some("TIMEOUT")),
+            "[Timeout] after [timeout]"
+        );
+        assert_eq!(segments("aaaa", Some("aa")), "[aa][aa]");
+        assert_eq!(segments("no match", Some("xyz")), "no match");
+        assert_eq!(segments("İstanbul", Some("i")), "İstanbul");
+        assert_eq!(
+            highlight_segments("abc", Some("b")),
+            vec![
+                ("a".to_string(), false),
+                ("b".to_string(), true),
+                ("c".to_string(), false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::{HashSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use leptos::*;
+use leptos_router::*;
+
+use crate::{
+    components::{
+        list::{
+            header::ColumnList,
+            toolbar::{PollingControl, SearchBox, ToolbarButton},
+            Filters, Footer, ListSection, ListTable, Toolbar, ZeroResults,
+        },
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        polling::use_polling,
+    },
+    pages::{
+        manage::logs::{
+            list::{LogFilterBar, LogItem},
+            LogEntry, LogFilter,
+        },
+        maybe_plural, List,
+    },
+};
+
+const TAIL_FETCH_SIZE: u32 = 100;
+const TAIL_MAX_PAGES: u32 = 10;
+const TAIL_BUFFER_SIZE: usize = 1000;
+const TAIL_INTERVAL: u64 = 5;
+
+// Oldest first, the oldest entries are dropped once full
+#[derive(Clone, Default)]
+struct TailBuffer {
+    entries: VecDeque<LogEntry>,
+    last_timestamp: Option<DateTime<Utc>>,
+    last_ids: HashSet<String>,
+    dropped: usize,
+    has_gap: bool,
+}
+
+#[component]
+pub fn LogTail() -> impl IntoView {
+    let query = use_query_map();
+    let filter = create_memo(move |_| query.with(LogFilter::from_query));
+
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let buffer = create_rw_signal(TailBuffer::default());
+    let generation = store_value(0u64);
+
+    let fetch_logs = create_action(move |_: &()| {
+        let auth = auth.get_untracked();
+        let filter = filter.get_untracked();
+        let current_generation = generation.get_value();
+
+        async move {
+            // Keep paging until reaching the entries already seen
+            let last_timestamp = buffer.with_untracked(|buffer| buffer.last_timestamp);
+            let mut logs = Vec::new();
+            let mut has_gap = false;
+            let mut page = 1;
+            let result = loop {
+                match filter
+                    .apply(
+                        HttpRequest::get("/api/logs")
+                            .with_authorization(&auth)
+                            .with_parameter("page", page.to_string())
+                            .with_parameter("limit", TAIL_FETCH_SIZE.to_string()),
+                    )
+                    .send::<List<LogEntry>>()
+                    .await
+                {
+                    Ok(list) => {
+                        let is_last = list.items.len() < TAIL_FETCH_SIZE as usize
+                            || (page * TAIL_FETCH_SIZE) as u64 >= list.total;
+                        let caught_up = last_timestamp.map_or(true, |last_timestamp| {
+                            list.items.iter().any(|log| log.timestamp <= last_timestamp)
+                        });
+                        logs.extend(list.items);
+                        if caught_up || is_last {
+                            break Ok(());
+                        } else if page == TAIL_MAX_PAGES {
+                            has_gap = true;
+                            break Ok(());
+                        }
+                        page += 1;
+                    }
+                    Err(err) => break Err(err),
+                }
+            };
+
+            // Discard results requested before the filter changed
+            if current_generation != generation.get_value() {
+                return None;
+            }
+
+            match result {
+                Ok(_) => {
+                    buffer.update(|buffer| {
+                        buffer.has_gap |= has_gap;
+                        buffer.append(logs.into_iter().filter(|log| filter.matches(log)))
+                    });
+                    Some(true)
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                    None
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                    Some(false)
+                }
+            }
+        }
+    });
+    let poller = use_polling("logs-tail", move || fetch_logs.dispatch(()));
+    if poller.interval.get_untracked() == 0 {
+        poller.interval.set(TAIL_INTERVAL);
+    }
+    create_effect(move |_| {
+        if let Some(Some(is_ok)) = fetch_logs.value().get() {
+            poller.report(&if is_ok { Ok(()) } else { Err(()) });
+        }
+    });
+
+    // Start over whenever the filter changes
+    create_effect(move |_| {
+        filter.with(|_| ());
+        generation.update_value(|generation| *generation += 1);
+        buffer.set(TailBuffer::default());
+        poller.refresh();
+    });
+
+    // Keep the newest entries in view unless paused
+    create_effect(move |_| {
+        buffer.with(|buffer| buffer.entries.len());
+        if !poller.paused.get_untracked() {
+            request_animation_frame(move || {
+                if let Some(body) = document().body() {
+                    window().scroll_to_with_x_and_y(0.0, body.scroll_height() as f64);
+                }
+            });
+        }
+    });
+
+    let on_change = Callback::new(move |filter: LogFilter| {
+        use_navigate()(&filter.tail_url(), Default::default());
+    });
+
+    view! {
+        <ListSection>
+            <ListTable title="Live log" subtitle="Follow new log entries as they are written">
+                <Toolbar slot>
+                    <SearchBox
+                        value=Signal::derive(move || filter.get().text)
+                        on_search=Callback::new(move |value: String| {
+                            let mut filter = filter.get();
+                            filter.text = Some(value);
+                            on_change.call(filter);
+                        })
+                    />
+
+                    <PollingControl poller/>
+
+                    <ToolbarButton
+                        text="Clear"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            buffer.update(|buffer| buffer.clear());
+                        })
+                    />
+
+                    <ToolbarButton
+                        text="Stop tail"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            use_navigate()(&filter.get().url(None), Default::default());
+                        })
+                    />
+
+                </Toolbar>
+
+                <Filters slot>
+                    <LogFilterBar filter=filter on_change=on_change/>
+                </Filters>
+
+                <Show
+                    when=move || buffer.with(|buffer| !buffer.entries.is_empty())
+                    fallback=|| {
+                        view! {
+                            <ZeroResults
+                                title="Waiting for log entries"
+                                subtitle="New log entries matching the selected criteria will appear here."
+                            />
+                        }
+                    }
+                >
+
+                    <ColumnList headers=vec![
+                        "Date".to_string(),
+                        "Level".to_string(),
+                        "Message".to_string(),
+                    ]>
+
+                        <For
+                            each=move || buffer.with(|buffer| buffer.entries.clone())
+                            key=|log| log.id()
+                            children=move |log| {
+                                view! {
+                                    <LogItem
+                                        columns=vec![]
+                                        filter=filter.get_untracked()
+                                        on_change=on_change
+                                        log
+                                    />
+                                }
+                            }
+                        />
+
+                    </ColumnList>
+                </Show>
+
+                <Footer slot>
+                    <div class="px-6 py-4 grid gap-3 md:flex md:justify-between md:items-center border-t border-gray-200 dark:border-gray-700">
+                        <p class="text-sm text-gray-600 dark:text-gray-400">
+
+                            {move || {
+                                let (total, dropped, has_gap) = buffer
+                                    .with(|buffer| {
+                                        (buffer.entries.len(), buffer.dropped, buffer.has_gap)
+                                    });
+                                let mut status = format!(
+                                    "Showing the last {}",
+                                    maybe_plural(total, "entry", "entries"),
+                                );
+                                if has_gap {
+                                    status.push_str(
+                                        ", some entries were written too fast to be fetched",
+                                    );
+                                }
+                                if dropped > 0 {
+                                    status.push_str(
+                                        &format!(", {dropped} older entries were discarded"),
+                                    );
+                                }
+                                if poller.paused.get() {
+                                    status.push_str(" (paused)");
+                                }
+                                status
+                            }}
+
+                        </p>
+                    </div>
+                </Footer>
+            </ListTable>
+        </ListSection>
+    }
+}
+
+impl TailBuffer {
+    // Entries sharing the last timestamp are told apart by their id
+    fn append(&mut self, logs: impl Iterator<Item = LogEntry>) {
+        let mut new_logs = logs
+            .filter(|log| match self.last_timestamp {
+                Some(last_timestamp) => {
+                    log.timestamp > last_timestamp
+                        || (log.timestamp == last_timestamp && !self.last_ids.contains(&log.id()))
+                }
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        new_logs.sort_by_key(|log| log.timestamp);
+
+        for log in new_logs {
+            if self.last_timestamp != Some(log.timestamp) {
+                self.last_timestamp = Some(log.timestamp);
+                self.last_ids.clear();
+            }
+            self.last_ids.insert(log.id());
+            self.entries.push_back(log);
+            if self.entries.len() > TAIL_BUFFER_SIZE {
+                self.entries.pop_front();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // Entries already seen are still skipped
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+        self.has_gap = false;
+    }
+}
@@ -21,6 +21,8 @@
  * for more details.
 */
 
+use std::sync::Arc;
+
 use leptos::*;
 use leptos_router::use_navigate;
 
@@ -32,8 +34,12 @@ use crate::{
     core::{
         http::{self, HttpRequest},
         oauth::use_authorization,
+        schema::Schemas,
+    },
+    pages::config::{
+        reload::{ReloadIssue, ReloadIssues},
+        ReloadSettings,
     },
-    pages::config::ReloadSettings,
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -88,14 +94,18 @@ const ACTIONS: &[Action] = &[
 pub fn Maintenance() -> impl IntoView {
     let auth = use_authorization();
     let alert = use_alerts();
+    let schemas = expect_context::<Arc<Schemas>>();
     let (pending, set_pending) = create_signal(false);
+    let issues = create_rw_signal(Vec::<ReloadIssue>::new());
 
     let execute = create_action(move |idx: &usize| {
         let auth = auth.get();
         let action = ACTIONS[*idx];
+        let schemas = schemas.clone();
 
         async move {
             set_pending.set(true);
+            issues.set(Vec::new());
 
             let err = if action.url.starts_with("/api/reload") {
                 match HttpRequest::get(action.url)
@@ -108,6 +118,7 @@ pub fn Maintenance() -> impl IntoView {
                         if result.errors.is_empty() && result.warnings.is_empty() {
                             alert.set(Alert::success(action.success_message).without_timeout());
                         } else {
+                            issues.set(result.issues(&schemas));
                             alert.set(Alert::from(result));
                         }
                         return;
@@ -186,6 +197,7 @@ pub fn Maintenance() -> impl IntoView {
                 {actions}
 
             </div>
+            <ReloadIssues issues=issues/>
 
         </div>
     }
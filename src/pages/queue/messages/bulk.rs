@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use leptos::*;
+
+use crate::{
+    components::progress::ProgressCard,
+    core::{
+        http::{self, HttpRequest},
+        oauth::AuthToken,
+    },
+    pages::{
+        maybe_plural,
+        queue::messages::{fetch_all_messages, QueueFilter},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    Retry,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkState {
+    Collecting,
+    Running,
+    Stopped,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkProgress {
+    pub action: BulkAction,
+    pub state: BulkState,
+    pub total: usize,
+    pub processed: usize,
+    pub succeeded: usize,
+    pub not_found: usize,
+    pub failed: usize,
+}
+
+impl BulkProgress {
+    pub fn new(action: BulkAction) -> Self {
+        BulkProgress {
+            action,
+            state: BulkState::Collecting,
+            total: 0,
+            processed: 0,
+            succeeded: 0,
+            not_found: 0,
+            failed: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, BulkState::Collecting | BulkState::Running)
+    }
+}
+
+impl BulkAction {
+    fn request(&self, id: u64) -> HttpRequest {
+        match self {
+            BulkAction::Retry => HttpRequest::patch(format!("/api/queue/messages/{id}")),
+            BulkAction::Cancel => HttpRequest::delete(format!("/api/queue/messages/{id}")),
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            BulkAction::Retry => "Retrying",
+            BulkAction::Cancel => "Cancelling",
+        }
+    }
+}
+
+// Ids are collected first so cancelled messages do not shift the pages still to read
+pub async fn run_bulk_action(
+    auth: AuthToken,
+    filter: QueueFilter,
+    action: BulkAction,
+    progress: RwSignal<Option<BulkProgress>>,
+    stop: RwSignal<bool>,
+) -> http::Result<()> {
+    stop.set(false);
+    progress.set(Some(BulkProgress::new(action)));
+
+    let mut ids = Vec::new();
+    fetch_all_messages(&auth, &filter, |messages, _| {
+        ids.extend(messages.into_iter().map(|message| message.id));
+        update(progress, |p| p.total = ids.len());
+        !stop.get_untracked()
+    })
+    .await?;
+    if stop.get_untracked() {
+        update(progress, |p| p.state = BulkState::Stopped);
+        return Ok(());
+    }
+
+    update(progress, |p| p.state = BulkState::Running);
+    for id in ids {
+        if stop.get_untracked() {
+            update(progress, |p| p.state = BulkState::Stopped);
+            return Ok(());
+        }
+
+        let result = action
+            .request(id)
+            .with_authorization(&auth)
+            .send::<bool>()
+            .await;
+        update(progress, |p| {
+            p.processed += 1;
+            match &result {
+                Ok(true) => p.succeeded += 1,
+                Ok(false) | Err(http::Error::NotFound) => p.not_found += 1,
+                Err(_) => p.failed += 1,
+            }
+        });
+
+        if let Err(http::Error::Unauthorized) = result {
+            update(progress, |p| p.state = BulkState::Stopped);
+            return Err(http::Error::Unauthorized);
+        }
+    }
+
+    update(progress, |p| p.state = BulkState::Done);
+
+    Ok(())
+}
+
+fn update(progress: RwSignal<Option<BulkProgress>>, f: impl FnOnce(&mut BulkProgress)) {
+    progress.update(|progress| {
+        if let Some(progress) = progress {
+            f(progress);
+        }
+    });
+}
+
+#[component]
+pub fn BulkStatus(
+    progress: RwSignal<Option<BulkProgress>>,
+    #[prop(into)] on_stop: Callback<(), ()>,
+) -> impl IntoView {
+    view! {
+        {move || {
+            progress
+                .get()
+                .map(|p| {
+                    let percent = (p.processed * 100).checked_div(p.total).unwrap_or_default();
+                    let title = match p.state {
+                        BulkState::Collecting => {
+                            format!(
+                                "Finding matching messages… {} so far",
+                                p.total,
+                            )
+                        }
+                        BulkState::Running => {
+                            format!("{} messages… {} of {}", p.action.verb(), p.processed, p.total)
+                        }
+                        BulkState::Stopped => {
+                            format!("Stopped after {} of {}", maybe_plural(p.processed, "message", "messages"), p.total)
+                        }
+                        BulkState::Done => {
+                            format!("Processed {}", maybe_plural(p.processed, "message", "messages"))
+                        }
+                    };
+                    let summary = format!(
+                        "{} succeeded, {} not found, {} failed.",
+                        p.succeeded,
+                        p.not_found,
+                        p.failed,
+                    );
+                    let is_running = p.is_running();
+
+                    view! {
+                        <ProgressCard
+                            title
+                            summary
+                            percent=percent as u64
+                            is_running
+                            on_stop
+                            on_dismiss=Callback::new(move |_| progress.set(None))
+                        />
+                    }
+                })
+        }}
+    }
+}
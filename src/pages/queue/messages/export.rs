@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use chrono::{DateTime, Utc};
+
+use crate::pages::queue::messages::{Message, Status};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+const CSV_HEADERS: &[&str] = &[
+    "id",
+    "return_path",
+    "env_id",
+    "created",
+    "size",
+    "priority",
+    "domain",
+    "domain_status",
+    "domain_status_text",
+    "retry_num",
+    "next_retry",
+    "next_notify",
+    "expires",
+    "recipient",
+    "orcpt",
+    "recipient_status",
+    "recipient_status_text",
+];
+
+impl ExportFormat {
+    pub fn file_name(&self) -> String {
+        format!(
+            "queue-{}.{}",
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            match self {
+                ExportFormat::Csv => "csv",
+                ExportFormat::Json => "json",
+            }
+        )
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+        }
+    }
+
+    pub fn serialize(&self, messages: &[Message]) -> Result<String, String> {
+        match self {
+            ExportFormat::Csv => Ok(messages_to_csv(messages)),
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(messages).map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+// Domains without recipients still get a row to keep their retry schedule
+fn messages_to_csv(messages: &[Message]) -> String {
+    let mut csv = String::new();
+    write_row(
+        &mut csv,
+        CSV_HEADERS.iter().map(|header| header.to_string()),
+    );
+
+    for message in messages {
+        for domain in &message.domains {
+            let mut recipients = domain
+                .recipients
+                .iter()
+                .map(|rcpt| {
+                    [
+                        rcpt.address.clone(),
+                        rcpt.orcpt.clone().unwrap_or_default(),
+                        status_name(&rcpt.status).to_string(),
+                        status_text(&rcpt.status),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            if recipients.is_empty() {
+                recipients.push(Default::default());
+            }
+
+            for recipient in recipients {
+                write_row(
+                    &mut csv,
+                    [
+                        message.id.to_string(),
+                        message.return_path.clone(),
+                        message.env_id.clone().unwrap_or_default(),
+                        format_datetime(Some(message.created)),
+                        message.size.to_string(),
+                        message.priority.to_string(),
+                        domain.name.clone(),
+                        status_name(&domain.status).to_string(),
+                        status_text(&domain.status),
+                        domain.retry_num.to_string(),
+                        format_datetime(domain.next_retry),
+                        format_datetime(domain.next_notify),
+                        format_datetime(Some(domain.expires)),
+                    ]
+                    .into_iter()
+                    .chain(recipient),
+                );
+            }
+        }
+    }
+
+    csv
+}
+
+pub fn write_row(csv: &mut String, fields: impl IntoIterator<Item = String>) {
+    for (pos, field) in fields.into_iter().enumerate() {
+        if pos > 0 {
+            csv.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(&field);
+        }
+    }
+    csv.push_str("\r\n");
+}
+
+fn status_name(status: &Status) -> &'static str {
+    match status {
+        Status::Scheduled => "scheduled",
+        Status::Completed(_) => "completed",
+        Status::TemporaryFailure(_) => "temp_fail",
+        Status::PermanentFailure(_) => "perm_fail",
+    }
+}
+
+fn status_text(status: &Status) -> String {
+    match status {
+        Status::Scheduled => String::new(),
+        status => status.clone().unwrap_message(),
+    }
+}
+
+fn format_datetime(datetime: Option<DateTime<Utc>>) -> String {
+    datetime
+        .map(|datetime| datetime.to_rfc3339())
+        .unwrap_or_default()
+}
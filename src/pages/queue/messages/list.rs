@@ -29,14 +29,15 @@ use crate::{
     components::{
         badge::Badge,
         icon::{
-            IconAlertTriangle, IconCancel, IconCheckCircle, IconClock, IconLaunch, IconRefresh,
+            IconAlertTriangle, IconArrowDownTray, IconCancel, IconCheckCircle, IconClock,
+            IconLaunch, IconRefresh,
         },
         list::{
             header::ColumnList,
             pagination::Pagination,
-            row::SelectItem,
-            toolbar::{SearchBox, ToolbarButton},
-            Footer, ListItem, ListSection, ListTable, Toolbar, ZeroResults,
+            row::{SelectItem, HIGHLIGHT_ROW_CLASS},
+            toolbar::{FilterInput, FilterSelect, PollingControl, SearchBox, ToolbarButton},
+            Filters, Footer, ListItem, ListSection, ListTable, Toolbar, ZeroResults,
         },
         messages::{
             alert::{use_alerts, Alert},
@@ -46,13 +47,18 @@ use crate::{
         Color,
     },
     core::{
+        file::download_file,
         http::{self, HttpRequest},
         oauth::use_authorization,
-        url::UrlBuilder,
+        polling::{use_polling, ChangeTracker},
     },
     pages::{
         maybe_plural,
-        queue::messages::{Message, Status},
+        queue::messages::{
+            bulk::{run_bulk_action, BulkAction, BulkProgress, BulkStatus},
+            export::ExportFormat,
+            fetch_all_messages, Message, QueueFilter, Status, STATUS_FILTERS,
+        },
         List,
     },
 };
@@ -70,18 +76,7 @@ pub fn QueueList() -> impl IntoView {
             .filter(|&page| page > 0)
             .unwrap_or(1)
     });
-    let filter = create_memo(move |_| {
-        query.with(|q| {
-            q.get("filter").and_then(|s| {
-                let s = s.trim();
-                if !s.is_empty() {
-                    Some(s.to_string())
-                } else {
-                    None
-                }
-            })
-        })
-    });
+    let filter = create_memo(move |_| query.with(QueueFilter::from_query));
 
     let auth = use_authorization();
     let alert = use_alerts();
@@ -95,18 +90,43 @@ pub fn QueueList() -> impl IntoView {
             let auth = auth.get_untracked();
 
             async move {
-                HttpRequest::get("/api/queue/messages")
-                    .with_authorization(&auth)
-                    .with_parameter("page", page.to_string())
-                    .with_parameter("limit", PAGE_SIZE.to_string())
-                    .with_parameter("values", "1")
-                    .with_optional_parameter("text", filter)
-                    .send::<List<Message>>()
-                    .await
+                if filter.status.is_none() {
+                    return filter
+                        .apply(
+                            HttpRequest::get("/api/queue/messages")
+                                .with_authorization(&auth)
+                                .with_parameter("page", page.to_string())
+                                .with_parameter("limit", PAGE_SIZE.to_string())
+                                .with_parameter("values", "1"),
+                        )
+                        .send::<List<Message>>()
+                        .await;
+                }
+
+                // The server can't filter by status, so the matching messages
+                // are collected first and paginated here.
+                let mut matches = Vec::new();
+                fetch_all_messages(&auth, &filter, |messages, _| {
+                    matches.extend(messages);
+                    true
+                })
+                .await?;
+                Ok(List {
+                    total: matches.len() as u64,
+                    items: matches
+                        .into_iter()
+                        .skip(((page - 1) * PAGE_SIZE) as usize)
+                        .take(PAGE_SIZE as usize)
+                        .collect(),
+                })
             }
         },
     );
 
+    let poller = use_polling("queue", move || messages.refetch());
+    poller.watch(messages);
+    let changes = ChangeTracker::<u64, Message>::new();
+
     let cancel_action = create_action(move |items: &HashSet<String>| {
         let items = items.clone();
         let auth = auth.get();
@@ -172,24 +192,121 @@ pub fn QueueList() -> impl IntoView {
         }
     });
 
+    let bulk_progress = create_rw_signal(None::<BulkProgress>);
+    let bulk_stop = create_rw_signal(false);
+    let bulk_action = create_action(move |action: &BulkAction| {
+        let action = *action;
+        let auth = auth.get();
+        let filter = filter.get();
+
+        async move {
+            match run_bulk_action(auth, filter, action, bulk_progress, bulk_stop).await {
+                Ok(_) => {
+                    messages.refetch();
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    bulk_progress.set(None);
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+    let confirm_bulk = move |action: BulkAction| {
+        let (title, message, button) = match action {
+            BulkAction::Retry => (
+                "Retry all matching messages",
+                "Request immediate delivery of every queued message matching the current filter, on all pages?",
+                "Retry all",
+            ),
+            BulkAction::Cancel => (
+                "Cancel all matching messages",
+                "Cancel delivery of every queued message matching the current filter, on all pages? This action cannot be undone.",
+                "Cancel all",
+            ),
+        };
+        let modal_ = Modal::with_title(title)
+            .with_message(message)
+            .with_button(button);
+        modal.set(match action {
+            BulkAction::Retry => modal_.with_callback(move || {
+                bulk_action.dispatch(action);
+            }),
+            BulkAction::Cancel => modal_.with_dangerous_callback(move || {
+                bulk_action.dispatch(action);
+            }),
+        });
+    };
+    let export_progress = create_rw_signal(None::<(ExportFormat, usize)>);
+    let export_action = create_action(move |format: &ExportFormat| {
+        let format = *format;
+        let auth = auth.get();
+        let filter = filter.get();
+
+        async move {
+            export_progress.set(Some((format, 0)));
+            let mut exported = Vec::new();
+            let result = fetch_all_messages(&auth, &filter, |messages, _| {
+                exported.extend(messages);
+                export_progress.set(Some((format, exported.len())));
+                true
+            })
+            .await;
+            export_progress.set(None);
+
+            match result {
+                Ok(_) if !exported.is_empty() => {
+                    if let Err(err) = format.serialize(&exported).and_then(|contents| {
+                        download_file(&format.file_name(), format.content_type(), contents)
+                    }) {
+                        alert.set(Alert::error(format!("Failed to download file: {err}")));
+                    }
+                }
+                Ok(_) => {
+                    alert.set(Alert::warning("There are no queued messages to export"));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+    let export_button_text = move |format: ExportFormat| {
+        Signal::derive(move || match export_progress.get() {
+            Some((exporting, total)) if exporting == format => {
+                format!("Exporting… {total}")
+            }
+            _ => format!("Export {}", format.label()),
+        })
+    };
+
+    let set_filter = move |update: &dyn Fn(&mut QueueFilter)| {
+        let mut filter = filter.get();
+        update(&mut filter);
+        use_navigate()(&filter.url(None), Default::default());
+    };
+
     let total_results = create_rw_signal(None::<u32>);
 
     view! {
         <ListSection>
+            <BulkStatus progress=bulk_progress on_stop=move |_| bulk_stop.set(true)/>
             <ListTable title="Message Queue" subtitle="View, cancel or reschedule queued messages">
                 <Toolbar slot>
                     <SearchBox
-                        value=filter
-                        on_search=move |value| {
-                            use_navigate()(
-                                &UrlBuilder::new("/manage/queue/messages")
-                                    .with_parameter("filter", value)
-                                    .finish(),
-                                Default::default(),
-                            );
+                        value=Signal::derive(move || filter.get().text)
+                        on_search=move |value: String| {
+                            set_filter(&|filter| filter.text = Some(value.clone()));
                         }
                     />
 
+                    <PollingControl poller/>
+
                     <ToolbarButton
                         text="Refresh"
 
@@ -259,6 +376,80 @@ pub fn QueueList() -> impl IntoView {
 
                 </Toolbar>
 
+                <Filters slot>
+                    <FilterInput
+                        placeholder="Sender"
+                        value=Signal::derive(move || filter.get().from)
+                        on_change=move |value: String| {
+                            set_filter(&|filter| filter.from = Some(value.clone()));
+                        }
+                    />
+                    <FilterInput
+                        placeholder="Recipient domain"
+                        value=Signal::derive(move || filter.get().to)
+                        on_change=move |value: String| {
+                            set_filter(&|filter| filter.to = Some(value.clone()));
+                        }
+                    />
+                    <FilterSelect
+                        value=Signal::derive(move || filter.get().status)
+                        options=STATUS_FILTERS
+                        on_change=move |value: String| {
+                            set_filter(&|filter| filter.status = Some(value.clone()));
+                        }
+                    />
+                    <div class="inline-flex gap-x-2 ms-auto">
+                        <ToolbarButton
+                            text="Retry all matching"
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                if !bulk_progress.get().map_or(false, |p| p.is_running()) {
+                                    confirm_bulk(BulkAction::Retry);
+                                }
+                            })
+                        >
+
+                            <IconLaunch/>
+                        </ToolbarButton>
+                        <ToolbarButton
+                            text="Cancel all matching"
+                            color=Color::Red
+                            on_click=Callback::new(move |_| {
+                                if !bulk_progress.get().map_or(false, |p| p.is_running()) {
+                                    confirm_bulk(BulkAction::Cancel);
+                                }
+                            })
+                        >
+
+                            <IconCancel/>
+                        </ToolbarButton>
+                        <ToolbarButton
+                            text=export_button_text(ExportFormat::Csv)
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                if export_progress.get().is_none() {
+                                    export_action.dispatch(ExportFormat::Csv);
+                                }
+                            })
+                        >
+
+                            <IconArrowDownTray/>
+                        </ToolbarButton>
+                        <ToolbarButton
+                            text=export_button_text(ExportFormat::Json)
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                if export_progress.get().is_none() {
+                                    export_action.dispatch(ExportFormat::Json);
+                                }
+                            })
+                        >
+
+                            <IconArrowDownTray/>
+                        </ToolbarButton>
+                    </div>
+                </Filters>
+
                 <Transition fallback=Skeleton>
                     {move || match messages.get() {
                         None => None,
@@ -273,6 +464,11 @@ pub fn QueueList() -> impl IntoView {
                         }
                         Some(Ok(messages)) if !messages.items.is_empty() => {
                             total_results.set(Some(messages.total as u32));
+                            changes
+                                .update(
+                                    (page.get_untracked(), filter.get_untracked()),
+                                    messages.items.iter().map(|m| (m.id, m.clone())),
+                                );
                             let messages_ = messages.clone();
                             Some(
                                 view! {
@@ -299,7 +495,10 @@ pub fn QueueList() -> impl IntoView {
                                             key=|message| message.id
                                             let:message
                                         >
-                                            <QueueItem message/>
+                                            <QueueItem
+                                                highlight=changes.is_changed(&message.id)
+                                                message
+                                            />
                                         </For>
 
                                     </ColumnList>
@@ -330,13 +529,7 @@ pub fn QueueList() -> impl IntoView {
                         total_results=total_results.read_only()
                         page_size=PAGE_SIZE
                         on_page_change=move |page: u32| {
-                            use_navigate()(
-                                &UrlBuilder::new("/manage/queue/messages")
-                                    .with_parameter("page", page.to_string())
-                                    .with_optional_parameter("filter", filter())
-                                    .finish(),
-                                Default::default(),
-                            );
+                            use_navigate()(&filter.get().url(Some(page)), Default::default());
                         }
                     />
 
@@ -347,7 +540,7 @@ pub fn QueueList() -> impl IntoView {
 }
 
 #[component]
-fn QueueItem(message: Message) -> impl IntoView {
+fn QueueItem(message: Message, #[prop(optional)] highlight: bool) -> impl IntoView {
     let mut total_success = 0;
     let mut total_pending = 0;
     let mut total_failed = 0;
@@ -382,7 +575,7 @@ fn QueueItem(message: Message) -> impl IntoView {
     );
 
     view! {
-        <tr>
+        <tr class=highlight.then_some(HIGHLIGHT_ROW_CLASS)>
             <ListItem>
                 <label class="flex">
                     <SelectItem item_id=message.id.to_string()/>
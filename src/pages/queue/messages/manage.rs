@@ -53,7 +53,10 @@ use crate::{
     },
     pages::{
         maybe_plural,
-        queue::messages::{Message, Status},
+        queue::messages::{
+            related::RelatedLogs, schedule::DeliverySchedule, timeline::DeliveryTimeline, Message,
+            Status,
+        },
         FormatDateTime,
     },
 };
@@ -161,6 +164,9 @@ pub fn QueueManage() -> impl IntoView {
                     let next_retry = message.next_retry();
                     let next_dsn = message.next_dsn();
                     let expires = message.expires();
+                    let schedule = message.clone();
+                    let timeline = message.clone();
+                    let related = message.clone();
                     let recipients = message
                         .clone()
                         .domains
@@ -276,6 +282,13 @@ pub fn QueueManage() -> impl IntoView {
 
                             </Card>
 
+                            <div class="max-w-[85rem] px-4 pt-8 sm:px-6 lg:px-8 lg:pt-10 mx-auto">
+                                <DeliverySchedule
+                                    message=schedule
+                                    on_change=move |_| fetch_message.refetch()
+                                />
+                            </div>
+
                             <div class="max-w-[85rem] px-4 py-8 sm:px-6 lg:px-8 lg:py-10 mx-auto">
                                 <ListTable title="Recipients" subtitle="Retry or cancel delivery">
                                     <Toolbar slot>
@@ -477,6 +490,11 @@ pub fn QueueManage() -> impl IntoView {
 
                                 </ListTable>
                             </div>
+
+                            <div class="max-w-[85rem] px-4 pb-8 sm:px-6 lg:px-8 lg:pb-10 mx-auto grid gap-6 lg:grid-cols-2">
+                                <DeliveryTimeline message=timeline/>
+                                <RelatedLogs message=related/>
+                            </div>
                         }
                             .into_view(),
                     )
@@ -21,12 +21,29 @@
  * for more details.
 */
 
+pub mod bulk;
+pub mod export;
 pub mod list;
 pub mod manage;
+pub mod related;
+pub mod schedule;
+pub mod timeline;
 
 use chrono::{DateTime, Utc};
+use leptos_router::ParamsMap;
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::{
+    core::{
+        http::{self, HttpRequest},
+        oauth::AuthToken,
+        url::UrlBuilder,
+    },
+    pages::List,
+};
+
+const FETCH_PAGE_SIZE: u32 = 100;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Message {
     pub id: u64,
@@ -145,6 +162,112 @@ impl Message {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QueueFilter {
+    pub text: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub status: Option<String>,
+}
+
+pub const STATUS_FILTERS: &[(&str, &str)] = &[
+    ("", "Any status"),
+    ("scheduled", "Scheduled"),
+    ("temp_fail", "Temporary failure"),
+    ("perm_fail", "Permanent failure"),
+];
+
+impl QueueFilter {
+    pub fn from_query(query: &ParamsMap) -> Self {
+        let param = |name: &str| {
+            query
+                .get(name)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        QueueFilter {
+            text: param("filter"),
+            from: param("from"),
+            to: param("to"),
+            status: param("status").filter(|status| {
+                STATUS_FILTERS
+                    .iter()
+                    .any(|(id, _)| !id.is_empty() && id == status)
+            }),
+        }
+    }
+
+    pub fn apply(&self, request: HttpRequest) -> HttpRequest {
+        request
+            .with_optional_parameter("text", self.text.as_ref())
+            .with_optional_parameter("from", self.from.as_ref())
+            .with_optional_parameter("to", self.to.as_ref())
+    }
+
+    // Status is not supported by the server
+    pub fn matches(&self, message: &Message) -> bool {
+        self.status.as_deref().map_or(true, |status| {
+            message.domains.iter().any(|domain| {
+                matches!(
+                    (status, &domain.status),
+                    ("scheduled", Status::Scheduled)
+                        | ("temp_fail", Status::TemporaryFailure(_))
+                        | ("perm_fail", Status::PermanentFailure(_))
+                )
+            })
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.from.is_none() && self.to.is_none() && self.status.is_none()
+    }
+
+    pub fn url(&self, page: Option<u32>) -> String {
+        UrlBuilder::new("/manage/queue/messages")
+            .with_optional_parameter("page", page.map(|page| page.to_string()))
+            .with_optional_parameter("filter", self.text.as_ref())
+            .with_optional_parameter("from", self.from.as_ref())
+            .with_optional_parameter("to", self.to.as_ref())
+            .with_optional_parameter("status", self.status.as_ref())
+            .finish()
+    }
+}
+
+// The callback receives each page and the server total, and returns whether to go on
+pub async fn fetch_all_messages(
+    auth: &AuthToken,
+    filter: &QueueFilter,
+    mut on_page: impl FnMut(Vec<Message>, u64) -> bool,
+) -> http::Result<()> {
+    let mut page = 1;
+    loop {
+        let messages = filter
+            .apply(
+                HttpRequest::get("/api/queue/messages")
+                    .with_authorization(auth)
+                    .with_parameter("page", page.to_string())
+                    .with_parameter("limit", FETCH_PAGE_SIZE.to_string())
+                    .with_parameter("values", "1"),
+            )
+            .send::<List<Message>>()
+            .await?;
+        let is_last = messages.items.len() < FETCH_PAGE_SIZE as usize
+            || (page * FETCH_PAGE_SIZE) as u64 >= messages.total;
+        let items = messages
+            .items
+            .into_iter()
+            .filter(|message| filter.matches(message))
+            .collect();
+
+        if !on_page(items, messages.total) || is_last {
+            return Ok(());
+        }
+        page += 1;
+    }
+}
+
 impl Status {
     pub fn unwrap_message(self) -> String {
         match self {
@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use ahash::AHashMap;
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{badge::Badge, messages::alert::Alert, skeleton::Skeleton, Color},
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+    },
+    pages::{
+        manage::logs::{LogEntry, LogFilter},
+        maybe_plural,
+        queue::messages::Message,
+        FormatDateTime, List,
+    },
+};
+
+const RELATED_LOGS_LIMIT: u32 = 50;
+const MAX_RECIPIENT_TERMS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MatchKind {
+    QueueId,
+    Sender,
+    Recipient,
+}
+
+#[derive(Debug, Clone)]
+struct RelatedEntry {
+    entry: LogEntry,
+    matches: Vec<MatchKind>,
+}
+
+impl MatchKind {
+    fn label(&self) -> &'static str {
+        match self {
+            MatchKind::QueueId => "Queue id",
+            MatchKind::Sender => "Sender",
+            MatchKind::Recipient => "Recipient",
+        }
+    }
+}
+
+// One request is sent per term, so only the first recipients are searched
+fn search_terms(message: &Message) -> Vec<(MatchKind, String)> {
+    let mut terms = vec![(MatchKind::QueueId, message.id.to_string())];
+    if !message.return_path.is_empty() {
+        terms.push((MatchKind::Sender, message.return_path.clone()));
+    }
+    let mut recipients = 0;
+    for rcpt in message
+        .domains
+        .iter()
+        .flat_map(|domain| domain.recipients.iter())
+    {
+        if !terms.iter().any(|(_, term)| term == &rcpt.address) {
+            terms.push((MatchKind::Recipient, rcpt.address.clone()));
+            recipients += 1;
+            if recipients == MAX_RECIPIENT_TERMS {
+                break;
+            }
+        }
+    }
+    terms
+}
+
+#[component]
+pub fn RelatedLogs(message: Message) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = expect_context::<RwSignal<Alert>>();
+    let terms = search_terms(&message);
+    let queue_id = message.id.to_string();
+    let total_recipients = message
+        .domains
+        .iter()
+        .map(|domain| domain.recipients.len())
+        .sum::<usize>();
+
+    let related = create_local_resource(
+        move || terms.clone(),
+        move |terms| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let mut entries: AHashMap<String, RelatedEntry> = AHashMap::new();
+                for (kind, term) in terms {
+                    let logs = HttpRequest::get("/api/logs")
+                        .with_authorization(&auth)
+                        .with_parameter("page", "1")
+                        .with_parameter("limit", RELATED_LOGS_LIMIT.to_string())
+                        .with_parameter("filter", term)
+                        .send::<List<LogEntry>>()
+                        .await?;
+                    for entry in logs.items {
+                        let related = entries.entry(entry.id()).or_insert_with(|| RelatedEntry {
+                            entry,
+                            matches: vec![],
+                        });
+                        if !related.matches.contains(&kind) {
+                            related.matches.push(kind);
+                        }
+                    }
+                }
+
+                let mut entries = entries.into_values().collect::<Vec<_>>();
+                entries.sort_by_key(|related| related.entry.timestamp);
+                Ok(entries)
+            }
+        },
+    );
+
+    let logs_url = LogFilter {
+        text: Some(queue_id),
+        ..Default::default()
+    }
+    .url(None);
+
+    view! {
+        <div class="bg-white border border-gray-200 rounded-xl shadow-sm p-4 sm:p-7 dark:bg-slate-900 dark:border-gray-700">
+            <div class="mb-6 flex justify-between items-start gap-x-3">
+                <div>
+                    <h2 class="text-xl font-semibold text-gray-800 dark:text-gray-200">
+                        Related Logs
+                    </h2>
+                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                        {if total_recipients > MAX_RECIPIENT_TERMS {
+                            format!(
+                                "Log entries mentioning the queue id, sender or the first {MAX_RECIPIENT_TERMS} of {total_recipients} recipients",
+                            )
+                        } else {
+                            "Log entries mentioning the queue id, sender or recipients".to_string()
+                        }}
+
+                    </p>
+                </div>
+                <a
+                    class="inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                    href=logs_url
+                >
+                    Open in logs
+                </a>
+            </div>
+            <Transition fallback=Skeleton>
+                {move || match related.get() {
+                    None => None,
+                    Some(Err(http::Error::Unauthorized)) => {
+                        use_navigate()("/login", Default::default());
+                        Some(view! { <div></div> }.into_view())
+                    }
+                    Some(Err(err)) => {
+                        alert.set(Alert::from(err));
+                        Some(view! { <Skeleton/> }.into_view())
+                    }
+                    Some(Ok(entries)) if !entries.is_empty() => {
+                        let total = maybe_plural(entries.len(), "entry", "entries");
+                        Some(
+                            view! {
+                                <p class="mb-3 text-xs text-gray-500 dark:text-gray-400">
+                                    {format!("{total}, oldest first")}
+                                </p>
+                                <div class="space-y-3 max-h-[40rem] overflow-y-auto">
+
+                                    {entries
+                                        .into_iter()
+                                        .map(|related| view! { <RelatedLogItem related/> })
+                                        .collect_view()}
+
+                                </div>
+                            }
+                                .into_view(),
+                        )
+                    }
+                    Some(Ok(_)) => {
+                        Some(
+                            view! {
+                                <p class="text-sm text-gray-500 dark:text-gray-400">
+                                    No log entries were found for this message.
+                                </p>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+        </div>
+    }
+}
+
+#[component]
+fn RelatedLogItem(related: RelatedEntry) -> impl IntoView {
+    let entry = related.entry;
+    let color = match entry.level.as_str() {
+        "ERROR" => Color::Red,
+        "WARN" => Color::Yellow,
+        "INFO" => Color::Green,
+        "DEBUG" => Color::Blue,
+        _ => Color::Gray,
+    };
+    let matches = related
+        .matches
+        .into_iter()
+        .map(|kind| view! { <Badge color=Color::Gray>{kind.label()}</Badge> })
+        .collect_view();
+
+    view! {
+        <div class="pb-3 border-b border-gray-200 last:border-b-0 dark:border-gray-700">
+            <div class="flex flex-wrap items-center gap-2">
+                <span class="text-xs text-gray-500 dark:text-gray-400">
+                    {entry.timestamp.format_date_time()}
+                </span>
+                <Badge color=color>{entry.level}</Badge>
+                {matches}
+            </div>
+            <p class="mt-1 text-sm text-gray-600 dark:text-gray-400 break-all">{entry.message}</p>
+        </div>
+    }
+}
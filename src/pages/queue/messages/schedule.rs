@@ -0,0 +1,262 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_humanize::HumanTime;
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        list::{
+            header::ColumnList, toolbar::ToolbarButton, Footer, ListItem, ListTable, ListTextItem,
+            Toolbar,
+        },
+        messages::alert::Alert,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+    },
+    pages::{
+        queue::messages::{Domain, Message, Status},
+        FormatDateTime,
+    },
+};
+
+const INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Debug, Clone)]
+enum ScheduleChange {
+    Retry { domain: String, at: DateTime<Utc> },
+    Priority(i16),
+}
+
+impl Domain {
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, Status::Scheduled | Status::TemporaryFailure(_))
+    }
+
+    pub fn validate_retry(&self, at: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), String> {
+        if !self.is_pending() {
+            Err(format!("Delivery to {} is no longer pending.", self.name))
+        } else if at < now {
+            Err("The delivery time must be in the future.".to_string())
+        } else if at >= self.expires {
+            Err(format!(
+                "Delivery to {} expires on {}, pick an earlier time.",
+                self.name,
+                self.expires.format_date_time()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Message {
+    // Priority only affects deliveries that can still take place
+    pub fn validate_priority(&self, now: DateTime<Utc>) -> Result<(), String> {
+        match self.expires() {
+            Some(expires) if expires > now => Ok(()),
+            Some(_) => Err("The message has already expired.".to_string()),
+            None => Err("The message has no pending deliveries.".to_string()),
+        }
+    }
+}
+
+// `datetime-local` inputs are in local time
+pub fn parse_local_datetime(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, INPUT_FORMAT)
+        .ok()
+        .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+        .map(|dt| dt.to_utc())
+}
+
+pub fn format_local_datetime(dt: DateTime<Utc>) -> String {
+    dt.with_timezone(&Local).format(INPUT_FORMAT).to_string()
+}
+
+#[component]
+pub fn DeliverySchedule(
+    message: Message,
+    #[prop(into)] on_change: Callback<(), ()>,
+) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = expect_context::<RwSignal<Alert>>();
+    let priority = create_rw_signal(message.priority.to_string());
+    let message_id = message.id;
+
+    let update_action = create_action(move |change: &ScheduleChange| {
+        let change = change.clone();
+        let auth = auth.get();
+
+        async move {
+            let request = HttpRequest::patch(format!("/api/queue/messages/{message_id}"))
+                .with_authorization(&auth);
+            let (request, success) = match &change {
+                ScheduleChange::Retry { domain, at } => (
+                    request
+                        .with_parameter("filter", domain)
+                        .with_parameter("at", at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    format!(
+                        "Delivery to {domain} rescheduled for {}.",
+                        at.format_date_time()
+                    ),
+                ),
+                ScheduleChange::Priority(priority) => (
+                    request.with_parameter("priority", priority.to_string()),
+                    format!("Priority changed to {priority}."),
+                ),
+            };
+
+            match request.send::<bool>().await {
+                Ok(true) => {
+                    alert.set(Alert::success(success));
+                    on_change.call(());
+                }
+                Ok(false) | Err(http::Error::NotFound) => {
+                    alert.set(Alert::warning("The message is no longer in the queue."));
+                    on_change.call(());
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let message_ = message.clone();
+    let set_priority = Callback::new(move |_| {
+        let result = priority
+            .get()
+            .trim()
+            .parse::<i16>()
+            .map_err(|_| "Priority must be a number between -32768 and 32767.".to_string())
+            .and_then(|priority| message_.validate_priority(Utc::now()).map(|_| priority));
+        match result {
+            Ok(priority) => update_action.dispatch(ScheduleChange::Priority(priority)),
+            Err(err) => alert.set(Alert::error(err)),
+        }
+    });
+
+    let rows = message
+        .domains
+        .into_iter()
+        .map(|domain| {
+            let is_pending = domain.is_pending();
+            let retry_at = create_rw_signal(
+                domain
+                    .next_retry
+                    .map(format_local_datetime)
+                    .unwrap_or_default(),
+            );
+            let max_value = format_local_datetime(domain.expires);
+            let min_value = format_local_datetime(Utc::now());
+            let next_retry = domain
+                .next_retry
+                .filter(|_| is_pending)
+                .map(|dt| format!("{} ({})", HumanTime::from(dt), dt.format_date_time()))
+                .unwrap_or_default();
+            let expires = format!(
+                "{} ({})",
+                HumanTime::from(domain.expires),
+                domain.expires.format_date_time()
+            );
+            let name = domain.name.clone();
+            let reschedule = move |_| {
+                let result = parse_local_datetime(&retry_at.get())
+                    .ok_or_else(|| "Please enter a valid date and time.".to_string())
+                    .and_then(|at| domain.validate_retry(at, Utc::now()).map(|_| at));
+                match result {
+                    Ok(at) => update_action.dispatch(ScheduleChange::Retry {
+                        domain: domain.name.clone(),
+                        at,
+                    }),
+                    Err(err) => alert.set(Alert::error(err)),
+                }
+            };
+
+            view! {
+                <tr>
+                    <ListItem>
+                        <span class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                            {name}
+                        </span>
+                    </ListItem>
+                    <ListTextItem>{next_retry}</ListTextItem>
+                    <ListTextItem>{expires}</ListTextItem>
+                    <ListItem subclass="px-6 py-1.5 flex items-center gap-x-3">
+                        <input
+                            type="datetime-local"
+                            class="py-1.5 px-2 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                            min=min_value
+                            max=max_value
+                            disabled=!is_pending
+                            prop:value=move || retry_at.get()
+                            on:change=move |ev| retry_at.set(event_target_value(&ev))
+                        />
+                        <button
+                            type="button"
+                            class="text-sm text-blue-600 decoration-2 hover:underline font-medium disabled:opacity-50 disabled:pointer-events-none"
+                            disabled=!is_pending
+                            on:click=reschedule
+                        >
+                            Reschedule
+                        </button>
+                    </ListItem>
+                </tr>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <ListTable title="Delivery Schedule" subtitle="Hold or bring forward delivery to each domain">
+            <Toolbar slot>
+                <input
+                    type="number"
+                    class="py-2 px-3 block w-24 border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                    min=i16::MIN
+                    max=i16::MAX
+                    title="Messages with a higher priority are delivered first"
+                    prop:value=move || priority.get()
+                    on:change=move |ev| priority.set(event_target_value(&ev))
+                />
+                <ToolbarButton text="Set priority" color=Color::Gray on_click=set_priority/>
+            </Toolbar>
+            <ColumnList headers=vec![
+                "Domain".to_string(),
+                "Next Retry".to_string(),
+                "Expires".to_string(),
+                "Deliver At".to_string(),
+            ]>{rows}</ColumnList>
+            <Footer slot>
+                <div></div>
+            </Footer>
+        </ListTable>
+    }
+}
@@ -0,0 +1,243 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use humansize::{format_size, DECIMAL};
+use leptos::*;
+
+use crate::pages::{
+    maybe_plural,
+    queue::messages::{Domain, Message, Status},
+    FormatDateTime,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventKind {
+    Received,
+    Delivered,
+    Deferred,
+    Failed,
+    Now,
+    Attempt,
+    Notify,
+    Expiry,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimelineEvent {
+    kind: EventKind,
+    time: Option<DateTime<Utc>>,
+    title: String,
+    details: Vec<String>,
+}
+
+// The server does not keep the time of past attempts, so they are listed before now
+fn build_timeline(message: &Message, now: DateTime<Utc>) -> Vec<TimelineEvent> {
+    let mut events = vec![TimelineEvent {
+        kind: EventKind::Received,
+        time: Some(message.created),
+        title: "Message received".to_string(),
+        details: vec![format!(
+            "From {} to {} ({})",
+            message.return_path(),
+            maybe_plural(message.domains.len(), "domain", "domains"),
+            format_size(message.size, DECIMAL)
+        )],
+    }];
+    let mut scheduled = Vec::new();
+
+    for domain in &message.domains {
+        if let Some(event) = past_attempts(domain) {
+            events.push(event);
+        }
+
+        if matches!(
+            domain.status,
+            Status::Scheduled | Status::TemporaryFailure(_)
+        ) {
+            if let Some(next_retry) = domain.next_retry {
+                scheduled.push(TimelineEvent {
+                    kind: EventKind::Attempt,
+                    time: Some(next_retry),
+                    title: format!(
+                        "Delivery attempt #{} to {}",
+                        domain.retry_num + 1,
+                        domain.name
+                    ),
+                    details: vec![],
+                });
+            }
+            if let Some(next_notify) = domain.next_notify.filter(|dt| *dt < domain.expires) {
+                scheduled.push(TimelineEvent {
+                    kind: EventKind::Notify,
+                    time: Some(next_notify),
+                    title: format!("Delay notification for {}", domain.name),
+                    details: vec![
+                        "The sender is told that delivery is delayed but still being attempted."
+                            .to_string(),
+                    ],
+                });
+            }
+            scheduled.push(TimelineEvent {
+                kind: EventKind::Expiry,
+                time: Some(domain.expires),
+                title: format!("Delivery to {} expires", domain.name),
+                details: vec![
+                    "Attempts stop and the sender receives a failure notification.".to_string(),
+                ],
+            });
+        }
+    }
+
+    scheduled.sort_by_key(|event| event.time);
+    let overdue = scheduled.partition_point(|event| event.time.map_or(false, |t| t <= now));
+    let upcoming = scheduled.split_off(overdue);
+    events.extend(scheduled);
+    events.push(TimelineEvent {
+        kind: EventKind::Now,
+        time: Some(now),
+        title: "Now".to_string(),
+        details: vec![],
+    });
+    events.extend(upcoming);
+
+    events
+}
+
+fn past_attempts(domain: &Domain) -> Option<TimelineEvent> {
+    let (kind, title) = match &domain.status {
+        Status::Completed(_) => (
+            EventKind::Delivered,
+            format!("Delivered to {}", domain.name),
+        ),
+        Status::PermanentFailure(_) => (
+            EventKind::Failed,
+            format!("Delivery to {} failed permanently", domain.name),
+        ),
+        Status::TemporaryFailure(_) => (
+            EventKind::Deferred,
+            format!("Delivery to {} deferred", domain.name),
+        ),
+        Status::Scheduled if domain.retry_num > 0 => (
+            EventKind::Deferred,
+            format!("Delivery to {} deferred", domain.name),
+        ),
+        Status::Scheduled => return None,
+    };
+
+    let mut details = Vec::new();
+    if domain.retry_num > 0 {
+        details.push(format!(
+            "{} so far.",
+            maybe_plural(domain.retry_num as usize, "attempt", "attempts")
+        ));
+    }
+    for rcpt in &domain.recipients {
+        let status = match &rcpt.status {
+            Status::Scheduled => &domain.status,
+            status => status,
+        };
+        if !matches!(status, Status::Scheduled) {
+            details.push(format!(
+                "{}: {}",
+                rcpt.address,
+                status.clone().unwrap_message()
+            ));
+        }
+    }
+
+    Some(TimelineEvent {
+        kind,
+        time: None,
+        title,
+        details,
+    })
+}
+
+#[component]
+pub fn DeliveryTimeline(message: Message) -> impl IntoView {
+    let events = build_timeline(&message, Utc::now())
+        .into_iter()
+        .map(|event| {
+            let dot_class = match event.kind {
+                EventKind::Received | EventKind::Attempt => "size-2 rounded-full bg-blue-600",
+                EventKind::Delivered => "size-2 rounded-full bg-teal-500",
+                EventKind::Deferred | EventKind::Notify => "size-2 rounded-full bg-yellow-500",
+                EventKind::Failed | EventKind::Expiry => "size-2 rounded-full bg-red-500",
+                EventKind::Now => "size-3 rounded-full border-2 border-blue-600 bg-white",
+            };
+            let (date, relative) = match event.time {
+                Some(time) if event.kind != EventKind::Now => {
+                    (time.format_date_time(), HumanTime::from(time).to_string())
+                }
+                Some(_) => (String::new(), String::new()),
+                None => (String::new(), "Past attempts".to_string()),
+            };
+            let title_class = if event.kind == EventKind::Now {
+                "text-xs font-semibold uppercase text-blue-600 dark:text-blue-500"
+            } else {
+                "font-semibold text-sm text-gray-800 dark:text-gray-200"
+            };
+            let details = event
+                .details
+                .into_iter()
+                .map(|detail| {
+                    view! { <p class="mt-1 text-sm text-gray-600 dark:text-gray-400 break-all">{detail}</p> }
+                })
+                .collect_view();
+
+            view! {
+                <div class="group flex gap-x-3">
+                    <div class="w-44 flex-shrink-0 text-end">
+                        <span class="block text-xs text-gray-500 dark:text-gray-400">{relative}</span>
+                        <span class="block text-xs text-gray-400 dark:text-gray-500">{date}</span>
+                    </div>
+                    <div class="relative group-last:after:hidden after:absolute after:top-7 after:bottom-0 after:start-3.5 after:w-px after:-translate-x-[0.5px] after:bg-gray-200 dark:after:bg-gray-700">
+                        <div class="relative z-10 size-7 flex justify-center items-center">
+                            <div class=dot_class></div>
+                        </div>
+                    </div>
+                    <div class="grow pt-0.5 pb-8">
+                        <h3 class=title_class>{event.title}</h3>
+                        {details}
+                    </div>
+                </div>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <div class="bg-white border border-gray-200 rounded-xl shadow-sm p-4 sm:p-7 dark:bg-slate-900 dark:border-gray-700">
+            <div class="mb-6">
+                <h2 class="text-xl font-semibold text-gray-800 dark:text-gray-200">
+                    Delivery Timeline
+                </h2>
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+                    What has happened to this message so far and what is scheduled next
+                </p>
+            </div>
+            <div>{events}</div>
+        </div>
+    }
+}
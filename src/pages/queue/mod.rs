@@ -23,3 +23,4 @@
 
 pub mod messages;
 pub mod reports;
+pub mod stats;
@@ -50,6 +50,7 @@ pub fn ArfReportDisplay(
     received: DateTime<Utc>,
     extra: Vec<(String, String)>,
     back_url: String,
+    #[prop(optional)] children: Option<Children>,
 ) -> impl IntoView {
     let received_date = received.format_date();
     let received_time = received.format_time();
@@ -194,6 +195,7 @@ pub fn ArfReportDisplay(
                 {extra}
             </ReportSection>
             {auth_failure}
+            {children.map(|children| children())}
 
             <div class="flex justify-end">
 
@@ -32,8 +32,8 @@ use crate::{
         list::{
             header::ColumnList,
             pagination::Pagination,
-            row::SelectItem,
-            toolbar::{SearchBox, ToolbarButton},
+            row::{SelectItem, HIGHLIGHT_ROW_CLASS},
+            toolbar::{PollingControl, SearchBox, ToolbarButton},
             Footer, ListItem, ListSection, ListTable, ListTextItem, Toolbar, ZeroResults,
         },
         messages::{
@@ -46,6 +46,7 @@ use crate::{
     core::{
         http::{self, HttpRequest},
         oauth::use_authorization,
+        polling::{use_polling, ChangeTracker},
         url::UrlBuilder,
     },
     pages::{
@@ -151,6 +152,10 @@ pub fn ReportList() -> impl IntoView {
         }
     });
 
+    let poller = use_polling("queue-reports", move || reports.refetch());
+    poller.watch(reports);
+    let changes = ChangeTracker::<String, ()>::new();
+
     let total_results = create_rw_signal(None::<u32>);
 
     view! {
@@ -172,6 +177,8 @@ pub fn ReportList() -> impl IntoView {
                         }
                     />
 
+                    <PollingControl poller/>
+
                     <ToolbarButton
                         text="Refresh"
 
@@ -234,6 +241,11 @@ pub fn ReportList() -> impl IntoView {
                         }
                         Some(Ok(reports)) if !reports.items.is_empty() => {
                             total_results.set(Some(reports.total as u32));
+                            changes
+                                .update(
+                                    (page.get_untracked(), filter.get_untracked()),
+                                    reports.items.iter().map(|report| (report.id.clone(), ())),
+                                );
                             let reports_ = reports.clone();
                             Some(
                                 view! {
@@ -260,7 +272,10 @@ pub fn ReportList() -> impl IntoView {
                                             key=|report| report.id.clone()
                                             let:report
                                         >
-                                            <ReportItem report/>
+                                            <ReportItem
+                                                highlight=changes.is_changed(&report.id)
+                                                report
+                                            />
                                         </For>
 
                                     </ColumnList>
@@ -308,11 +323,11 @@ pub fn ReportList() -> impl IntoView {
 }
 
 #[component]
-fn ReportItem(report: AggregateReportId) -> impl IntoView {
+fn ReportItem(report: AggregateReportId, #[prop(optional)] highlight: bool) -> impl IntoView {
     let show_url = format!("/manage/queue/report/{}", report.id);
 
     view! {
-        <tr>
+        <tr class=highlight.then_some(HIGHLIGHT_ROW_CLASS)>
             <ListItem>
                 <label class="flex">
                     <SelectItem item_id=report.id.to_string()/>
@@ -183,8 +183,8 @@ pub enum PolicyOverride {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct PolicyOverrideReason {
-    type_: PolicyOverride,
-    comment: Option<String>,
+    pub type_: PolicyOverride,
+    pub comment: Option<String>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -210,9 +210,9 @@ pub struct Extension {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Identifier {
-    envelope_to: Option<String>,
-    envelope_from: String,
-    header_from: String,
+    pub envelope_to: Option<String>,
+    pub envelope_from: String,
+    pub header_from: String,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -229,10 +229,10 @@ pub enum DkimResult {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct DKIMAuthResult {
-    domain: String,
-    selector: String,
-    result: DkimResult,
-    human_result: Option<String>,
+    pub domain: String,
+    pub selector: String,
+    pub result: DkimResult,
+    pub human_result: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -257,16 +257,16 @@ pub enum SpfResult {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct SPFAuthResult {
-    domain: String,
-    scope: SPFDomainScope,
-    result: SpfResult,
-    human_result: Option<String>,
+    pub domain: String,
+    pub scope: SPFDomainScope,
+    pub result: SpfResult,
+    pub human_result: Option<String>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct AuthResult {
-    dkim: Vec<DKIMAuthResult>,
-    spf: Vec<SPFAuthResult>,
+    pub dkim: Vec<DKIMAuthResult>,
+    pub spf: Vec<SPFAuthResult>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -297,6 +297,7 @@ pub struct URI {
 pub struct TlsReport {
     #[serde(rename = "organization-name")]
     #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub organization_name: Option<String>,
 
     #[serde(rename = "date-range")]
@@ -304,6 +305,7 @@ pub struct TlsReport {
 
     #[serde(rename = "contact-info")]
     #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub contact_info: Option<String>,
 
     #[serde(rename = "report-id")]
@@ -363,15 +365,19 @@ pub struct FailureDetails {
     pub result_type: ResultType,
 
     #[serde(rename = "sending-mta-ip")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub sending_mta_ip: Option<IpAddr>,
 
     #[serde(rename = "receiving-mx-hostname")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub receiving_mx_hostname: Option<String>,
 
     #[serde(rename = "receiving-mx-helo")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub receiving_mx_helo: Option<String>,
 
     #[serde(rename = "receiving-ip")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub receiving_ip: Option<IpAddr>,
 
     #[serde(rename = "failed-session-count")]
@@ -379,9 +385,11 @@ pub struct FailureDetails {
     pub failed_session_count: u32,
 
     #[serde(rename = "additional-information")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub additional_information: Option<String>,
 
     #[serde(rename = "failure-reason-code")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub failure_reason_code: Option<String>,
 }
 
@@ -665,6 +673,19 @@ impl Display for ResultType {
     }
 }
 
+impl FeedbackType {
+    pub fn id(&self) -> &'static str {
+        match self {
+            FeedbackType::Abuse => "abuse",
+            FeedbackType::AuthFailure => "auth-failure",
+            FeedbackType::Fraud => "fraud",
+            FeedbackType::NotSpam => "not-spam",
+            FeedbackType::Other => "other",
+            FeedbackType::Virus => "virus",
+        }
+    }
+}
+
 impl Display for FeedbackType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,446 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::BTreeMap;
+
+use ahash::AHashMap;
+use chrono::{DateTime, Duration, Utc};
+use chrono_humanize::HumanTime;
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        card::{Card, CardItem},
+        form::button::Button,
+        icon::{IconAlertTriangle, IconCancel, IconClock, IconEnvelope, IconRefresh},
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        report::{ReportView, StatsSection},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{http, oauth::use_authorization},
+    pages::{
+        maybe_plural,
+        queue::messages::{fetch_all_messages, Message, QueueFilter, Status},
+        FormatDateTime,
+    },
+};
+
+const AGE_BUCKETS: &[(i64, &str)] = &[
+    (1, "Less than 1 hour"),
+    (6, "1 to 6 hours"),
+    (24, "6 to 24 hours"),
+    (72, "1 to 3 days"),
+    (168, "3 to 7 days"),
+    (i64::MAX, "More than 7 days"),
+];
+const TOP_DOMAINS: usize = 10;
+const EXPIRY_WINDOW_HOURS: i64 = 24;
+const MAX_EXPIRING: usize = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct QueueStats {
+    messages: usize,
+    recipients: usize,
+    age_buckets: Vec<usize>,
+    domains: Vec<DomainStats>,
+    failures: Vec<FailureStats>,
+    expiring: Vec<(DateTime<Utc>, u64, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct DomainStats {
+    name: String,
+    messages: usize,
+    recipients: usize,
+    temp_failures: usize,
+    perm_failures: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FailureStats {
+    code: Option<u16>,
+    temporary: usize,
+    permanent: usize,
+    example: String,
+}
+
+#[component]
+pub fn QueueStatistics() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let loaded = create_rw_signal((0usize, 0u64));
+
+    let stats = create_resource(
+        || (),
+        move |_| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let mut messages = Vec::new();
+                loaded.set((0, 0));
+                fetch_all_messages(&auth, &QueueFilter::default(), |page, total| {
+                    messages.extend(page);
+                    loaded.set((messages.len(), total));
+                    true
+                })
+                .await?;
+
+                Ok(messages)
+            }
+        },
+    );
+
+    view! {
+        <div class="max-w-[85rem] px-4 pt-10 sm:px-6 lg:px-8 lg:pt-14 mx-auto flex items-center justify-between">
+            <div>
+                <h2 class="text-xl font-semibold text-gray-800 dark:text-gray-200">
+                    Queue Statistics
+                </h2>
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+                    {move || {
+                        let (messages, total) = loaded.get();
+                        if (messages as u64) < total {
+                            format!("Loading messages… {messages} of {total}")
+                        } else {
+                            "Summary of the messages currently queued for delivery".to_string()
+                        }
+                    }}
+
+                </p>
+            </div>
+            <Button
+                text="Refresh"
+                color=Color::Gray
+                on_click=move |_| {
+                    stats.refetch();
+                }
+            >
+
+                <IconRefresh/>
+            </Button>
+        </div>
+
+        <Transition fallback=Skeleton>
+            {move || match stats.get() {
+                None => None,
+                Some(Err(http::Error::Unauthorized)) => {
+                    use_navigate()("/login", Default::default());
+                    Some(view! { <div></div> }.into_view())
+                }
+                Some(Err(err)) => {
+                    alert.set(Alert::from(err));
+                    Some(view! { <Skeleton/> }.into_view())
+                }
+                Some(Ok(messages)) => Some(QueueStats::new(&messages, Utc::now()).into_view()),
+            }}
+
+        </Transition>
+    }
+}
+
+impl QueueStats {
+    fn new(messages: &[Message], now: DateTime<Utc>) -> Self {
+        let mut stats = QueueStats {
+            messages: messages.len(),
+            age_buckets: vec![0; AGE_BUCKETS.len()],
+            ..Default::default()
+        };
+        let mut domains: AHashMap<&str, DomainStats> = AHashMap::new();
+        let mut failures: BTreeMap<Option<u16>, FailureStats> = BTreeMap::new();
+        let expires_before = now + Duration::hours(EXPIRY_WINDOW_HOURS);
+
+        for message in messages {
+            let age = (now - message.created).num_hours();
+            if let Some(pos) = AGE_BUCKETS.iter().position(|(max, _)| age < *max) {
+                stats.age_buckets[pos] += 1;
+            }
+
+            for domain in &message.domains {
+                let domain_stats =
+                    domains
+                        .entry(domain.name.as_str())
+                        .or_insert_with(|| DomainStats {
+                            name: domain.name.clone(),
+                            ..Default::default()
+                        });
+                domain_stats.messages += 1;
+
+                for rcpt in &domain.recipients {
+                    stats.recipients += 1;
+                    domain_stats.recipients += 1;
+
+                    // Recipients without a result yet inherit the domain status
+                    let status = match &rcpt.status {
+                        Status::Scheduled => &domain.status,
+                        status => status,
+                    };
+                    let (reason, is_temporary) = match status {
+                        Status::TemporaryFailure(reason) => (reason, true),
+                        Status::PermanentFailure(reason) => (reason, false),
+                        Status::Scheduled | Status::Completed(_) => continue,
+                    };
+                    let code = smtp_code(reason);
+                    let failure = failures.entry(code).or_insert_with(|| FailureStats {
+                        code,
+                        example: reason.clone(),
+                        ..Default::default()
+                    });
+                    if is_temporary {
+                        failure.temporary += 1;
+                        domain_stats.temp_failures += 1;
+                    } else {
+                        failure.permanent += 1;
+                        domain_stats.perm_failures += 1;
+                    }
+                }
+            }
+
+            if let Some(expires) = message.expires().filter(|e| *e <= expires_before) {
+                stats
+                    .expiring
+                    .push((expires, message.id, message.return_path().to_string()));
+            }
+        }
+
+        stats.domains = domains.into_values().collect();
+        stats.domains.sort_unstable_by(|a, b| {
+            b.recipients
+                .cmp(&a.recipients)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        stats.domains.truncate(TOP_DOMAINS);
+        stats.failures = failures.into_values().collect();
+        stats
+            .failures
+            .sort_by_key(|f| std::cmp::Reverse(f.temporary + f.permanent));
+        stats.expiring.sort_unstable();
+        stats.expiring.truncate(MAX_EXPIRING);
+
+        stats
+    }
+
+    fn failures(&self) -> (usize, usize) {
+        self.failures.iter().fold((0, 0), |(temp, perm), failure| {
+            (temp + failure.temporary, perm + failure.permanent)
+        })
+    }
+}
+
+// The server writes "Code: NNN" for remote replies, other statuses may start
+// with the reply code itself.
+fn smtp_code(reason: &str) -> Option<u16> {
+    let reply_code = |text: &str| {
+        let code = text.get(..3)?;
+        (code.starts_with(['4', '5'])
+            && code.bytes().all(|ch| ch.is_ascii_digit())
+            && !text[3..].starts_with(|ch: char| ch.is_ascii_digit()))
+        .then(|| code.parse().ok())
+        .flatten()
+    };
+
+    reply_code(reason.trim_start()).or_else(|| {
+        reason
+            .to_ascii_lowercase()
+            .match_indices("code")
+            .find_map(|(pos, _)| reply_code(reason[pos + 4..].trim_start_matches([':', ' '])))
+    })
+}
+
+impl IntoView for QueueStats {
+    fn into_view(self) -> View {
+        let (temp_failures, perm_failures) = self.failures();
+        let max_bucket = self
+            .age_buckets
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_default()
+            .max(1);
+        let age_rows = AGE_BUCKETS
+            .iter()
+            .zip(self.age_buckets)
+            .map(|((_, label), total)| {
+                let width = format!("width: {}%", total * 100 / max_bucket);
+                view! {
+                    <TableRow>
+                        <span>{*label}</span>
+                        <span>{total}</span>
+                        <div class="flex w-48 h-1.5 bg-gray-200 rounded-full overflow-hidden dark:bg-gray-700">
+                            <div class="bg-blue-600" style=width></div>
+                        </div>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let domain_rows = self
+            .domains
+            .into_iter()
+            .map(|domain| {
+                view! {
+                    <TableRow>
+                        <span>{domain.name}</span>
+                        <span>{domain.messages}</span>
+                        <span>{domain.recipients}</span>
+                        <span>{domain.temp_failures}</span>
+                        <span>{domain.perm_failures}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let failure_rows = self
+            .failures
+            .into_iter()
+            .map(|failure| {
+                let code = failure
+                    .code
+                    .map_or_else(|| "Other".to_string(), |code| code.to_string());
+                view! {
+                    <TableRow>
+                        <span class="font-mono">{code}</span>
+                        <span>{failure.temporary}</span>
+                        <span>{failure.permanent}</span>
+                        <span class="block max-w-md truncate" title=failure.example.clone()>
+                            {failure.example}
+                        </span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let expiring_rows = self
+            .expiring
+            .into_iter()
+            .map(|(expires, id, return_path)| {
+                let url = format!("/manage/queue/message/{id}");
+                view! {
+                    <TableRow>
+                        <a
+                            class="text-blue-600 decoration-2 hover:underline font-medium dark:text-blue-500"
+                            href=url
+                        >
+                            {id}
+                        </a>
+                        <span>{return_path}</span>
+                        <span>{expires.format_date_time()}</span>
+                        <span>{HumanTime::from(expires).to_string()}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+
+        view! {
+            <Card>
+                <CardItem title="Messages" contents=self.messages.to_string()>
+
+                    <IconEnvelope attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Recipients" contents=self.recipients.to_string()>
+
+                    <IconClock attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Temporary Failures" contents=temp_failures.to_string()>
+
+                    <IconAlertTriangle attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Permanent Failures" contents=perm_failures.to_string()>
+
+                    <IconCancel attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+            </Card>
+
+            <ReportView>
+                <StatsSection title="Queue Age">
+                    <Table headers=vec![
+                        "Age".to_string(),
+                        "Messages".to_string(),
+                        "".to_string(),
+                    ]>{age_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Top Destination Domains">
+                    <Table headers=vec![
+                        "Domain".to_string(),
+                        "Messages".to_string(),
+                        "Recipients".to_string(),
+                        "Temporary Failures".to_string(),
+                        "Permanent Failures".to_string(),
+                    ]>{domain_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Failures by SMTP Code">
+                    <Table headers=vec![
+                        "Code".to_string(),
+                        "Temporary".to_string(),
+                        "Permanent".to_string(),
+                        "Example".to_string(),
+                    ]>{failure_rows}</Table>
+                </StatsSection>
+                <StatsSection title=format!(
+                    "Expiring within {}",
+                    maybe_plural(EXPIRY_WINDOW_HOURS as usize, "hour", "hours"),
+                )>
+                    <Table headers=vec![
+                        "Message".to_string(),
+                        "Sender".to_string(),
+                        "Expires".to_string(),
+                        "".to_string(),
+                    ]>{expiring_rows}</Table>
+                </StatsSection>
+            </ReportView>
+        }
+        .into_view()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smtp_reply_codes() {
+        for (reason, code) in [
+            (
+                "Unexpected response from 'mx.example.org': Code: 550, Enhanced code: 5.1.1, Message: User unknown",
+                Some(550),
+            ),
+            ("Code 421 Service not available", Some(421)),
+            ("452 4.2.2 Mailbox full", Some(452)),
+            ("  554 Transaction failed", Some(554)),
+            (
+                "Connection to 'mx.example.org' failed: connect to 10.0.0.1:465 timed out",
+                None,
+            ),
+            ("Connection to 'mx452.example.org' failed: Connection refused", None),
+            ("DNS lookup failed: no MX for mail500.example.org", None),
+            ("Code: 5000", None),
+            ("Enhanced code: 5.1.1", None),
+            ("", None),
+        ] {
+            assert_eq!(smtp_code(reason), code, "{reason}");
+        }
+    }
+}
@@ -0,0 +1,407 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ahash::AHashMap;
+use chrono::NaiveDate;
+use leptos::*;
+use leptos_router::{use_navigate, use_query_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        card::{Card, CardItem},
+        icon::{IconCancel, IconCheckCircle, IconDocumentChartBar, IconEnvelope},
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        report::{ReportView, StatsSection},
+        skeleton::Skeleton,
+    },
+    core::{http, oauth::use_authorization},
+    pages::{
+        queue::reports::{DmarcResult, Record, Report},
+        reports::{
+            analytics::{fetch_reports, percent, report_day, AnalyticsHeader, ReportRange},
+            IncomingReport, ReportType,
+        },
+    },
+};
+
+const MAX_SOURCES: usize = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlignmentTotals {
+    pub messages: u64,
+    pub spf_aligned: u64,
+    pub dkim_aligned: u64,
+    pub dmarc_pass: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct SourceStats {
+    source_ip: String,
+    header_from: BTreeSet<String>,
+    totals: AlignmentTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct OrganizationStats {
+    name: String,
+    reports: usize,
+    totals: AlignmentTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct DomainStats {
+    domain: String,
+    policy: String,
+    totals: AlignmentTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct DmarcStats {
+    reports: usize,
+    totals: AlignmentTotals,
+    by_day: BTreeMap<NaiveDate, AlignmentTotals>,
+    by_source: Vec<SourceStats>,
+    by_organization: Vec<OrganizationStats>,
+    by_domain: Vec<DomainStats>,
+}
+
+impl AlignmentTotals {
+    pub fn add(&mut self, record: &Record) {
+        let count = record.row.count as u64;
+        let spf = record.row.policy_evaluated.spf == DmarcResult::Pass;
+        let dkim = record.row.policy_evaluated.dkim == DmarcResult::Pass;
+
+        self.messages += count;
+        if spf {
+            self.spf_aligned += count;
+        }
+        if dkim {
+            self.dkim_aligned += count;
+        }
+        if spf || dkim {
+            self.dmarc_pass += count;
+        }
+    }
+
+    // Failing both SPF and DKIM alignment, what `p=reject` would have rejected
+    pub fn failing(&self) -> u64 {
+        self.messages - self.dmarc_pass
+    }
+}
+
+impl DmarcStats {
+    fn new(reports: &[(String, IncomingReport<Report>)]) -> Self {
+        let mut stats = DmarcStats {
+            reports: reports.len(),
+            ..Default::default()
+        };
+        let mut by_source: AHashMap<String, SourceStats> = AHashMap::new();
+        let mut by_organization: AHashMap<String, OrganizationStats> = AHashMap::new();
+        let mut by_domain: AHashMap<String, DomainStats> = AHashMap::new();
+
+        for (_, report) in reports {
+            let report = &report.report;
+            let org_name = &report.report_metadata.org_name;
+            let day = report_day(report.report_metadata.date_range.begin);
+            let organization =
+                by_organization
+                    .entry(org_name.clone())
+                    .or_insert_with(|| OrganizationStats {
+                        name: org_name.clone(),
+                        ..Default::default()
+                    });
+            organization.reports += 1;
+
+            for record in &report.record {
+                stats.totals.add(record);
+                stats.by_day.entry(day).or_default().add(record);
+                organization.totals.add(record);
+
+                let source_ip = record
+                    .row
+                    .source_ip
+                    .map_or_else(|| "Unknown".to_string(), |ip| ip.to_string());
+                let header_from = if !record.identifiers.header_from.is_empty() {
+                    record.identifiers.header_from.to_lowercase()
+                } else {
+                    report.policy_published.domain.to_lowercase()
+                };
+                let source = by_source
+                    .entry(source_ip.clone())
+                    .or_insert_with(|| SourceStats {
+                        source_ip,
+                        ..Default::default()
+                    });
+                source.totals.add(record);
+                source.header_from.insert(header_from.clone());
+
+                let domain = by_domain
+                    .entry(header_from.clone())
+                    .or_insert_with(|| DomainStats {
+                        domain: header_from,
+                        ..Default::default()
+                    });
+                domain.totals.add(record);
+                domain.policy = report.policy_published.p.to_string();
+            }
+        }
+
+        // Senders failing alignment come first, as they are the ones that
+        // would break when enforcing the policy.
+        stats.by_source = by_source.into_values().collect();
+        stats.by_source.sort_by(|a, b| {
+            b.totals
+                .failing()
+                .cmp(&a.totals.failing())
+                .then(b.totals.messages.cmp(&a.totals.messages))
+                .then(a.source_ip.cmp(&b.source_ip))
+        });
+        stats.by_source.truncate(MAX_SOURCES);
+        stats.by_organization = by_organization.into_values().collect();
+        stats.by_organization.sort_by(|a, b| {
+            b.totals
+                .messages
+                .cmp(&a.totals.messages)
+                .then(a.name.cmp(&b.name))
+        });
+        stats.by_domain = by_domain.into_values().collect();
+        stats.by_domain.sort_by(|a, b| {
+            b.totals
+                .messages
+                .cmp(&a.totals.messages)
+                .then(a.domain.cmp(&b.domain))
+        });
+
+        stats
+    }
+}
+
+#[component]
+pub fn DmarcAnalytics() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let query = use_query_map();
+    let range = create_memo(move |_| query.with(ReportRange::from_query));
+    let progress = create_rw_signal((0usize, 0usize));
+
+    let stats = create_resource(
+        move || range.get(),
+        move |range| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let reports =
+                    fetch_reports::<Report>(&auth, ReportType::Dmarc, &range, |loaded, total| {
+                        progress.set((loaded, total))
+                    })
+                    .await?;
+
+                Ok(DmarcStats::new(&reports))
+            }
+        },
+    );
+
+    view! {
+        <AnalyticsHeader
+            title="DMARC Analytics"
+            subtitle="Alignment of the messages reported in DMARC aggregate reports"
+            range=range
+            progress=progress
+            on_range_change=move |range: ReportRange| {
+                use_navigate()(&range.url("/manage/analytics/dmarc"), Default::default());
+            }
+
+            on_refresh=move |_| stats.refetch()
+        />
+
+        <Transition fallback=Skeleton>
+            {move || match stats.get() {
+                None => None,
+                Some(Err(http::Error::Unauthorized)) => {
+                    use_navigate()("/login", Default::default());
+                    Some(view! { <div></div> }.into_view())
+                }
+                Some(Err(err)) => {
+                    alert.set(Alert::from(err));
+                    Some(view! { <Skeleton/> }.into_view())
+                }
+                Some(Ok(stats)) => Some(stats.into_view()),
+            }}
+
+        </Transition>
+    }
+}
+
+impl IntoView for DmarcStats {
+    fn into_view(self) -> View {
+        let day_rows = self
+            .by_day
+            .into_iter()
+            .map(|(day, totals)| {
+                let width = format!(
+                    "width: {}%",
+                    (totals.dmarc_pass * 100)
+                        .checked_div(totals.messages)
+                        .unwrap_or_default(),
+                );
+                view! {
+                    <TableRow>
+                        <span>{day.format("%Y-%m-%d").to_string()}</span>
+                        <span>{totals.messages}</span>
+                        <span>{percent(totals.spf_aligned, totals.messages)}</span>
+                        <span>{percent(totals.dkim_aligned, totals.messages)}</span>
+                        <span>{percent(totals.dmarc_pass, totals.messages)}</span>
+                        <div class="flex w-48 h-1.5 bg-red-200 rounded-full overflow-hidden dark:bg-red-900">
+                            <div class="bg-teal-500" style=width></div>
+                        </div>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let domain_rows = self
+            .by_domain
+            .into_iter()
+            .map(|domain| {
+                let totals = domain.totals;
+                view! {
+                    <TableRow>
+                        <span>{domain.domain}</span>
+                        <span>{domain.policy}</span>
+                        <span>{totals.messages}</span>
+                        <span>{percent(totals.spf_aligned, totals.messages)}</span>
+                        <span>{percent(totals.dkim_aligned, totals.messages)}</span>
+                        <span>{percent(totals.dmarc_pass, totals.messages)}</span>
+                        <span>{totals.failing()}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let source_rows = self
+            .by_source
+            .into_iter()
+            .map(|source| {
+                let totals = source.totals;
+                view! {
+                    <TableRow>
+                        <span class="font-mono">{source.source_ip}</span>
+                        <span>{source.header_from.into_iter().collect::<Vec<_>>().join(", ")}</span>
+                        <span>{totals.messages}</span>
+                        <span>{percent(totals.spf_aligned, totals.messages)}</span>
+                        <span>{percent(totals.dkim_aligned, totals.messages)}</span>
+                        <span>{totals.failing()}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let organization_rows = self
+            .by_organization
+            .into_iter()
+            .map(|organization| {
+                let totals = organization.totals;
+                view! {
+                    <TableRow>
+                        <span>{organization.name}</span>
+                        <span>{organization.reports}</span>
+                        <span>{totals.messages}</span>
+                        <span>{percent(totals.dmarc_pass, totals.messages)}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+
+        view! {
+            <Card>
+                <CardItem title="Reports" contents=self.reports.to_string()>
+
+                    <IconDocumentChartBar attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Messages" contents=self.totals.messages.to_string()>
+
+                    <IconEnvelope attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem
+                    title="DMARC Pass Rate"
+                    contents=percent(self.totals.dmarc_pass, self.totals.messages)
+                >
+
+                    <IconCheckCircle attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Would Be Rejected" contents=self.totals.failing().to_string()>
+
+                    <IconCancel attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+            </Card>
+
+            <ReportView>
+                <StatsSection title="Alignment Over Time">
+                    <Table headers=vec![
+                        "Date".to_string(),
+                        "Messages".to_string(),
+                        "SPF Aligned".to_string(),
+                        "DKIM Aligned".to_string(),
+                        "DMARC Pass".to_string(),
+                        "".to_string(),
+                    ]>{day_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Header From Domains">
+                    <Table headers=vec![
+                        "Domain".to_string(),
+                        "Policy".to_string(),
+                        "Messages".to_string(),
+                        "SPF Aligned".to_string(),
+                        "DKIM Aligned".to_string(),
+                        "DMARC Pass".to_string(),
+                        "Would Be Rejected".to_string(),
+                    ]>{domain_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Sending Sources">
+                    <Table headers=vec![
+                        "Source IP".to_string(),
+                        "Header From".to_string(),
+                        "Messages".to_string(),
+                        "SPF Aligned".to_string(),
+                        "DKIM Aligned".to_string(),
+                        "Would Be Rejected".to_string(),
+                    ]>{source_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Reporting Organizations">
+                    <Table headers=vec![
+                        "Organization".to_string(),
+                        "Reports".to_string(),
+                        "Messages".to_string(),
+                        "DMARC Pass".to_string(),
+                    ]>{organization_rows}</Table>
+                </StatsSection>
+            </ReportView>
+        }
+        .into_view()
+    }
+}
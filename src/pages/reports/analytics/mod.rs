@@ -0,0 +1,176 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+pub mod dmarc;
+pub mod senders;
+pub mod tls;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use leptos::*;
+use leptos_router::ParamsMap;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    components::{form::button::Button, icon::IconRefresh, list::toolbar::FilterInput, Color},
+    core::{http, oauth::AuthToken, url::UrlBuilder},
+    pages::reports::{
+        fetch_report_ids, fetch_reports_by_id, parse_report_date, IncomingReport, ReportType,
+    },
+};
+
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl ReportRange {
+    pub fn from_query(query: &ParamsMap) -> Self {
+        let param = |name: &str| {
+            query
+                .get(name)
+                .and_then(|value| NaiveDate::parse_from_str(value.trim(), DATE_FORMAT).ok())
+        };
+        let today = Local::now().date_naive();
+        let to = param("to").unwrap_or(today);
+        let from = param("from")
+            .filter(|from| from <= &to)
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_RANGE_DAYS));
+
+        ReportRange { from, to }
+    }
+
+    pub fn contains(&self, datetime: DateTime<Utc>) -> bool {
+        let date = datetime.with_timezone(&Local).date_naive();
+        date >= self.from && date <= self.to
+    }
+
+    pub fn url(&self, base_url: &str) -> String {
+        self.url_builder(base_url).finish()
+    }
+
+    pub fn url_builder(&self, base_url: &str) -> UrlBuilder {
+        UrlBuilder::new(base_url)
+            .with_parameter("from", self.from.format(DATE_FORMAT).to_string())
+            .with_parameter("to", self.to.format(DATE_FORMAT).to_string())
+    }
+}
+
+// Report ids carry the time received, so only the reports in range are downloaded
+pub async fn fetch_reports<T: DeserializeOwned>(
+    auth: &AuthToken,
+    report_type: ReportType,
+    range: &ReportRange,
+    on_progress: impl FnMut(usize, usize),
+) -> http::Result<Vec<(String, IncomingReport<T>)>> {
+    let ids = fetch_report_ids(auth, report_type, None, |id| {
+        range.contains(parse_report_date(id))
+    })
+    .await?;
+
+    fetch_reports_by_id(auth, report_type, ids, on_progress).await
+}
+
+pub fn percent(part: u64, total: u64) -> String {
+    if total > 0 {
+        format!("{:.1}%", part as f64 * 100.0 / total as f64)
+    } else {
+        "-".to_string()
+    }
+}
+
+#[component]
+pub fn AnalyticsHeader(
+    #[prop(into)] title: String,
+    #[prop(into)] subtitle: String,
+    #[prop(into)] range: Signal<ReportRange>,
+    #[prop(into)] progress: Signal<(usize, usize)>,
+    #[prop(into)] on_range_change: Callback<ReportRange, ()>,
+    #[prop(into)] on_refresh: Callback<(), ()>,
+) -> impl IntoView {
+    let set_date = move |value: String, is_from: bool| {
+        if let Ok(date) = NaiveDate::parse_from_str(&value, DATE_FORMAT) {
+            let mut range = range.get();
+            if is_from {
+                range.from = date;
+            } else {
+                range.to = date;
+            }
+            if range.from <= range.to {
+                on_range_change.call(range);
+            }
+        }
+    };
+
+    view! {
+        <div class="max-w-[85rem] px-4 pt-10 sm:px-6 lg:px-8 lg:pt-14 mx-auto grid gap-3 md:flex md:items-center md:justify-between">
+            <div>
+                <h2 class="text-xl font-semibold text-gray-800 dark:text-gray-200">{title}</h2>
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+
+                    {move || {
+                        let (loaded, total) = progress.get();
+                        if loaded < total {
+                            format!("Loading reports… {loaded} of {total}")
+                        } else {
+                            subtitle.clone()
+                        }
+                    }}
+
+                </p>
+            </div>
+            <div class="inline-flex items-center gap-x-2">
+                <FilterInput
+                    placeholder="From"
+                    input_type="date"
+                    value=Signal::derive(move || {
+                        Some(range.get().from.format(DATE_FORMAT).to_string())
+                    })
+                    on_change=Callback::new(move |value: String| set_date(value, true))
+                />
+                <FilterInput
+                    placeholder="To"
+                    input_type="date"
+                    value=Signal::derive(move || Some(range.get().to.format(DATE_FORMAT).to_string()))
+                    on_change=Callback::new(move |value: String| set_date(value, false))
+                />
+                <Button
+                    text="Refresh"
+                    color=Color::Gray
+                    on_click=Callback::new(move |_| on_refresh.call(()))
+                >
+                    <IconRefresh/>
+                </Button>
+            </div>
+        </div>
+    }
+}
+
+pub fn report_day(timestamp: u64) -> NaiveDate {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|datetime| datetime.with_timezone(&Local).date_naive())
+        .unwrap_or_else(|| Local::now().date_naive())
+}
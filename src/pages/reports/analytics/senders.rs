@@ -0,0 +1,493 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use ahash::AHashMap;
+use chrono::{Duration, NaiveDate};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::{use_navigate, use_query_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        list::{
+            header::ColumnList, toolbar::FilterSelect, Filters, Footer, ListItem, ListSection,
+            ListTable, ListTextItem, Toolbar, ZeroResults,
+        },
+        messages::alert::{use_alerts, Alert},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{http, oauth::use_authorization},
+    pages::{
+        maybe_plural,
+        queue::reports::{DkimResult, Record, Report, SPFDomainScope},
+        reports::{
+            analytics::{
+                dmarc::AlignmentTotals, fetch_reports, percent, report_day, AnalyticsHeader,
+                ReportRange,
+            },
+            IncomingReport, ReportType,
+        },
+    },
+    STATE_SENDER_LABELS_KEY,
+};
+
+const HISTORY_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SenderLabel {
+    Authorized,
+    Unauthorized,
+}
+
+const LABEL_FILTERS: &[(&str, &str)] = &[
+    ("", "All senders"),
+    ("authorized", "Authorized"),
+    ("unauthorized", "Unauthorized"),
+    ("unlabeled", "Not labeled"),
+];
+
+// Identified by the DKIM `d=` and SPF domains, or by IP when neither is present
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Sender {
+    key: String,
+    dkim_domain: Option<String>,
+    spf_domain: Option<String>,
+    source_ips: BTreeSet<String>,
+    header_from: BTreeSet<String>,
+    totals: AlignmentTotals,
+    history: BTreeMap<NaiveDate, AlignmentTotals>,
+}
+
+impl Sender {
+    fn identify(record: &Record) -> (Option<String>, Option<String>) {
+        let dkim = &record.auth_results.dkim;
+        let dkim_domain = dkim
+            .iter()
+            .find(|dkim| dkim.result == DkimResult::Pass)
+            .or_else(|| dkim.first())
+            .map(|dkim| dkim.domain.to_lowercase())
+            .filter(|domain| !domain.is_empty());
+        let spf = &record.auth_results.spf;
+        let spf_domain = spf
+            .iter()
+            .find(|spf| spf.scope == SPFDomainScope::MailFrom)
+            .or_else(|| spf.first())
+            .map(|spf| spf.domain.to_lowercase())
+            .filter(|domain| !domain.is_empty());
+        (dkim_domain, spf_domain)
+    }
+
+    fn name(&self) -> String {
+        match (&self.dkim_domain, &self.spf_domain) {
+            (Some(dkim), Some(spf)) if dkim == spf => dkim.clone(),
+            (Some(dkim), Some(spf)) => format!("{dkim} / {spf}"),
+            (Some(domain), None) | (None, Some(domain)) => domain.clone(),
+            (None, None) => self
+                .source_ips
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string()),
+        }
+    }
+}
+
+fn build_inventory(reports: &[(String, IncomingReport<Report>)]) -> Vec<Sender> {
+    let mut senders: AHashMap<String, Sender> = AHashMap::new();
+
+    for (_, report) in reports {
+        let report = &report.report;
+        let day = report_day(report.report_metadata.date_range.begin);
+
+        for record in &report.record {
+            let source_ip = record
+                .row
+                .source_ip
+                .map_or_else(|| "Unknown".to_string(), |ip| ip.to_string());
+            let (dkim_domain, spf_domain) = Sender::identify(record);
+            let key = if dkim_domain.is_some() || spf_domain.is_some() {
+                format!(
+                    "d={};spf={}",
+                    dkim_domain.as_deref().unwrap_or_default(),
+                    spf_domain.as_deref().unwrap_or_default()
+                )
+            } else {
+                format!("ip={source_ip}")
+            };
+
+            let sender = senders.entry(key.clone()).or_insert_with(|| Sender {
+                key,
+                dkim_domain,
+                spf_domain,
+                ..Default::default()
+            });
+            sender.source_ips.insert(source_ip);
+            if !record.identifiers.header_from.is_empty() {
+                sender
+                    .header_from
+                    .insert(record.identifiers.header_from.to_lowercase());
+            }
+            sender.totals.add(record);
+            sender.history.entry(day).or_default().add(record);
+        }
+    }
+
+    let mut senders = senders.into_values().collect::<Vec<_>>();
+    senders.sort_by(|a, b| {
+        b.totals
+            .messages
+            .cmp(&a.totals.messages)
+            .then(a.key.cmp(&b.key))
+    });
+    senders
+}
+
+fn load_labels() -> HashMap<String, SenderLabel> {
+    LocalStorage::get(STATE_SENDER_LABELS_KEY).unwrap_or_default()
+}
+
+fn save_labels(labels: &HashMap<String, SenderLabel>) {
+    if let Err(err) = LocalStorage::set(STATE_SENDER_LABELS_KEY, labels) {
+        log::error!("Failed to save sender labels to local storage: {}", err);
+    }
+}
+
+#[component]
+pub fn SenderInventory() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let query = use_query_map();
+    let range = create_memo(move |_| query.with(ReportRange::from_query));
+    let label_filter = create_memo(move |_| {
+        query.with(|q| q.get("label").cloned().filter(|label| !label.is_empty()))
+    });
+    let progress = create_rw_signal((0usize, 0usize));
+    let labels = create_rw_signal(load_labels());
+
+    let senders = create_resource(
+        move || range.get(),
+        move |range| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let reports =
+                    fetch_reports::<Report>(&auth, ReportType::Dmarc, &range, |loaded, total| {
+                        progress.set((loaded, total))
+                    })
+                    .await?;
+
+                Ok(build_inventory(&reports))
+            }
+        },
+    );
+
+    let set_label = move |(key, label): (String, Option<SenderLabel>)| {
+        labels.update(|labels| {
+            match label {
+                Some(label) => {
+                    labels.insert(key, label);
+                }
+                None => {
+                    labels.remove(&key);
+                }
+            }
+            save_labels(labels);
+        });
+    };
+    let navigate_to = move |range: ReportRange, label: Option<String>| {
+        use_navigate()(
+            &range
+                .url_builder("/manage/analytics/senders")
+                .with_optional_parameter("label", label)
+                .finish(),
+            Default::default(),
+        );
+    };
+
+    view! {
+        <AnalyticsHeader
+            title="Sender Inventory"
+            subtitle="Systems sending as your domains, as seen in DMARC aggregate reports"
+            range=range
+            progress=progress
+            on_range_change=move |range: ReportRange| navigate_to(range, label_filter.get())
+            on_refresh=move |_| senders.refetch()
+        />
+
+        <ListSection>
+            <ListTable title="Senders" subtitle="Label each sender as authorized or unauthorized">
+                <Toolbar slot>
+                    <div></div>
+                </Toolbar>
+
+                <Filters slot>
+                    <FilterSelect
+                        value=label_filter
+                        options=LABEL_FILTERS
+                        on_change=move |label: String| {
+                            navigate_to(range.get(), Some(label).filter(|label| !label.is_empty()))
+                        }
+                    />
+                </Filters>
+
+                <Transition fallback=Skeleton>
+                    {move || match senders.get() {
+                        None => None,
+                        Some(Err(http::Error::Unauthorized)) => {
+                            use_navigate()("/login", Default::default());
+                            Some(view! { <div></div> }.into_view())
+                        }
+                        Some(Err(err)) => {
+                            alert.set(Alert::from(err));
+                            Some(view! { <Skeleton/> }.into_view())
+                        }
+                        Some(Ok(senders)) => {
+                            let label_filter = label_filter.get();
+                            let days = history_days(&range.get());
+                            let senders = senders
+                                .into_iter()
+                                .filter(|sender| {
+                                    let label = labels
+                                        .with(|labels| labels.get(&sender.key).copied());
+                                    match label_filter.as_deref() {
+                                        Some("authorized") => label == Some(SenderLabel::Authorized),
+                                        Some("unauthorized") => {
+                                            label == Some(SenderLabel::Unauthorized)
+                                        }
+                                        Some("unlabeled") => label.is_none(),
+                                        _ => true,
+                                    }
+                                })
+                                .collect::<Vec<_>>();
+                            if senders.is_empty() {
+                                return Some(
+                                    view! {
+                                        <ZeroResults
+                                            title="No senders"
+                                            subtitle="No DMARC reports matching the selected criteria were found."
+                                        />
+                                    }
+                                        .into_view(),
+                                );
+                            }
+                            Some(
+                                view! {
+                                    <ColumnList headers=vec![
+                                        "Sender".to_string(),
+                                        "Source IPs".to_string(),
+                                        "Header From".to_string(),
+                                        "Messages".to_string(),
+                                        "DMARC Pass".to_string(),
+                                        format!(
+                                            "Last {}",
+                                            maybe_plural(days.len(), "day", "days"),
+                                        ),
+                                        "Label".to_string(),
+                                    ]>
+
+                                        {senders
+                                            .into_iter()
+                                            .map(|sender| {
+                                                view! {
+                                                    <SenderItem
+                                                        sender=sender
+                                                        labels=labels
+                                                        days=days.clone()
+                                                        on_label=set_label
+                                                    />
+                                                }
+                                            })
+                                            .collect_view()}
+
+                                    </ColumnList>
+                                }
+                                    .into_view(),
+                            )
+                        }
+                    }}
+
+                </Transition>
+
+                <Footer slot>
+                    <div></div>
+                </Footer>
+            </ListTable>
+        </ListSection>
+    }
+}
+
+// Calendar days at the end of the range, including the ones without reports
+fn history_days(range: &ReportRange) -> Arc<[NaiveDate]> {
+    let from = range.from.max(range.to - Duration::days(HISTORY_DAYS - 1));
+    from.iter_days()
+        .take_while(|day| day <= &range.to)
+        .collect()
+}
+
+#[component]
+fn SenderItem(
+    sender: Sender,
+    labels: RwSignal<HashMap<String, SenderLabel>>,
+    days: Arc<[NaiveDate]>,
+    #[prop(into)] on_label: Callback<(String, Option<SenderLabel>), ()>,
+) -> impl IntoView {
+    let name = sender.name();
+    let key = sender.key.clone();
+    let key_ = sender.key.clone();
+    let label = create_memo(move |_| labels.with(|labels| labels.get(&key_).copied()));
+    let source_ips = sender.source_ips.iter().cloned().collect::<Vec<_>>();
+    let ips_title = source_ips.join(", ");
+    let ips = if source_ips.len() > 3 {
+        format!(
+            "{} and {} more",
+            source_ips[..3].join(", "),
+            source_ips.len() - 3
+        )
+    } else {
+        ips_title.clone()
+    };
+    let header_from = sender
+        .header_from
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(", ");
+    let totals = sender.totals;
+    let max_messages = days
+        .iter()
+        .filter_map(|day| sender.history.get(day))
+        .map(|totals| totals.messages)
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    let history = days
+        .iter()
+        .map(|day| {
+            let Some(totals) = sender.history.get(day) else {
+                return view! {
+                    <div
+                        class="w-1.5 bg-gray-200 dark:bg-gray-700"
+                        style="height: 5%"
+                        title=format!("{}: no messages", day.format("%Y-%m-%d"))
+                    ></div>
+                };
+            };
+            let height = format!("height: {}%", (totals.messages * 100 / max_messages).max(5));
+            let class = match totals.dmarc_pass * 100 / totals.messages.max(1) {
+                90.. => "w-1.5 bg-teal-500",
+                50.. => "w-1.5 bg-yellow-500",
+                _ => "w-1.5 bg-red-500",
+            };
+            let title = format!(
+                "{}: {}, {} DMARC pass",
+                day.format("%Y-%m-%d"),
+                maybe_plural(totals.messages as usize, "message", "messages"),
+                percent(totals.dmarc_pass, totals.messages)
+            );
+            view! { <div class=class style=height title=title></div> }
+        })
+        .collect_view();
+
+    view! {
+        <tr>
+            <ListItem>
+                <span class="block text-sm font-semibold text-gray-800 dark:text-gray-200">
+                    {name}
+                </span>
+                <span class="block text-xs text-gray-500">
+                    {match (&sender.dkim_domain, &sender.spf_domain) {
+                        (None, None) => "No DKIM or SPF domain".to_string(),
+                        (dkim, spf) => {
+                            format!(
+                                "DKIM: {}, SPF: {}",
+                                dkim.as_deref().unwrap_or("-"),
+                                spf.as_deref().unwrap_or("-"),
+                            )
+                        }
+                    }}
+
+                </span>
+            </ListItem>
+            <ListItem>
+                <span class="text-sm font-mono text-gray-500" title=ips_title>
+                    {ips}
+                </span>
+            </ListItem>
+            <ListTextItem>{header_from}</ListTextItem>
+            <ListTextItem>{totals.messages}</ListTextItem>
+            <ListTextItem>{percent(totals.dmarc_pass, totals.messages)}</ListTextItem>
+            <ListItem>
+                <div class="flex items-end gap-px h-8">{history}</div>
+            </ListItem>
+            <ListItem>
+                <div class="flex items-center gap-x-2">
+
+                    {move || match label.get() {
+                        Some(SenderLabel::Authorized) => {
+                            view! { <Badge color=Color::Green>Authorized</Badge> }.into_view()
+                        }
+                        Some(SenderLabel::Unauthorized) => {
+                            view! { <Badge color=Color::Red>Unauthorized</Badge> }.into_view()
+                        }
+                        None => view! { <Badge color=Color::Gray>Not labeled</Badge> }.into_view(),
+                    }}
+
+                    <select
+                        class="py-1 px-2 pe-8 block border-gray-200 rounded-lg text-xs focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                        on:change=move |ev| {
+                            let label = match event_target_value(&ev).as_str() {
+                                "authorized" => Some(SenderLabel::Authorized),
+                                "unauthorized" => Some(SenderLabel::Unauthorized),
+                                _ => None,
+                            };
+                            on_label.call((key.clone(), label));
+                        }
+                    >
+
+                        <option value="" selected=move || label.get().is_none()>
+                            Not labeled
+                        </option>
+                        <option
+                            value="authorized"
+                            selected=move || label.get() == Some(SenderLabel::Authorized)
+                        >
+                            Authorized
+                        </option>
+                        <option
+                            value="unauthorized"
+                            selected=move || label.get() == Some(SenderLabel::Unauthorized)
+                        >
+                            Unauthorized
+                        </option>
+                    </select>
+                </div>
+            </ListItem>
+        </tr>
+    }
+}
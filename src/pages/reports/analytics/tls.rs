@@ -0,0 +1,462 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ahash::AHashMap;
+use chrono::NaiveDate;
+use leptos::*;
+use leptos_router::{use_navigate, use_query_map};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        card::{Card, CardItem},
+        icon::{IconCancel, IconCheckCircle, IconDocumentChartBar, IconLockClosed},
+        list::table::{Table, TableRow},
+        messages::alert::{use_alerts, Alert},
+        report::{ReportView, StatsSection},
+        skeleton::Skeleton,
+    },
+    core::{http, oauth::use_authorization},
+    pages::{
+        queue::reports::{FailureDetails, PolicyType, ResultType, TlsReport},
+        reports::{
+            analytics::{fetch_reports, percent, report_day, AnalyticsHeader, ReportRange},
+            IncomingReport, ReportType,
+        },
+    },
+};
+
+const MAX_HOSTS: usize = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct SessionTotals {
+    successful: u64,
+    failed: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct FailureStats {
+    name: String,
+    failed: u64,
+    result_types: BTreeSet<String>,
+    by_day: BTreeMap<NaiveDate, u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct PolicyStats {
+    domain: String,
+    policy_type: String,
+    totals: SessionTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct OrganizationStats {
+    name: String,
+    reports: usize,
+    totals: SessionTotals,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct TlsStats {
+    reports: usize,
+    totals: SessionTotals,
+    by_day: BTreeMap<NaiveDate, SessionTotals>,
+    by_result: Vec<FailureStats>,
+    by_mx: Vec<FailureStats>,
+    by_policy: Vec<PolicyStats>,
+    by_organization: Vec<OrganizationStats>,
+}
+
+impl SessionTotals {
+    fn add(&mut self, other: SessionTotals) {
+        self.successful += other.successful;
+        self.failed += other.failed;
+    }
+
+    fn total(&self) -> u64 {
+        self.successful + self.failed
+    }
+}
+
+impl FailureStats {
+    fn add(&mut self, day: NaiveDate, result_type: ResultType, failed: u64) {
+        self.failed += failed;
+        self.result_types.insert(result_type.to_string());
+        *self.by_day.entry(day).or_default() += failed;
+    }
+}
+
+impl TlsStats {
+    fn new(reports: &[(String, IncomingReport<TlsReport>)]) -> Self {
+        let mut stats = TlsStats {
+            reports: reports.len(),
+            ..Default::default()
+        };
+        let mut by_result: AHashMap<ResultType, FailureStats> = AHashMap::new();
+        let mut by_mx: AHashMap<String, FailureStats> = AHashMap::new();
+        let mut by_policy: AHashMap<(String, PolicyType), PolicyStats> = AHashMap::new();
+        let mut by_organization: AHashMap<String, OrganizationStats> = AHashMap::new();
+
+        for (_, report) in reports {
+            let report = &report.report;
+            let day = report_day(report.date_range.start_datetime.timestamp() as u64);
+            let org_name = report
+                .organization_name
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string());
+            let organization =
+                by_organization
+                    .entry(org_name.clone())
+                    .or_insert_with(|| OrganizationStats {
+                        name: org_name,
+                        ..Default::default()
+                    });
+            organization.reports += 1;
+
+            for policy in &report.policies {
+                let totals = SessionTotals {
+                    successful: policy.summary.total_success as u64,
+                    failed: policy.summary.total_failure as u64,
+                };
+                stats.totals.add(totals);
+                stats.by_day.entry(day).or_default().add(totals);
+                organization.totals.add(totals);
+
+                let domain = policy.policy.policy_domain.to_lowercase();
+                by_policy
+                    .entry((domain.clone(), policy.policy.policy_type))
+                    .or_insert_with(|| PolicyStats {
+                        domain,
+                        policy_type: policy.policy.policy_type.to_string(),
+                        ..Default::default()
+                    })
+                    .totals
+                    .add(totals);
+
+                for failure in &policy.failure_details {
+                    let failed = failure.failed_session_count as u64;
+                    by_result
+                        .entry(failure.result_type)
+                        .or_insert_with(|| FailureStats {
+                            name: failure.result_type.to_string(),
+                            ..Default::default()
+                        })
+                        .add(day, failure.result_type, failed);
+
+                    let mx = receiving_mx(failure, &policy.policy.mx_host);
+                    by_mx
+                        .entry(mx.clone())
+                        .or_insert_with(|| FailureStats {
+                            name: mx,
+                            ..Default::default()
+                        })
+                        .add(day, failure.result_type, failed);
+                }
+            }
+        }
+
+        stats.by_result = by_result.into_values().collect();
+        stats
+            .by_result
+            .sort_by(|a, b| b.failed.cmp(&a.failed).then(a.name.cmp(&b.name)));
+        stats.by_mx = by_mx.into_values().collect();
+        stats
+            .by_mx
+            .sort_by(|a, b| b.failed.cmp(&a.failed).then(a.name.cmp(&b.name)));
+        stats.by_mx.truncate(MAX_HOSTS);
+        stats.by_policy = by_policy.into_values().collect();
+        stats.by_policy.sort_by(|a, b| {
+            b.totals
+                .failed
+                .cmp(&a.totals.failed)
+                .then(a.domain.cmp(&b.domain))
+        });
+        stats.by_organization = by_organization.into_values().collect();
+        stats.by_organization.sort_by(|a, b| {
+            b.totals
+                .total()
+                .cmp(&a.totals.total())
+                .then(a.name.cmp(&b.name))
+        });
+
+        stats
+    }
+}
+
+// Falls back to the policy MX hosts when the reporter did not include one
+fn receiving_mx(failure: &FailureDetails, mx_hosts: &[String]) -> String {
+    failure
+        .receiving_mx_hostname
+        .as_deref()
+        .or(failure.receiving_mx_helo.as_deref())
+        .map(|host| host.trim_end_matches('.').to_lowercase())
+        .or_else(|| failure.receiving_ip.map(|ip| ip.to_string()))
+        .or_else(|| (!mx_hosts.is_empty()).then(|| mx_hosts.join(", ")))
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+#[component]
+pub fn TlsAnalytics() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let query = use_query_map();
+    let range = create_memo(move |_| query.with(ReportRange::from_query));
+    let progress = create_rw_signal((0usize, 0usize));
+
+    let stats = create_resource(
+        move || range.get(),
+        move |range| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let reports =
+                    fetch_reports::<TlsReport>(&auth, ReportType::Tls, &range, |loaded, total| {
+                        progress.set((loaded, total))
+                    })
+                    .await?;
+
+                Ok(TlsStats::new(&reports))
+            }
+        },
+    );
+
+    view! {
+        <AnalyticsHeader
+            title="TLS Analytics"
+            subtitle="Failed TLS sessions reported in TLS aggregate reports"
+            range=range
+            progress=progress
+            on_range_change=move |range: ReportRange| {
+                use_navigate()(&range.url("/manage/analytics/tls"), Default::default());
+            }
+
+            on_refresh=move |_| stats.refetch()
+        />
+
+        <Transition fallback=Skeleton>
+            {move || match stats.get() {
+                None => None,
+                Some(Err(http::Error::Unauthorized)) => {
+                    use_navigate()("/login", Default::default());
+                    Some(view! { <div></div> }.into_view())
+                }
+                Some(Err(err)) => {
+                    alert.set(Alert::from(err));
+                    Some(view! { <Skeleton/> }.into_view())
+                }
+                Some(Ok(stats)) => Some(stats.into_view()),
+            }}
+
+        </Transition>
+    }
+}
+
+impl IntoView for TlsStats {
+    fn into_view(self) -> View {
+        let days = self.by_day.keys().copied().collect::<Vec<_>>();
+        let day_rows = self
+            .by_day
+            .into_iter()
+            .map(|(day, totals)| {
+                let width = format!(
+                    "width: {}%",
+                    (totals.successful * 100)
+                        .checked_div(totals.total())
+                        .unwrap_or_default(),
+                );
+                view! {
+                    <TableRow>
+                        <span>{day.format("%Y-%m-%d").to_string()}</span>
+                        <span>{totals.successful}</span>
+                        <span>{totals.failed}</span>
+                        <span>{percent(totals.failed, totals.total())}</span>
+                        <div class="flex w-48 h-1.5 bg-red-200 rounded-full overflow-hidden dark:bg-red-900">
+                            <div class="bg-teal-500" style=width></div>
+                        </div>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let result_rows = self
+            .by_result
+            .into_iter()
+            .map(|result| {
+                let days = days.clone();
+                view! {
+                    <TableRow>
+                        <span>{result.name}</span>
+                        <span>{result.failed}</span>
+                        <span>{percent(result.failed, self.totals.failed)}</span>
+                        <Trend days values=result.by_day/>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let mx_rows = self
+            .by_mx
+            .into_iter()
+            .map(|mx| {
+                let days = days.clone();
+                view! {
+                    <TableRow>
+                        <span class="font-mono">{mx.name}</span>
+                        <span>{mx.result_types.into_iter().collect::<Vec<_>>().join(", ")}</span>
+                        <span>{mx.failed}</span>
+                        <Trend days values=mx.by_day/>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let policy_rows = self
+            .by_policy
+            .into_iter()
+            .map(|policy| {
+                let totals = policy.totals;
+                view! {
+                    <TableRow>
+                        <span>{policy.domain}</span>
+                        <span>{policy.policy_type}</span>
+                        <span>{totals.successful}</span>
+                        <span>{totals.failed}</span>
+                        <span>{percent(totals.failed, totals.total())}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+        let organization_rows = self
+            .by_organization
+            .into_iter()
+            .map(|organization| {
+                let totals = organization.totals;
+                view! {
+                    <TableRow>
+                        <span>{organization.name}</span>
+                        <span>{organization.reports}</span>
+                        <span>{totals.successful}</span>
+                        <span>{totals.failed}</span>
+                        <span>{percent(totals.failed, totals.total())}</span>
+                    </TableRow>
+                }
+            })
+            .collect_view();
+
+        view! {
+            <Card>
+                <CardItem title="Reports" contents=self.reports.to_string()>
+
+                    <IconDocumentChartBar attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Successful Sessions" contents=self.totals.successful.to_string()>
+
+                    <IconLockClosed attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem title="Failed Sessions" contents=self.totals.failed.to_string()>
+
+                    <IconCancel attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+                <CardItem
+                    title="Success Rate"
+                    contents=percent(self.totals.successful, self.totals.total())
+                >
+
+                    <IconCheckCircle attr:class="flex-shrink-0 size-5 text-gray-400 dark:text-gray-600"/>
+
+                </CardItem>
+            </Card>
+
+            <ReportView>
+                <StatsSection title="Sessions Over Time">
+                    <Table headers=vec![
+                        "Date".to_string(),
+                        "Successful".to_string(),
+                        "Failed".to_string(),
+                        "Failure Rate".to_string(),
+                        "".to_string(),
+                    ]>{day_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Failures by Result Type">
+                    <Table headers=vec![
+                        "Result Type".to_string(),
+                        "Failed Sessions".to_string(),
+                        "Share".to_string(),
+                        "Trend".to_string(),
+                    ]>{result_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Failures by Receiving MX">
+                    <Table headers=vec![
+                        "Receiving MX".to_string(),
+                        "Result Types".to_string(),
+                        "Failed Sessions".to_string(),
+                        "Trend".to_string(),
+                    ]>{mx_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Policies">
+                    <Table headers=vec![
+                        "Policy Domain".to_string(),
+                        "Policy Type".to_string(),
+                        "Successful".to_string(),
+                        "Failed".to_string(),
+                        "Failure Rate".to_string(),
+                    ]>{policy_rows}</Table>
+                </StatsSection>
+                <StatsSection title="Sending Organizations">
+                    <Table headers=vec![
+                        "Organization".to_string(),
+                        "Reports".to_string(),
+                        "Successful".to_string(),
+                        "Failed".to_string(),
+                        "Failure Rate".to_string(),
+                    ]>{organization_rows}</Table>
+                </StatsSection>
+            </ReportView>
+        }
+        .into_view()
+    }
+}
+
+#[component]
+fn Trend(days: Vec<NaiveDate>, values: BTreeMap<NaiveDate, u64>) -> impl IntoView {
+    let max = values.values().copied().max().unwrap_or_default().max(1);
+
+    view! {
+        <div class="flex items-end gap-px h-6">
+
+            {days
+                .into_iter()
+                .map(|day| {
+                    let value = values.get(&day).copied().unwrap_or_default();
+                    let style = format!("height: {}%", (value * 100 / max).max(4));
+                    let class = if value > 0 { "w-1.5 bg-red-500" } else { "w-1.5 bg-gray-200 dark:bg-gray-700" };
+                    let title = format!("{}: {value}", day.format("%Y-%m-%d"));
+                    view! { <div class=class style=style title=title></div> }
+                })
+                .collect_view()}
+
+        </div>
+    }
+}
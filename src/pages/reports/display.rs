@@ -29,8 +29,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     components::{
+        form::button::Button,
+        icon::IconArrowDownTray,
         messages::alert::{use_alerts, Alert, Alerts},
         skeleton::Skeleton,
+        Color,
     },
     core::{
         http::{self, HttpRequest},
@@ -45,7 +48,12 @@ use crate::{
     },
 };
 
-use super::{parse_report_date, ReportType};
+use super::{
+    export::{ExportFormat, ExportedReports},
+    parse_report_date,
+    triage::ArfTriage,
+    ReportType,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -126,51 +134,77 @@ pub fn IncomingReportDisplay() -> impl IntoView {
                     Some(view! { <div></div> }.into_view())
                 }
                 Some(Ok(report)) => {
-                    match report {
+                    let report_id = params().get("id").cloned().unwrap_or_default();
+                    let exported = store_value(report.exported(report_id.clone()));
+                    let download = move |format: ExportFormat| {
+                        if let Err(err) = exported.with_value(|exported| exported.download(format))
+                        {
+                            alert.set(Alert::error(format!("Failed to download file: {err}")));
+                        }
+                    };
+                    let downloads = view! {
+                        <div class="max-w-[85rem] px-4 pt-5 sm:px-6 lg:px-8 lg:pt-7 mx-auto flex justify-end gap-x-2">
+                            <Button
+                                text=format!(
+                                    "Download {}",
+                                    ExportFormat::Original.label(report_type.get()),
+                                )
+
+                                color=Color::Gray
+                                on_click=Callback::new(move |_| download(ExportFormat::Original))
+                            >
+                                <IconArrowDownTray/>
+                            </Button>
+                            <Button
+                                text="Download CSV"
+                                color=Color::Gray
+                                on_click=Callback::new(move |_| download(ExportFormat::Csv))
+                            >
+                                <IconArrowDownTray/>
+                            </Button>
+                        </div>
+                    };
+                    let report = match report {
                         ReportWrapper::Tls(report) => {
                             let (report, extra) = report.unwrap_report();
-                            Some(
-                                view! {
-                                    <TlsReportDisplay
-                                        report=report
-                                        extra=extra
-                                        back_url="/manage/reports/tls".to_string()
-                                    />
-                                }
-                                    .into_view(),
-                            )
+                            view! {
+                                <TlsReportDisplay
+                                    report=report
+                                    extra=extra
+                                    back_url="/manage/reports/tls".to_string()
+                                />
+                            }
+                            .into_view()
                         }
                         ReportWrapper::Dmarc(report) => {
                             let (report, extra) = report.unwrap_report();
-                            Some(
-                                view! {
-                                    <DmarcReportDisplay
-                                        report=report
-                                        extra=extra
-                                        back_url="/manage/reports/dmarc".to_string()
-                                    />
-                                }
-                                    .into_view(),
-                            )
+                            view! {
+                                <DmarcReportDisplay
+                                    report=report
+                                    extra=extra
+                                    back_url="/manage/reports/dmarc".to_string()
+                                />
+                            }
+                            .into_view()
                         }
                         ReportWrapper::Arf(report) => {
                             let (report, extra) = report.unwrap_report();
-                            let received = parse_report_date(
-                                &params().get("id").cloned().unwrap_or_default(),
-                            );
-                            Some(
-                                view! {
-                                    <ArfReportDisplay
-                                        report=report
-                                        received=received
-                                        extra=extra
-                                        back_url="/manage/reports/arf".to_string()
-                                    />
-                                }
-                                    .into_view(),
-                            )
+                            let received = parse_report_date(&report_id);
+                            let sender = report.original_mail_from.clone();
+                            view! {
+                                <ArfReportDisplay
+                                    report=report
+                                    received=received
+                                    extra=extra
+                                    back_url="/manage/reports/arf".to_string()
+                                >
+                                    <ArfTriage report_id=report_id.clone() sender=sender/>
+                                </ArfReportDisplay>
+                            }
+                            .into_view()
                         }
-                    }
+                    };
+                    Some(view! { {downloads} {report} }.into_view())
                 }
             }}
 
@@ -178,6 +212,18 @@ pub fn IncomingReportDisplay() -> impl IntoView {
     }
 }
 
+impl ReportWrapper {
+    fn exported(&self, id: String) -> ExportedReports {
+        match self {
+            ReportWrapper::Dmarc(report) => {
+                ExportedReports::Dmarc(vec![(id, report.report.clone())])
+            }
+            ReportWrapper::Tls(report) => ExportedReports::Tls(vec![(id, report.report.clone())]),
+            ReportWrapper::Arf(report) => ExportedReports::Arf(vec![(id, report.report.clone())]),
+        }
+    }
+}
+
 impl<T> IncomingReport<T> {
     pub fn unwrap_report(self) -> (T, Vec<(String, String)>) {
         (
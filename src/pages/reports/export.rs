@@ -0,0 +1,737 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    core::file::download_file,
+    pages::{
+        queue::{
+            messages::export::write_row,
+            reports::{
+                ActionDisposition, Alignment, AuthFailureType, DeliveryResult, Disposition,
+                DkimResult, DmarcResult, Feedback, IdentityAlignment, PolicyOverride, Report,
+                SPFDomainScope, SpfResult, TlsReport,
+            },
+        },
+        reports::ReportType,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    // RFC 7489 XML, RFC 8460 JSON or RFC 5965 feedback reports
+    Original,
+    Csv,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExportedReports {
+    Dmarc(Vec<(String, Report)>),
+    Tls(Vec<(String, TlsReport)>),
+    Arf(Vec<(String, Feedback)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedFile {
+    pub name: String,
+    pub content_type: &'static str,
+    pub contents: String,
+}
+
+const DMARC_CSV_HEADERS: &[&str] = &[
+    "id",
+    "org_name",
+    "report_id",
+    "begin",
+    "end",
+    "policy_domain",
+    "policy",
+    "source_ip",
+    "count",
+    "disposition",
+    "dkim",
+    "spf",
+    "reason",
+    "header_from",
+    "envelope_from",
+    "envelope_to",
+    "dkim_results",
+    "spf_results",
+];
+
+const TLS_CSV_HEADERS: &[&str] = &[
+    "id",
+    "org_name",
+    "report_id",
+    "begin",
+    "end",
+    "policy_type",
+    "policy_domain",
+    "mx_host",
+    "successful_sessions",
+    "failed_sessions",
+    "result_type",
+    "sending_mta_ip",
+    "receiving_mx_hostname",
+    "receiving_ip",
+    "failed_session_count",
+    "failure_reason_code",
+    "additional_information",
+];
+
+const ARF_CSV_HEADERS: &[&str] = &[
+    "id",
+    "feedback_type",
+    "arrival_date",
+    "source_ip",
+    "source_port",
+    "reporting_mta",
+    "original_mail_from",
+    "original_rcpt_to",
+    "reported_domain",
+    "reported_uri",
+    "incidents",
+    "auth_failure",
+    "delivery_result",
+    "dkim_domain",
+    "identity_alignment",
+    "user_agent",
+];
+
+impl ExportFormat {
+    pub fn label(&self, report_type: ReportType) -> &'static str {
+        match (self, report_type) {
+            (ExportFormat::Original, ReportType::Dmarc) => "XML",
+            (ExportFormat::Original, ReportType::Tls) => "JSON",
+            (ExportFormat::Original, ReportType::Arf) => "ARF",
+            (ExportFormat::Csv, _) => "CSV",
+        }
+    }
+}
+
+impl ExportedReports {
+    pub fn report_type(&self) -> ReportType {
+        match self {
+            ExportedReports::Dmarc(_) => ReportType::Dmarc,
+            ExportedReports::Tls(_) => ReportType::Tls,
+            ExportedReports::Arf(_) => ReportType::Arf,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ExportedReports::Dmarc(reports) => reports.len(),
+            ExportedReports::Tls(reports) => reports.len(),
+            ExportedReports::Arf(reports) => reports.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn download(&self, format: ExportFormat) -> Result<(), String> {
+        let file = self.serialize(format)?;
+        download_file(&file.name, file.content_type, file.contents)
+    }
+
+    // Original formats hold a single report, lists are exported as CSV
+    pub fn serialize(&self, format: ExportFormat) -> Result<ExportedFile, String> {
+        let report_type = self.report_type().as_str();
+
+        match (format, self) {
+            (ExportFormat::Csv, _) => Ok(ExportedFile {
+                name: format!(
+                    "{report_type}-reports-{}.csv",
+                    Utc::now().format("%Y%m%d-%H%M%S")
+                ),
+                content_type: "text/csv",
+                contents: self.to_csv(),
+            }),
+            (ExportFormat::Original, _) if self.len() != 1 => {
+                Err("Only a single report can be exported in its original format".to_string())
+            }
+            (ExportFormat::Original, ExportedReports::Dmarc(reports)) => {
+                let (id, report) = &reports[0];
+                Ok(ExportedFile {
+                    name: file_name(report_type, id, "xml"),
+                    content_type: "application/xml",
+                    contents: dmarc_to_xml(report),
+                })
+            }
+            (ExportFormat::Original, ExportedReports::Tls(reports)) => {
+                let (id, report) = &reports[0];
+                serde_json::to_string_pretty(report)
+                    .map(|contents| ExportedFile {
+                        name: file_name(report_type, id, "json"),
+                        content_type: "application/tlsrpt+json",
+                        contents,
+                    })
+                    .map_err(|err| err.to_string())
+            }
+            (ExportFormat::Original, ExportedReports::Arf(reports)) => {
+                let (id, report) = &reports[0];
+                Ok(ExportedFile {
+                    name: file_name(report_type, id, "txt"),
+                    content_type: "message/feedback-report",
+                    contents: arf_to_text(report),
+                })
+            }
+        }
+    }
+
+    // One row per DMARC record, per TLS-RPT failure detail (or policy) and per ARF report
+    fn to_csv(&self) -> String {
+        let mut csv = String::new();
+
+        match self {
+            ExportedReports::Dmarc(reports) => {
+                write_row(&mut csv, DMARC_CSV_HEADERS.iter().map(|h| h.to_string()));
+                for (id, report) in reports {
+                    let metadata = &report.report_metadata;
+                    for record in &report.record {
+                        let evaluated = &record.row.policy_evaluated;
+                        write_row(
+                            &mut csv,
+                            [
+                                id.clone(),
+                                metadata.org_name.clone(),
+                                metadata.report_id.clone(),
+                                format_timestamp(metadata.date_range.begin as i64),
+                                format_timestamp(metadata.date_range.end as i64),
+                                report.policy_published.domain.clone(),
+                                disposition(report.policy_published.p).to_string(),
+                                record
+                                    .row
+                                    .source_ip
+                                    .map(|ip| ip.to_string())
+                                    .unwrap_or_default(),
+                                record.row.count.to_string(),
+                                action_disposition(evaluated.disposition).to_string(),
+                                dmarc_result(evaluated.dkim).to_string(),
+                                dmarc_result(evaluated.spf).to_string(),
+                                evaluated
+                                    .reason
+                                    .iter()
+                                    .map(|reason| policy_override(reason.type_))
+                                    .collect::<Vec<_>>()
+                                    .join(";"),
+                                record.identifiers.header_from.clone(),
+                                record.identifiers.envelope_from.clone(),
+                                record.identifiers.envelope_to.clone().unwrap_or_default(),
+                                record
+                                    .auth_results
+                                    .dkim
+                                    .iter()
+                                    .map(|dkim| {
+                                        format!("{}:{}", dkim.domain, dkim_result(dkim.result))
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join(";"),
+                                record
+                                    .auth_results
+                                    .spf
+                                    .iter()
+                                    .map(|spf| format!("{}:{}", spf.domain, spf_result(spf.result)))
+                                    .collect::<Vec<_>>()
+                                    .join(";"),
+                            ],
+                        );
+                    }
+                }
+            }
+            ExportedReports::Tls(reports) => {
+                write_row(&mut csv, TLS_CSV_HEADERS.iter().map(|h| h.to_string()));
+                for (id, report) in reports {
+                    for policy in &report.policies {
+                        let mut failures = policy
+                            .failure_details
+                            .iter()
+                            .map(|failure| {
+                                [
+                                    serde_json::to_value(failure.result_type)
+                                        .ok()
+                                        .and_then(|value| value.as_str().map(str::to_string))
+                                        .unwrap_or_default(),
+                                    failure
+                                        .sending_mta_ip
+                                        .map(|ip| ip.to_string())
+                                        .unwrap_or_default(),
+                                    failure.receiving_mx_hostname.clone().unwrap_or_default(),
+                                    failure
+                                        .receiving_ip
+                                        .map(|ip| ip.to_string())
+                                        .unwrap_or_default(),
+                                    failure.failed_session_count.to_string(),
+                                    failure.failure_reason_code.clone().unwrap_or_default(),
+                                    failure.additional_information.clone().unwrap_or_default(),
+                                ]
+                            })
+                            .collect::<Vec<_>>();
+                        if failures.is_empty() {
+                            failures.push(Default::default());
+                        }
+
+                        for failure in failures {
+                            write_row(
+                                &mut csv,
+                                [
+                                    id.clone(),
+                                    report.organization_name.clone().unwrap_or_default(),
+                                    report.report_id.clone(),
+                                    report.date_range.start_datetime.to_rfc3339(),
+                                    report.date_range.end_datetime.to_rfc3339(),
+                                    serde_json::to_value(policy.policy.policy_type)
+                                        .ok()
+                                        .and_then(|value| value.as_str().map(str::to_string))
+                                        .unwrap_or_default(),
+                                    policy.policy.policy_domain.clone(),
+                                    policy.policy.mx_host.join(";"),
+                                    policy.summary.total_success.to_string(),
+                                    policy.summary.total_failure.to_string(),
+                                ]
+                                .into_iter()
+                                .chain(failure),
+                            );
+                        }
+                    }
+                }
+            }
+            ExportedReports::Arf(reports) => {
+                write_row(&mut csv, ARF_CSV_HEADERS.iter().map(|h| h.to_string()));
+                for (id, report) in reports {
+                    write_row(
+                        &mut csv,
+                        [
+                            id.clone(),
+                            report.feedback_type.id().to_string(),
+                            report
+                                .arrival_date
+                                .map(format_timestamp)
+                                .unwrap_or_default(),
+                            report
+                                .source_ip
+                                .map(|ip| ip.to_string())
+                                .unwrap_or_default(),
+                            if report.source_port > 0 {
+                                report.source_port.to_string()
+                            } else {
+                                String::new()
+                            },
+                            report.reporting_mta.clone().unwrap_or_default(),
+                            report.original_mail_from.clone().unwrap_or_default(),
+                            report.original_rcpt_to.clone().unwrap_or_default(),
+                            report.reported_domain.join(";"),
+                            report.reported_uri.join(";"),
+                            report.incidents.max(1).to_string(),
+                            auth_failure(report.auth_failure)
+                                .unwrap_or_default()
+                                .to_string(),
+                            delivery_result(report.delivery_result)
+                                .unwrap_or_default()
+                                .to_string(),
+                            report.dkim_domain.clone().unwrap_or_default(),
+                            identity_alignment(report.identity_alignment)
+                                .unwrap_or_default()
+                                .to_string(),
+                            report.user_agent.clone().unwrap_or_default(),
+                        ],
+                    );
+                }
+            }
+        }
+
+        csv
+    }
+}
+
+// RFC 7489 Appendix C schema
+pub fn dmarc_to_xml(report: &Report) -> String {
+    let mut xml = String::with_capacity(1024);
+    let metadata = &report.report_metadata;
+    let policy = &report.policy_published;
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feedback>\n");
+    if report.version > 0.0 {
+        let _ = writeln!(xml, "  <version>{:.1}</version>", report.version);
+    }
+    xml.push_str("  <report_metadata>\n");
+    write_element(&mut xml, 4, "org_name", &metadata.org_name);
+    write_element(&mut xml, 4, "email", &metadata.email);
+    if let Some(info) = &metadata.extra_contact_info {
+        write_element(&mut xml, 4, "extra_contact_info", info);
+    }
+    write_element(&mut xml, 4, "report_id", &metadata.report_id);
+    xml.push_str("    <date_range>\n");
+    write_element(&mut xml, 6, "begin", &metadata.date_range.begin.to_string());
+    write_element(&mut xml, 6, "end", &metadata.date_range.end.to_string());
+    xml.push_str("    </date_range>\n");
+    for error in &metadata.error {
+        write_element(&mut xml, 4, "error", error);
+    }
+    xml.push_str("  </report_metadata>\n  <policy_published>\n");
+    write_element(&mut xml, 4, "domain", &policy.domain);
+    if let Some(adkim) = alignment(policy.adkim) {
+        write_element(&mut xml, 4, "adkim", adkim);
+    }
+    if let Some(aspf) = alignment(policy.aspf) {
+        write_element(&mut xml, 4, "aspf", aspf);
+    }
+    write_element(&mut xml, 4, "p", disposition(policy.p));
+    if policy.sp != Disposition::Unspecified {
+        write_element(&mut xml, 4, "sp", disposition(policy.sp));
+    }
+    // The percentage is not kept by the server, 100 is the RFC default.
+    write_element(&mut xml, 4, "pct", "100");
+    if let Some(fo) = &policy.fo {
+        write_element(&mut xml, 4, "fo", fo);
+    }
+    xml.push_str("  </policy_published>\n");
+
+    for record in &report.record {
+        let evaluated = &record.row.policy_evaluated;
+        xml.push_str("  <record>\n    <row>\n");
+        if let Some(source_ip) = record.row.source_ip {
+            write_element(&mut xml, 6, "source_ip", &source_ip.to_string());
+        }
+        write_element(&mut xml, 6, "count", &record.row.count.to_string());
+        xml.push_str("      <policy_evaluated>\n");
+        write_element(
+            &mut xml,
+            8,
+            "disposition",
+            action_disposition(evaluated.disposition),
+        );
+        write_element(&mut xml, 8, "dkim", dmarc_result(evaluated.dkim));
+        write_element(&mut xml, 8, "spf", dmarc_result(evaluated.spf));
+        for reason in &evaluated.reason {
+            xml.push_str("        <reason>\n");
+            write_element(&mut xml, 10, "type", policy_override(reason.type_));
+            if let Some(comment) = &reason.comment {
+                write_element(&mut xml, 10, "comment", comment);
+            }
+            xml.push_str("        </reason>\n");
+        }
+        xml.push_str("      </policy_evaluated>\n    </row>\n    <identifiers>\n");
+        if let Some(envelope_to) = &record.identifiers.envelope_to {
+            write_element(&mut xml, 6, "envelope_to", envelope_to);
+        }
+        if !record.identifiers.envelope_from.is_empty() {
+            write_element(
+                &mut xml,
+                6,
+                "envelope_from",
+                &record.identifiers.envelope_from,
+            );
+        }
+        write_element(&mut xml, 6, "header_from", &record.identifiers.header_from);
+        xml.push_str("    </identifiers>\n    <auth_results>\n");
+        for dkim in &record.auth_results.dkim {
+            xml.push_str("      <dkim>\n");
+            write_element(&mut xml, 8, "domain", &dkim.domain);
+            if !dkim.selector.is_empty() {
+                write_element(&mut xml, 8, "selector", &dkim.selector);
+            }
+            write_element(&mut xml, 8, "result", dkim_result(dkim.result));
+            if let Some(human_result) = &dkim.human_result {
+                write_element(&mut xml, 8, "human_result", human_result);
+            }
+            xml.push_str("      </dkim>\n");
+        }
+        for spf in &record.auth_results.spf {
+            xml.push_str("      <spf>\n");
+            write_element(&mut xml, 8, "domain", &spf.domain);
+            if let Some(scope) = spf_scope(spf.scope) {
+                write_element(&mut xml, 8, "scope", scope);
+            }
+            write_element(&mut xml, 8, "result", spf_result(spf.result));
+            xml.push_str("      </spf>\n");
+        }
+        xml.push_str("    </auth_results>\n  </record>\n");
+    }
+    xml.push_str("</feedback>\n");
+
+    xml
+}
+
+// RFC 5965, extended by RFC 6591 for authentication failures
+pub fn arf_to_text(report: &Feedback) -> String {
+    let mut text = String::with_capacity(512);
+    let mut field = |name: &str, value: &str| {
+        if !value.is_empty() {
+            let _ = write!(text, "{name}: {value}\r\n");
+        }
+    };
+
+    field("Feedback-Type", report.feedback_type.id());
+    field(
+        "User-Agent",
+        report.user_agent.as_deref().unwrap_or_default(),
+    );
+    field("Version", &report.version.max(1).to_string());
+    if let Some(arrival_date) = report
+        .arrival_date
+        .and_then(|date| DateTime::from_timestamp(date, 0))
+    {
+        field("Arrival-Date", &arrival_date.to_rfc2822());
+    }
+    for result in &report.authentication_results {
+        field("Authentication-Results", result);
+    }
+    if report.incidents > 1 {
+        field("Incidents", &report.incidents.to_string());
+    }
+    field(
+        "Original-Envelope-Id",
+        report.original_envelope_id.as_deref().unwrap_or_default(),
+    );
+    field(
+        "Original-Mail-From",
+        report.original_mail_from.as_deref().unwrap_or_default(),
+    );
+    field(
+        "Original-Rcpt-To",
+        report.original_rcpt_to.as_deref().unwrap_or_default(),
+    );
+    for domain in &report.reported_domain {
+        field("Reported-Domain", domain);
+    }
+    for uri in &report.reported_uri {
+        field("Reported-URI", uri);
+    }
+    field(
+        "Reporting-MTA",
+        &report
+            .reporting_mta
+            .as_deref()
+            .map(|mta| {
+                if mta.contains(';') {
+                    mta.to_string()
+                } else {
+                    format!("dns; {mta}")
+                }
+            })
+            .unwrap_or_default(),
+    );
+    field(
+        "Source-IP",
+        &report
+            .source_ip
+            .map(|ip| ip.to_string())
+            .unwrap_or_default(),
+    );
+    if report.source_port > 0 {
+        field("Source-Port", &report.source_port.to_string());
+    }
+    field(
+        "Auth-Failure",
+        auth_failure(report.auth_failure).unwrap_or_default(),
+    );
+    field(
+        "Delivery-Result",
+        delivery_result(report.delivery_result).unwrap_or_default(),
+    );
+    field(
+        "DKIM-Domain",
+        report.dkim_domain.as_deref().unwrap_or_default(),
+    );
+    field(
+        "DKIM-Identity",
+        report.dkim_identity.as_deref().unwrap_or_default(),
+    );
+    field(
+        "DKIM-Selector",
+        report.dkim_selector.as_deref().unwrap_or_default(),
+    );
+    field(
+        "DKIM-Canonicalized-Header",
+        report
+            .dkim_canonicalized_header
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    field(
+        "DKIM-Canonicalized-Body",
+        report
+            .dkim_canonicalized_body
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    field(
+        "DKIM-Selector-DNS",
+        report.dkim_selector_dns.as_deref().unwrap_or_default(),
+    );
+    field(
+        "DKIM-ADSP-DNS",
+        report.dkim_adsp_dns.as_deref().unwrap_or_default(),
+    );
+    field("SPF-DNS", report.spf_dns.as_deref().unwrap_or_default());
+    field(
+        "Identity-Alignment",
+        identity_alignment(report.identity_alignment).unwrap_or_default(),
+    );
+
+    text
+}
+
+fn write_element(xml: &mut String, indent: usize, name: &str, value: &str) {
+    let _ = write!(xml, "{:indent$}<{name}>", "");
+    for ch in value.chars() {
+        match ch {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            _ => xml.push(ch),
+        }
+    }
+    let _ = writeln!(xml, "</{name}>");
+}
+
+fn file_name(report_type: &str, id: &str, extension: &str) -> String {
+    format!(
+        "{report_type}-{}.{extension}",
+        id.replace(
+            |ch: char| !ch.is_ascii_alphanumeric() && ch != '-' && ch != '_',
+            "-"
+        )
+    )
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|datetime| datetime.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn alignment(alignment: Alignment) -> Option<&'static str> {
+    match alignment {
+        Alignment::Relaxed => Some("r"),
+        Alignment::Strict => Some("s"),
+        Alignment::Unspecified => None,
+    }
+}
+
+fn disposition(disposition: Disposition) -> &'static str {
+    match disposition {
+        Disposition::Quarantine => "quarantine",
+        Disposition::Reject => "reject",
+        Disposition::None | Disposition::Unspecified => "none",
+    }
+}
+
+fn action_disposition(disposition: ActionDisposition) -> &'static str {
+    match disposition {
+        ActionDisposition::Pass => "pass",
+        ActionDisposition::Quarantine => "quarantine",
+        ActionDisposition::Reject => "reject",
+        ActionDisposition::None | ActionDisposition::Unspecified => "none",
+    }
+}
+
+fn dmarc_result(result: DmarcResult) -> &'static str {
+    match result {
+        DmarcResult::Pass => "pass",
+        DmarcResult::Fail | DmarcResult::Unspecified => "fail",
+    }
+}
+
+fn policy_override(reason: PolicyOverride) -> &'static str {
+    match reason {
+        PolicyOverride::Forwarded => "forwarded",
+        PolicyOverride::SampledOut => "sampled_out",
+        PolicyOverride::TrustedForwarder => "trusted_forwarder",
+        PolicyOverride::MailingList => "mailing_list",
+        PolicyOverride::LocalPolicy => "local_policy",
+        PolicyOverride::Other => "other",
+    }
+}
+
+fn dkim_result(result: DkimResult) -> &'static str {
+    match result {
+        DkimResult::None => "none",
+        DkimResult::Pass => "pass",
+        DkimResult::Fail => "fail",
+        DkimResult::Policy => "policy",
+        DkimResult::Neutral => "neutral",
+        DkimResult::TempError => "temperror",
+        DkimResult::PermError => "permerror",
+    }
+}
+
+fn spf_scope(scope: SPFDomainScope) -> Option<&'static str> {
+    match scope {
+        SPFDomainScope::Helo => Some("helo"),
+        SPFDomainScope::MailFrom => Some("mfrom"),
+        SPFDomainScope::Unspecified => None,
+    }
+}
+
+fn spf_result(result: SpfResult) -> &'static str {
+    match result {
+        SpfResult::None => "none",
+        SpfResult::Neutral => "neutral",
+        SpfResult::Pass => "pass",
+        SpfResult::Fail => "fail",
+        SpfResult::SoftFail => "softfail",
+        SpfResult::TempError => "temperror",
+        SpfResult::PermError => "permerror",
+    }
+}
+
+fn auth_failure(failure: AuthFailureType) -> Option<&'static str> {
+    match failure {
+        AuthFailureType::Adsp => Some("adsp"),
+        AuthFailureType::BodyHash => Some("bodyhash"),
+        AuthFailureType::Revoked => Some("revoked"),
+        AuthFailureType::Signature => Some("signature"),
+        AuthFailureType::Spf => Some("spf"),
+        AuthFailureType::Dmarc => Some("dmarc"),
+        AuthFailureType::Unspecified => None,
+    }
+}
+
+fn delivery_result(result: DeliveryResult) -> Option<&'static str> {
+    match result {
+        DeliveryResult::Delivered => Some("delivered"),
+        DeliveryResult::Spam => Some("spam"),
+        DeliveryResult::Policy => Some("policy"),
+        DeliveryResult::Reject => Some("reject"),
+        DeliveryResult::Other => Some("other"),
+        DeliveryResult::Unspecified => None,
+    }
+}
+
+fn identity_alignment(alignment: IdentityAlignment) -> Option<&'static str> {
+    match alignment {
+        IdentityAlignment::None => Some("none"),
+        IdentityAlignment::Spf => Some("spf"),
+        IdentityAlignment::Dkim => Some("dkim"),
+        IdentityAlignment::DkimSpf => Some("dkim,spf"),
+        IdentityAlignment::Unspecified => None,
+    }
+}
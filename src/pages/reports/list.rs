@@ -28,13 +28,13 @@ use std::collections::HashSet;
 use super::ReportType;
 use crate::{
     components::{
-        icon::{IconCancel, IconRefresh},
+        icon::{IconArrowDownTray, IconCancel, IconRefresh},
         list::{
             header::ColumnList,
             pagination::Pagination,
-            row::SelectItem,
-            toolbar::{SearchBox, ToolbarButton},
-            Footer, ListItem, ListSection, ListTable, ListTextItem, Toolbar, ZeroResults,
+            row::{SelectItem, HIGHLIGHT_ROW_CLASS},
+            toolbar::{FilterSelect, PollingControl, SearchBox, ToolbarButton},
+            Filters, Footer, ListItem, ListSection, ListTable, ListTextItem, Toolbar, ZeroResults,
         },
         messages::{
             alert::{use_alerts, Alert},
@@ -45,13 +45,19 @@ use crate::{
     },
     core::{
         http::{self, HttpRequest},
-        oauth::use_authorization,
+        oauth::{use_authorization, AuthToken},
+        polling::{use_polling, ChangeTracker},
         url::UrlBuilder,
     },
     pages::{
         maybe_plural,
         queue::reports::{Feedback, Report, TlsReport},
-        reports::{IncomingReport, IncomingReportSummary},
+        reports::{
+            export::{ExportFormat, ExportedReports},
+            fetch_report_ids, fetch_reports_by_id,
+            triage::{TriageBadge, TriageStore, FEEDBACK_TYPE_FILTERS, STATUS_FILTERS},
+            IncomingReport, IncomingReportSummary,
+        },
         FormatDateTime, List,
     },
 };
@@ -80,6 +86,11 @@ pub fn IncomingReportList() -> impl IntoView {
             })
         })
     });
+    let feedback_type =
+        create_memo(move |_| query.with(|q| q.get("type").cloned().filter(|typ| !typ.is_empty())));
+    let triage_status = create_memo(move |_| {
+        query.with(|q| q.get("status").cloned().filter(|status| !status.is_empty()))
+    });
     let params = use_params_map();
     let report_type = create_memo(move |_| {
         match params()
@@ -99,14 +110,32 @@ pub fn IncomingReportList() -> impl IntoView {
     let modal = use_modals();
     let selected = create_rw_signal::<HashSet<String>>(HashSet::new());
     provide_context(selected);
+    let triage = TriageStore::load();
 
     let reports = create_resource(
-        move || (page(), filter()),
-        move |(page, filter)| {
+        move || (page(), filter(), feedback_type(), triage_status()),
+        move |(page, filter, feedback_type, triage_status)| {
             let auth = auth.get_untracked();
             let report_type = report_type.get();
 
             async move {
+                if report_type == ReportType::Arf
+                    && (feedback_type.is_some() || triage_status.is_some())
+                {
+                    return fetch_filtered_arf(
+                        &auth,
+                        page,
+                        filter.as_deref(),
+                        feedback_type.as_deref(),
+                        |id| {
+                            triage_status
+                                .as_ref()
+                                .map_or(true, |s| s == triage.get_untracked(id).status.id())
+                        },
+                    )
+                    .await;
+                }
+
                 let ids = HttpRequest::get(format!("/api/reports/{}", report_type.as_str()))
                     .with_authorization(&auth)
                     .with_parameter("page", page.to_string())
@@ -161,6 +190,7 @@ pub fn IncomingReportList() -> impl IntoView {
                     .await
                 {
                     Ok(true) => {
+                        triage.remove(&id);
                         total_deleted += 1;
                     }
                     Ok(false) | Err(http::Error::NotFound) => {}
@@ -181,7 +211,100 @@ pub fn IncomingReportList() -> impl IntoView {
         }
     });
 
+    let poller = use_polling("incoming-reports", move || reports.refetch());
+    poller.watch(reports);
+    let changes = ChangeTracker::<String, ()>::new();
+
+    let export_progress = create_rw_signal(None::<(usize, usize)>);
+    let export_action = create_action(move |_: &()| {
+        let auth = auth.get();
+        let report_type = report_type.get();
+        let filter = filter.get();
+        let feedback_type = feedback_type.get();
+        let triage_status = triage_status.get();
+
+        async move {
+            export_progress.set(Some((0, 0)));
+            let on_progress = |loaded, total| export_progress.set(Some((loaded, total)));
+            let result =
+                match fetch_report_ids(&auth, report_type, filter.as_deref(), |_| true).await {
+                    Ok(ids) => match report_type {
+                        ReportType::Dmarc => {
+                            fetch_reports_by_id::<Report>(&auth, report_type, ids, on_progress)
+                                .await
+                                .map(|reports| {
+                                    ExportedReports::Dmarc(
+                                        reports.into_iter().map(|(id, r)| (id, r.report)).collect(),
+                                    )
+                                })
+                        }
+                        ReportType::Tls => {
+                            fetch_reports_by_id::<TlsReport>(&auth, report_type, ids, on_progress)
+                                .await
+                                .map(|reports| {
+                                    ExportedReports::Tls(
+                                        reports.into_iter().map(|(id, r)| (id, r.report)).collect(),
+                                    )
+                                })
+                        }
+                        ReportType::Arf => {
+                            fetch_reports_by_id::<Feedback>(&auth, report_type, ids, on_progress)
+                                .await
+                                .map(|reports| {
+                                    ExportedReports::Arf(
+                                        reports
+                                            .into_iter()
+                                            .filter(|(id, r)| {
+                                                feedback_type.as_ref().map_or(true, |t| {
+                                                    t == r.report.feedback_type.id()
+                                                }) && triage_status.as_ref().map_or(true, |s| {
+                                                    s == triage.get_untracked(id).status.id()
+                                                })
+                                            })
+                                            .map(|(id, r)| (id, r.report))
+                                            .collect(),
+                                    )
+                                })
+                        }
+                    },
+                    Err(err) => Err(err),
+                };
+            export_progress.set(None);
+
+            match result {
+                Ok(reports) if !reports.is_empty() => {
+                    if let Err(err) = reports.download(ExportFormat::Csv) {
+                        alert.set(Alert::error(format!("Failed to download file: {err}")));
+                    }
+                }
+                Ok(_) => {
+                    alert.set(Alert::warning("There are no reports to export"));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+    let export_button_text = Signal::derive(move || match export_progress.get() {
+        Some((loaded, total)) => format!("Exporting… {loaded}/{total}"),
+        None => "Export CSV".to_string(),
+    });
+
     let total_results = create_rw_signal(None::<u32>);
+    let set_filters = move |feedback_type: Option<String>, triage_status: Option<String>| {
+        use_navigate()(
+            &UrlBuilder::new(format!("/manage/reports/{}", report_type.get().as_str()))
+                .with_optional_parameter("filter", filter())
+                .with_optional_parameter("type", feedback_type.filter(|v| !v.is_empty()))
+                .with_optional_parameter("status", triage_status.filter(|v| !v.is_empty()))
+                .finish(),
+            Default::default(),
+        );
+    };
 
     let title = create_memo(move |_| {
         match report_type.get() {
@@ -218,6 +341,8 @@ pub fn IncomingReportList() -> impl IntoView {
                         }
                     />
 
+                    <PollingControl poller/>
+
                     <ToolbarButton
                         text="Reload"
 
@@ -230,6 +355,19 @@ pub fn IncomingReportList() -> impl IntoView {
                         <IconRefresh/>
                     </ToolbarButton>
 
+                    <ToolbarButton
+                        text=export_button_text
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            if export_progress.get().is_none() {
+                                export_action.dispatch(());
+                            }
+                        })
+                    >
+
+                        <IconArrowDownTray/>
+                    </ToolbarButton>
+
                     <ToolbarButton
                         text=Signal::derive(move || {
                             let ns = selected.get().len();
@@ -266,6 +404,23 @@ pub fn IncomingReportList() -> impl IntoView {
 
                 </Toolbar>
 
+                <Filters slot hide=Signal::derive(move || report_type.get() != ReportType::Arf)>
+                    <FilterSelect
+                        value=feedback_type
+                        options=FEEDBACK_TYPE_FILTERS
+                        on_change=move |value: String| {
+                            set_filters(Some(value), triage_status.get());
+                        }
+                    />
+                    <FilterSelect
+                        value=triage_status
+                        options=STATUS_FILTERS
+                        on_change=move |value: String| {
+                            set_filters(feedback_type.get(), Some(value));
+                        }
+                    />
+                </Filters>
+
                 <Transition fallback=Skeleton>
                     {move || match reports.get() {
                         None => None,
@@ -280,6 +435,18 @@ pub fn IncomingReportList() -> impl IntoView {
                         }
                         Some(Ok(reports)) if !reports.items.is_empty() => {
                             total_results.set(Some(reports.total as u32));
+                            changes
+                                .update(
+                                    (
+                                        page.get_untracked(),
+                                        filter.get_untracked(),
+                                        report_type.get_untracked().as_str(),
+                                    ),
+                                    reports
+                                        .items
+                                        .iter()
+                                        .map(|report| (report.id().to_string(), ())),
+                                );
                             let reports_ = reports.clone();
                             let headers = match report_type.get() {
                                 ReportType::Dmarc => {
@@ -310,6 +477,7 @@ pub fn IncomingReportList() -> impl IntoView {
                                         "Date".to_string(),
                                         "Domains".to_string(),
                                         "Incidents".to_string(),
+                                        "Status".to_string(),
                                         "".to_string(),
                                     ]
                                 }
@@ -333,7 +501,11 @@ pub fn IncomingReportList() -> impl IntoView {
                                             key=|report| report.id().to_string()
                                             let:report
                                         >
-                                            <ReportItem report=report/>
+                                            <ReportItem
+                                                highlight=changes.is_changed(&report.id().to_string())
+                                                triage=triage
+                                                report
+                                            />
                                         </For>
 
                                     </ColumnList>
@@ -370,6 +542,8 @@ pub fn IncomingReportList() -> impl IntoView {
                                     )
                                     .with_parameter("page", page.to_string())
                                     .with_optional_parameter("filter", filter())
+                                    .with_optional_parameter("type", feedback_type())
+                                    .with_optional_parameter("status", triage_status())
                                     .finish(),
                                 Default::default(),
                             );
@@ -387,8 +561,64 @@ enum Item {
     Double((String, String)),
 }
 
+// Feedback type and triage status are not known to the server, so the
+// matching reports are collected across all pages and paginated here.
+async fn fetch_filtered_arf(
+    auth: &AuthToken,
+    page: u32,
+    filter: Option<&str>,
+    feedback_type: Option<&str>,
+    keep: impl Fn(&str) -> bool,
+) -> http::Result<List<IncomingReportSummary>> {
+    let ids = fetch_report_ids(auth, ReportType::Arf, filter, keep).await?;
+    let offset = ((page - 1) * PAGE_SIZE) as usize;
+
+    if let Some(feedback_type) = feedback_type {
+        let reports = fetch_reports_by_id::<Feedback>(auth, ReportType::Arf, ids, |_, _| ())
+            .await?
+            .into_iter()
+            .filter(|(_, report)| report.report.feedback_type.id() == feedback_type)
+            .collect::<Vec<_>>();
+        Ok(List {
+            total: reports.len() as u64,
+            items: reports
+                .into_iter()
+                .skip(offset)
+                .take(PAGE_SIZE as usize)
+                .map(|(id, report)| IncomingReportSummary::arf(id, report))
+                .collect(),
+        })
+    } else {
+        let total = ids.len() as u64;
+        let ids = ids
+            .into_iter()
+            .skip(offset)
+            .take(PAGE_SIZE as usize)
+            .collect();
+        Ok(List {
+            total,
+            items: fetch_reports_by_id::<Feedback>(auth, ReportType::Arf, ids, |_, _| ())
+                .await?
+                .into_iter()
+                .map(|(id, report)| IncomingReportSummary::arf(id, report))
+                .collect(),
+        })
+    }
+}
+
 #[component]
-fn ReportItem(report: IncomingReportSummary) -> impl IntoView {
+fn ReportItem(
+    report: IncomingReportSummary,
+    triage: TriageStore,
+    #[prop(optional)] highlight: bool,
+) -> impl IntoView {
+    let triage_status = match &report {
+        IncomingReportSummary::Arf { id, .. } => {
+            let id = id.clone();
+            Some(Signal::derive(move || triage.get(&id).status))
+        }
+        _ => None,
+    };
     let (show_url, item_id, columns) = match report {
         IncomingReportSummary::Dmarc {
             id,
@@ -515,7 +745,7 @@ fn ReportItem(report: IncomingReportSummary) -> impl IntoView {
         .collect_view();
 
     view! {
-        <tr>
+        <tr class=highlight.then_some(HIGHLIGHT_ROW_CLASS)>
             <ListItem>
                 <label class="flex">
                     <SelectItem item_id=item_id/>
@@ -526,6 +756,15 @@ fn ReportItem(report: IncomingReportSummary) -> impl IntoView {
 
             {columns}
 
+            {triage_status
+                .map(|status| {
+                    view! {
+                        <ListItem>
+                            <TriageBadge status=status/>
+                        </ListItem>
+                    }
+                })}
+
             <ListItem subclass="px-6 py-1.5">
                 <a
                     class="inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
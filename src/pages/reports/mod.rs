@@ -22,12 +22,25 @@
 */
 
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::queue::reports::{ActionDisposition, Feedback, FeedbackType, Report, TlsReport};
+use crate::core::{
+    http::{self, HttpRequest},
+    oauth::AuthToken,
+};
 
+use super::{
+    queue::reports::{ActionDisposition, Feedback, FeedbackType, Report, TlsReport},
+    List,
+};
+
+const FETCH_PAGE_SIZE: u32 = 100;
+
+pub mod analytics;
 pub mod display;
+pub mod export;
 pub mod list;
+pub mod triage;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReportType {
@@ -177,6 +190,57 @@ impl IncomingReportSummary {
     }
 }
 
+pub async fn fetch_report_ids(
+    auth: &AuthToken,
+    report_type: ReportType,
+    filter: Option<&str>,
+    keep: impl Fn(&str) -> bool,
+) -> http::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    let mut page = 1;
+    loop {
+        let list = HttpRequest::get(format!("/api/reports/{}", report_type.as_str()))
+            .with_authorization(auth)
+            .with_parameter("page", page.to_string())
+            .with_parameter("limit", FETCH_PAGE_SIZE.to_string())
+            .with_optional_parameter("filter", filter)
+            .send::<List<String>>()
+            .await?;
+        let is_last = list.items.len() < FETCH_PAGE_SIZE as usize
+            || (page * FETCH_PAGE_SIZE) as u64 >= list.total;
+        ids.extend(list.items.into_iter().filter(|id| keep(id)));
+        if is_last {
+            return Ok(ids);
+        }
+        page += 1;
+    }
+}
+
+// Skips the reports deleted in the meantime
+pub async fn fetch_reports_by_id<T: DeserializeOwned>(
+    auth: &AuthToken,
+    report_type: ReportType,
+    ids: Vec<String>,
+    mut on_progress: impl FnMut(usize, usize),
+) -> http::Result<Vec<(String, IncomingReport<T>)>> {
+    let mut reports = Vec::with_capacity(ids.len());
+    let total = ids.len();
+    on_progress(0, total);
+    for (num, id) in ids.into_iter().enumerate() {
+        if let Some(report) =
+            HttpRequest::get(format!("/api/reports/{}/{}", report_type.as_str(), id))
+                .with_authorization(auth)
+                .try_send::<IncomingReport<T>>()
+                .await?
+        {
+            reports.push((id, report));
+        }
+        on_progress(num + 1, total);
+    }
+
+    Ok(reports)
+}
+
 pub(super) fn parse_report_date(id: &str) -> DateTime<Utc> {
     DateTime::from_timestamp(
         id.split_once('_')
@@ -0,0 +1,385 @@
+/*
+ * Copyright (c) 2024, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Web-based Admin.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::HashMap;
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use leptos_router::use_navigate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::button::Button,
+        icon::IconUserCircle,
+        messages::alert::Alert,
+        report::{ReportItem, ReportSection, ReportTextValue},
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::{use_authorization, AuthToken},
+    },
+    pages::{
+        directory::{Principal, PrincipalType},
+        List,
+    },
+    STATE_ARF_TRIAGE_KEY,
+};
+
+const MAX_PRINCIPALS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TriageStatus {
+    #[default]
+    New,
+    Investigating,
+    Resolved,
+    FalsePositive,
+}
+
+pub const TRIAGE_STATUSES: &[(&str, &str)] = &[
+    ("new", "New"),
+    ("investigating", "Investigating"),
+    ("resolved", "Resolved"),
+    ("false-positive", "False positive"),
+];
+
+pub const STATUS_FILTERS: &[(&str, &str)] = &[
+    ("", "Any status"),
+    ("new", "New"),
+    ("investigating", "Investigating"),
+    ("resolved", "Resolved"),
+    ("false-positive", "False positive"),
+];
+
+pub const FEEDBACK_TYPE_FILTERS: &[(&str, &str)] = &[
+    ("", "Any type"),
+    ("abuse", "Abuse"),
+    ("auth-failure", "Authentication Failure"),
+    ("fraud", "Fraud"),
+    ("not-spam", "Not Spam"),
+    ("virus", "Virus"),
+    ("other", "Other"),
+];
+
+// Only kept in the browser, the server has no notion of it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Triage {
+    pub status: TriageStatus,
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TriageStore {
+    entries: RwSignal<HashMap<String, Triage>>,
+}
+
+impl TriageStatus {
+    pub fn id(&self) -> &'static str {
+        match self {
+            TriageStatus::New => "new",
+            TriageStatus::Investigating => "investigating",
+            TriageStatus::Resolved => "resolved",
+            TriageStatus::FalsePositive => "false-positive",
+        }
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        match id {
+            "new" => Some(TriageStatus::New),
+            "investigating" => Some(TriageStatus::Investigating),
+            "resolved" => Some(TriageStatus::Resolved),
+            "false-positive" => Some(TriageStatus::FalsePositive),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            TriageStatus::New => Color::Blue,
+            TriageStatus::Investigating => Color::Yellow,
+            TriageStatus::Resolved => Color::Green,
+            TriageStatus::FalsePositive => Color::Gray,
+        }
+    }
+}
+
+impl std::fmt::Display for TriageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriageStatus::New => f.write_str("New"),
+            TriageStatus::Investigating => f.write_str("Investigating"),
+            TriageStatus::Resolved => f.write_str("Resolved"),
+            TriageStatus::FalsePositive => f.write_str("False positive"),
+        }
+    }
+}
+
+impl TriageStore {
+    pub fn load() -> Self {
+        TriageStore {
+            entries: create_rw_signal(LocalStorage::get(STATE_ARF_TRIAGE_KEY).unwrap_or_default()),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Triage {
+        self.entries
+            .with(|entries| entries.get(id).cloned().unwrap_or_default())
+    }
+
+    pub fn get_untracked(&self, id: &str) -> Triage {
+        self.entries
+            .with_untracked(|entries| entries.get(id).cloned().unwrap_or_default())
+    }
+
+    pub fn set(&self, id: &str, triage: Triage) {
+        self.entries.update(|entries| {
+            if triage != Triage::default() {
+                entries.insert(id.to_string(), triage);
+            } else {
+                entries.remove(id);
+            }
+            Self::save(entries);
+        });
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.entries.update(|entries| {
+            if entries.remove(id).is_some() {
+                Self::save(entries);
+            }
+        });
+    }
+
+    fn save(entries: &HashMap<String, Triage>) {
+        if let Err(err) = LocalStorage::set(STATE_ARF_TRIAGE_KEY, entries) {
+            log::error!("Failed to save report triage to local storage: {}", err);
+        }
+    }
+}
+
+#[component]
+pub fn TriageBadge(#[prop(into)] status: MaybeSignal<TriageStatus>) -> impl IntoView {
+    move || {
+        let status = status.get();
+        view! { <Badge color=status.color()>{status.to_string()}</Badge> }
+    }
+}
+
+#[component]
+pub fn ArfTriage(report_id: String, sender: Option<String>) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = expect_context::<RwSignal<Alert>>();
+    let store = TriageStore::load();
+    let triage = store.get_untracked(&report_id);
+    let status = create_rw_signal(triage.status);
+    let notes = create_rw_signal(triage.notes);
+    let report_id = store_value(report_id);
+    let has_sender = sender.is_some();
+    let sender = store_value(sender.unwrap_or_default());
+
+    let save = move || {
+        store.set(
+            &report_id.get_value(),
+            Triage {
+                status: status.get_untracked(),
+                notes: notes.get_untracked(),
+            },
+        );
+    };
+
+    let find_sender = create_action(move |_: &()| {
+        let auth = auth.get();
+        let address = sender.get_value();
+
+        async move {
+            match find_principals(&auth, &address).await {
+                Ok(principals) => Some(principals),
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                    None
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                    None
+                }
+            }
+        }
+    });
+    let principals = find_sender.value();
+
+    view! {
+        <ReportSection title="Triage">
+            <ReportItem label="Status">
+                <div class="flex items-center gap-x-3">
+                    <TriageBadge status=status/>
+                    <select
+                        class="py-2 px-3 pe-9 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                        on:change=move |ev| {
+                            if let Some(value) = TriageStatus::parse(&event_target_value(&ev)) {
+                                status.set(value);
+                                save();
+                            }
+                        }
+                    >
+
+                        {TRIAGE_STATUSES
+                            .iter()
+                            .map(|(id, label)| {
+                                view! {
+                                    <option value=*id selected=move || status.get().id() == *id>
+                                        {*label}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+
+                    </select>
+                </div>
+            </ReportItem>
+            <ReportItem label="Notes">
+                <textarea
+                    class="py-3 px-4 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
+                    rows=4
+                    placeholder="Findings, actions taken, ticket references..."
+                    prop:value=move || notes.get()
+                    on:change=move |ev| {
+                        notes.set(event_target_value(&ev));
+                        save();
+                    }
+                ></textarea>
+            </ReportItem>
+            <ReportItem label="Sender" hide=!has_sender>
+                <div class="flex items-center gap-x-3">
+                    <ReportTextValue value=sender.get_value()/>
+                    <Button
+                        text=Signal::derive(move || {
+                            if find_sender.pending().get() {
+                                "Searching...".to_string()
+                            } else {
+                                "Find sender".to_string()
+                            }
+                        })
+
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            if !find_sender.pending().get_untracked() {
+                                find_sender.dispatch(());
+                            }
+                        })
+                    >
+
+                        <IconUserCircle/>
+                    </Button>
+                </div>
+
+                {move || {
+                    principals
+                        .get()
+                        .flatten()
+                        .map(|principals| {
+                            if principals.is_empty() {
+                                view! {
+                                    <p class="mt-2 text-sm text-gray-500">
+                                        No local account uses this address.
+                                    </p>
+                                }
+                                    .into_view()
+                            } else {
+                                principals
+                                    .into_iter()
+                                    .map(|principal| view! { <PrincipalLink principal=principal/> })
+                                    .collect_view()
+                            }
+                        })
+                }}
+
+            </ReportItem>
+        </ReportSection>
+    }
+}
+
+#[component]
+fn PrincipalLink(principal: Principal) -> impl IntoView {
+    let name = principal.name.unwrap_or_default();
+    let description = principal.description.unwrap_or_else(|| name.clone());
+    let typ = principal.typ.unwrap_or(PrincipalType::Individual);
+    let manage_url = matches!(
+        typ,
+        PrincipalType::Individual | PrincipalType::Group | PrincipalType::List
+    )
+    .then(|| format!("/manage/directory/{}/{}/edit", typ.resource_name(), name));
+
+    view! {
+        <div class="mt-2 flex items-center gap-x-3 text-sm">
+            <span class="font-semibold text-gray-800 dark:text-gray-200">{description}</span>
+            <span class="text-gray-500">{principal.emails.join(", ")}</span>
+
+            {manage_url
+                .map(|url| {
+                    view! {
+                        <a
+                            class="text-blue-600 decoration-2 hover:underline font-medium"
+                            href=url
+                        >
+                            Manage
+                        </a>
+                    }
+                })}
+
+        </div>
+    }
+}
+
+// The directory search is a substring match, so only exact addresses are kept
+async fn find_principals(auth: &AuthToken, address: &str) -> http::Result<Vec<Principal>> {
+    let address = address.trim().trim_start_matches('<').trim_end_matches('>');
+    let names = HttpRequest::get("/api/principal")
+        .with_authorization(auth)
+        .with_parameter("filter", address)
+        .with_parameter("limit", MAX_PRINCIPALS.to_string())
+        .send::<List<String>>()
+        .await?;
+    let mut principals = Vec::with_capacity(names.items.len());
+
+    for name in names.items {
+        if let Some(principal) = HttpRequest::get(format!("/api/principal/{name}"))
+            .with_authorization(auth)
+            .try_send::<Principal>()
+            .await?
+        {
+            if principal
+                .emails
+                .iter()
+                .any(|email| email.eq_ignore_ascii_case(address))
+            {
+                principals.push(principal);
+            }
+        }
+    }
+
+    Ok(principals)
+}